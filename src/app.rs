@@ -6,6 +6,7 @@ use winit::event::WindowEvent;
 use winit::event_loop::ActiveEventLoop;
 use winit::window::{Window, WindowId};
 
+use crate::spread::SPREAD;
 use crate::wgpu_ctx::WgpuCtx;
 
 #[derive(Default)]
@@ -17,8 +18,13 @@ pub struct App<'window, 'font> {
 impl<'window, 'font> ApplicationHandler for App<'window, 'font> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_none() {
+            //画价差时在标题上显示价差的名称
+            let title = match SPREAD.as_ref() {
+                Some(spread) => format!("VnpyRS极速K线图表 - {}", spread.name),
+                None => "VnpyRS极速K线图表".to_string(),
+            };
             let win_attr = Window::default_attributes()
-                .with_title("VnpyRS极速K线图表")
+                .with_title(title)
                 .with_min_inner_size(PhysicalSize::new(800, 600));
             // use Arc.
             let window = Arc::new(
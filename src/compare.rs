@@ -0,0 +1,105 @@
+use std::sync::LazyLock;
+
+use crate::config::{config_error, parse_color, CONFIG};
+use crate::manager::{data_file_name, data_file_path, read_history_file, HISTORY};
+
+//对比品种的画法：按第一根可见K线换算成涨跌幅对齐到主图，或者画在右侧的副坐标轴上
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareMode {
+    Percent,
+    Axis,
+}
+
+impl CompareMode {
+    //按C切换：涨跌幅、副坐标轴、不画
+    pub fn next(mode: Option<Self>) -> Option<Self> {
+        match mode {
+            Some(CompareMode::Percent) => Some(CompareMode::Axis),
+            Some(CompareMode::Axis) => None,
+            None => Some(CompareMode::Percent),
+        }
+    }
+
+    pub fn name(mode: Option<Self>) -> &'static str {
+        match mode {
+            Some(CompareMode::Percent) => "涨跌幅",
+            Some(CompareMode::Axis) => "副坐标轴",
+            None => "不画",
+        }
+    }
+}
+
+//可见范围内对比品种和主图的对应关系
+//Percent为第一根两边都有数据的K线上对比品种的收盘价和主图的收盘价，Axis为对比品种可见范围的最低和最高收盘价
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareView {
+    Percent { base: f64, price: f64 },
+    Axis { min: f64, max: f64 },
+}
+
+//对比品种，收盘价按时间对齐到主图的K线，主图有而对比品种没有的K线为NaN
+pub struct Compare {
+    pub name: String,
+    pub close_price: Vec<f64>,
+    pub color: [f32; 4],
+    pub mode: CompareMode,
+}
+
+//配置在[compare]节：file为K线文件，格式同history.dat；name默认为文件名；color默认为#FFA040；mode为percent或axis，默认为percent
+pub static COMPARE: LazyLock<Option<Compare>> = LazyLock::new(|| {
+    let file = CONFIG.get("compare", "file")?;
+    let path = data_file_path(file);
+    if !std::path::Path::new(&path).is_file() {
+        config_error(format!("对比品种文件{}不存在，不画对比品种", path));
+        return None;
+    }
+    let history = read_history_file(&path);
+    let mut close_price = vec![f64::NAN; HISTORY.datetime.len()];
+    for (datetime, price) in history.datetime.iter().zip(&history.close_price) {
+        if let Ok(ix) = HISTORY.datetime.binary_search(datetime) {
+            close_price[ix] = *price;
+        }
+    }
+    let name = CONFIG
+        .get("compare", "name")
+        .map_or_else(|| data_file_name(file), str::to_string);
+    let color = CONFIG.parse_or("compare", "color", [1.0, 0.63, 0.25, 1.0], parse_color);
+    let mode = CONFIG.parse_or("compare", "mode", CompareMode::Percent, |text| match text {
+        "percent" => Some(CompareMode::Percent),
+        "axis" => Some(CompareMode::Axis),
+        _ => None,
+    });
+    if close_price.iter().all(|price| price.is_nan()) {
+        log::warn!("对比品种{}和主图没有相同时间的K线", path);
+    }
+    Some(Compare {
+        name,
+        close_price,
+        color,
+        mode,
+    })
+});
+
+impl Compare {
+    pub fn view(&self, mode: CompareMode, left: usize, right: usize) -> Option<CompareView> {
+        let visible = &self.close_price[left..=right];
+        match mode {
+            CompareMode::Percent => {
+                let ix = visible.iter().position(|price| price.is_finite())?;
+                Some(CompareView::Percent {
+                    base: visible[ix],
+                    price: HISTORY.close_price[left + ix],
+                })
+            }
+            CompareMode::Axis => {
+                let (min, max) = visible
+                    .iter()
+                    .filter(|price| price.is_finite())
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), price| {
+                        (min.min(*price), max.max(*price))
+                    });
+                min.is_finite().then_some(CompareView::Axis { min, max })
+            }
+        }
+    }
+}
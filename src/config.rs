@@ -0,0 +1,115 @@
+use std::sync::{LazyLock, Mutex};
+
+use crate::manager::get_home_path;
+
+//图表配置，读取家目录下的vnpyrs/chart.ini，文件不存在时全部使用默认值
+//格式为ini：[节名]开始一节，每行“键 = 值”，#或;开头的行是注释，同一节内键可以重复
+#[derive(Default)]
+pub struct Config {
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Config {
+    //格式错误的行跳过，返回每个错误
+    pub fn parse(text: &str) -> (Config, Vec<String>) {
+        let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        let mut errors = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                sections.push((line[1..line.len() - 1].trim().to_lowercase(), Vec::new()));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                errors.push(format!("第{}行缺少“=”", i + 1));
+                continue;
+            };
+            let Some(section) = sections.last_mut() else {
+                errors.push(format!("第{}行不在任何节内", i + 1));
+                continue;
+            };
+            section
+                .1
+                .push((key.trim().to_string(), value.trim().to_string()));
+        }
+        (Config { sections }, errors)
+    }
+
+    //节不存在时返回None，用来区分“没有配置”和“配置为空”
+    pub fn section(&self, name: &str) -> Option<&[(String, String)]> {
+        self.sections
+            .iter()
+            .find(|(section, _)| section == name)
+            .map(|(_, items)| items.as_slice())
+    }
+
+    //节内键对应的值，键重复时取最后一个
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.section(section)?
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    //没有配置时为default，解析失败时记下错误，也用default
+    pub fn parse_or<T>(
+        &self,
+        section: &str,
+        key: &str,
+        default: T,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> T {
+        let Some(text) = self.get(section, key) else {
+            return default;
+        };
+        parse(text).unwrap_or_else(|| {
+            config_error(format!("配置“{} = {}”格式错误，已改用默认值", key, text));
+            default
+        })
+    }
+}
+
+pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
+    let path = format!("{}/vnpyrs/chart.ini", get_home_path());
+    match std::fs::read_to_string(&path) {
+        Ok(text) => {
+            let (config, errors) = Config::parse(&text);
+            for err in errors {
+                config_error(format!("配置文件{}{}，已跳过", path, err));
+            }
+            config
+        }
+        Err(_) => Config::default(),
+    }
+});
+
+//配置写错时不退出，记下错误，图表在状态栏中显示，调用方在信息里写明出错后怎么处理
+static CONFIG_ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn config_error(message: String) {
+    log::warn!("{}", message);
+    CONFIG_ERRORS.lock().unwrap().push(message);
+}
+
+//取出还没显示过的配置错误，有的配置在切换画法时才读取，所以每次绘制前都要取一次
+pub fn take_config_errors() -> Vec<String> {
+    std::mem::take(&mut *CONFIG_ERRORS.lock().unwrap())
+}
+
+//解析#RRGGBB或#RRGGBBAA格式的颜色
+pub fn parse_color(text: &str) -> Option<[f32; 4]> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+    let mut color = [1.0f32; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        let byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        *channel = byte as f32 / 255.0;
+    }
+    Some(color)
+}
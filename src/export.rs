@@ -0,0 +1,306 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::manager::{get_home_path, TradePair, TRADE_PAIRS};
+
+//逐笔交易对的统计，盈亏按价格计算，未乘合约乘数
+#[derive(Default)]
+pub struct TradeStatistics {
+    pub trade_count: usize,
+    pub winning_count: usize,
+    pub losing_count: usize,
+    pub winning_rate: f64,
+    pub total_pnl: f64,
+    pub gross_profit: f64,
+    pub gross_loss: f64,
+    pub profit_factor: Option<f64>, //没有亏损的交易时为None
+    pub average_pnl: f64,
+    pub average_profit: f64,
+    pub average_loss: f64,
+    pub max_profit: Option<f64>, //没有盈利的交易时为None
+    pub max_loss: Option<f64>,   //没有亏损的交易时为None
+    pub average_holding_bars: f64,
+    pub max_consecutive_wins: usize,
+    pub max_consecutive_losses: usize,
+}
+
+impl TradeStatistics {
+    pub fn new(trade_pairs: &[TradePair]) -> Self {
+        let mut statistics = TradeStatistics {
+            trade_count: trade_pairs.len(),
+            ..Default::default()
+        };
+        if trade_pairs.is_empty() {
+            return statistics;
+        }
+        let mut consecutive_wins = 0;
+        let mut consecutive_losses = 0;
+        let mut total_holding_bars = 0;
+        for d in trade_pairs {
+            let pnl = d.pnl();
+            statistics.total_pnl += pnl;
+            total_holding_bars += d.holding_bars();
+            if pnl > 0.0 {
+                statistics.winning_count += 1;
+                statistics.gross_profit += pnl;
+                statistics.max_profit = Some(statistics.max_profit.map_or(pnl, |max| max.max(pnl)));
+                consecutive_wins += 1;
+                consecutive_losses = 0;
+            } else if pnl < 0.0 {
+                statistics.losing_count += 1;
+                statistics.gross_loss += pnl;
+                statistics.max_loss = Some(statistics.max_loss.map_or(pnl, |min| min.min(pnl)));
+                consecutive_losses += 1;
+                consecutive_wins = 0;
+            } else {
+                consecutive_wins = 0;
+                consecutive_losses = 0;
+            }
+            statistics.max_consecutive_wins = statistics.max_consecutive_wins.max(consecutive_wins);
+            statistics.max_consecutive_losses =
+                statistics.max_consecutive_losses.max(consecutive_losses);
+        }
+        let count = trade_pairs.len() as f64;
+        statistics.winning_rate = statistics.winning_count as f64 / count;
+        if statistics.losing_count > 0 {
+            statistics.profit_factor = Some(statistics.gross_profit / -statistics.gross_loss);
+        }
+        statistics.average_pnl = statistics.total_pnl / count;
+        if statistics.winning_count > 0 {
+            statistics.average_profit = statistics.gross_profit / statistics.winning_count as f64;
+        }
+        if statistics.losing_count > 0 {
+            statistics.average_loss = statistics.gross_loss / statistics.losing_count as f64;
+        }
+        statistics.average_holding_bars = total_holding_bars as f64 / count;
+        statistics
+    }
+
+    //算不出来的项为None，CSV中留空，JSON中写成null
+    fn items(&self) -> Vec<(&'static str, Option<f64>)> {
+        vec![
+            ("trade_count", Some(self.trade_count as f64)),
+            ("winning_count", Some(self.winning_count as f64)),
+            ("losing_count", Some(self.losing_count as f64)),
+            ("winning_rate", Some(self.winning_rate)),
+            ("total_pnl", Some(self.total_pnl)),
+            ("gross_profit", Some(self.gross_profit)),
+            ("gross_loss", Some(self.gross_loss)),
+            ("profit_factor", self.profit_factor),
+            ("average_pnl", Some(self.average_pnl)),
+            ("average_profit", Some(self.average_profit)),
+            ("average_loss", Some(self.average_loss)),
+            ("max_profit", self.max_profit),
+            ("max_loss", self.max_loss),
+            ("average_holding_bars", Some(self.average_holding_bars)),
+            (
+                "max_consecutive_wins",
+                Some(self.max_consecutive_wins as f64),
+            ),
+            (
+                "max_consecutive_losses",
+                Some(self.max_consecutive_losses as f64),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name, value.filter(|value| value.is_finite())))
+        .collect()
+    }
+}
+
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+//JSON不支持NaN和无穷大，写成null
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn write_trade_pairs_csv(path: &Path) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(
+        writer,
+        "open_datetime,close_datetime,direction,open_price,close_price,volume,pnl,holding_bars,holding_minutes"
+    )?;
+    for d in TRADE_PAIRS.iter() {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{}",
+            d.open_dt.format(DATETIME_FORMAT),
+            d.close_dt.format(DATETIME_FORMAT),
+            if d.is_long() { "long" } else { "short" },
+            d.open_price,
+            d.close_price,
+            d.volume,
+            d.pnl(),
+            d.holding_bars(),
+            (d.close_dt - d.open_dt).num_minutes(),
+        )?;
+    }
+    writer.flush()
+}
+
+fn write_statistics_csv(path: &Path, statistics: &TradeStatistics) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "name,value")?;
+    for (name, value) in statistics.items() {
+        let value = value.map_or(String::new(), |value| value.to_string());
+        writeln!(writer, "{},{}", name, value)?;
+    }
+    writer.flush()
+}
+
+fn write_json(path: &Path, statistics: &TradeStatistics) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"statistics\": {{")?;
+    let items = statistics.items();
+    for (i, (name, value)) in items.iter().enumerate() {
+        let comma = if i + 1 < items.len() { "," } else { "" };
+        let value = value.map_or("null".to_string(), json_number);
+        writeln!(writer, "    \"{}\": {}{}", name, value, comma)?;
+    }
+    writeln!(writer, "  }},")?;
+    writeln!(writer, "  \"trade_pairs\": [")?;
+    for (i, d) in TRADE_PAIRS.iter().enumerate() {
+        let comma = if i + 1 < TRADE_PAIRS.len() { "," } else { "" };
+        writeln!(
+            writer,
+            "    {{\"open_datetime\": \"{}\", \"close_datetime\": \"{}\", \"direction\": \"{}\", \"open_price\": {}, \"close_price\": {}, \"volume\": {}, \"pnl\": {}, \"holding_bars\": {}, \"holding_minutes\": {}}}{}",
+            d.open_dt.format(DATETIME_FORMAT),
+            d.close_dt.format(DATETIME_FORMAT),
+            if d.is_long() { "long" } else { "short" },
+            json_number(d.open_price),
+            json_number(d.close_price),
+            json_number(d.volume),
+            json_number(d.pnl()),
+            d.holding_bars(),
+            (d.close_dt - d.open_dt).num_minutes(),
+            comma
+        )?;
+    }
+    writeln!(writer, "  ]")?;
+    writeln!(writer, "}}")?;
+    writer.flush()
+}
+
+pub fn default_export_dir() -> PathBuf {
+    PathBuf::from(format!("{}/vnpyrs", get_home_path()))
+}
+
+//导出交易对和统计，返回写出的文件
+pub fn export_trade_pairs(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let statistics = TradeStatistics::new(&TRADE_PAIRS);
+    let trade_pairs_csv = dir.join("trade_pairs.csv");
+    write_trade_pairs_csv(&trade_pairs_csv)?;
+    let statistics_csv = dir.join("trade_statistics.csv");
+    write_statistics_csv(&statistics_csv, &statistics)?;
+    let json = dir.join("trade_pairs.json");
+    write_json(&json, &statistics)?;
+    Ok(vec![trade_pairs_csv, statistics_csv, json])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    //direction：1多，2空
+    fn pair(direction: u8, open_price: f64, close_price: f64, holding_bars: i64) -> TradePair {
+        let dt = NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        TradePair {
+            open_dt: dt,
+            open_price,
+            close_dt: dt,
+            close_price,
+            direction,
+            volume: 1.0,
+            open_ix: 0,
+            close_ix: holding_bars,
+        }
+    }
+
+    fn item(statistics: &TradeStatistics, name: &str) -> Option<f64> {
+        statistics
+            .items()
+            .into_iter()
+            .find(|(item_name, _)| *item_name == name)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn mixed_trades() {
+        let statistics = TradeStatistics::new(&[
+            pair(1, 100.0, 110.0, 2),
+            pair(2, 100.0, 104.0, 4),
+            pair(1, 100.0, 106.0, 3),
+            pair(2, 100.0, 90.0, 1),
+            pair(1, 100.0, 100.0, 5),
+        ]);
+        assert_eq!(statistics.trade_count, 5);
+        assert_eq!(statistics.winning_count, 3);
+        assert_eq!(statistics.losing_count, 1);
+        assert_eq!(statistics.winning_rate, 0.6);
+        assert_eq!(statistics.total_pnl, 22.0);
+        assert_eq!(statistics.gross_profit, 26.0);
+        assert_eq!(statistics.gross_loss, -4.0);
+        assert_eq!(statistics.profit_factor, Some(6.5));
+        assert_eq!(statistics.average_profit, 26.0 / 3.0);
+        assert_eq!(statistics.average_loss, -4.0);
+        assert_eq!(statistics.max_profit, Some(10.0));
+        assert_eq!(statistics.max_loss, Some(-4.0));
+        assert_eq!(statistics.average_holding_bars, 3.0);
+        assert_eq!(statistics.max_consecutive_wins, 2);
+        assert_eq!(statistics.max_consecutive_losses, 1);
+    }
+
+    #[test]
+    fn no_losing_trades() {
+        let statistics = TradeStatistics::new(&[pair(1, 100.0, 101.0, 1), pair(2, 100.0, 99.0, 1)]);
+        assert_eq!(statistics.profit_factor, None);
+        assert_eq!(item(&statistics, "profit_factor"), None);
+        assert_eq!(statistics.max_consecutive_wins, 2);
+        assert_eq!(statistics.max_profit, Some(1.0));
+        assert_eq!(item(&statistics, "max_loss"), None);
+    }
+
+    //最大亏损不能被0或盈利的交易顶掉，最大盈利也一样
+    #[test]
+    fn only_losing_trades() {
+        let statistics = TradeStatistics::new(&[
+            pair(1, 100.0, 97.0, 1),
+            pair(2, 100.0, 102.0, 1),
+            pair(1, 100.0, 100.0, 1),
+        ]);
+        assert_eq!(statistics.max_loss, Some(-3.0));
+        assert_eq!(statistics.max_profit, None);
+        assert_eq!(item(&statistics, "max_profit"), None);
+    }
+
+    #[test]
+    fn no_trades() {
+        let statistics = TradeStatistics::new(&[]);
+        assert_eq!(statistics.trade_count, 0);
+        assert_eq!(statistics.profit_factor, None);
+        assert!(statistics
+            .items()
+            .iter()
+            .all(|(_, value)| value.is_none_or(f64::is_finite)));
+    }
+
+    #[test]
+    fn json_number_is_null_when_not_finite() {
+        assert_eq!(json_number(1.5), "1.5");
+        assert_eq!(json_number(f64::NAN), "null");
+        assert_eq!(json_number(f64::INFINITY), "null");
+    }
+}
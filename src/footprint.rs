@@ -0,0 +1,175 @@
+use std::sync::LazyLock;
+
+use chrono::NaiveDateTime;
+
+use crate::config::{config_error, CONFIG};
+use crate::indicator::format_value;
+use crate::manager::{get_home_path, last_bar_end, read_trades_file, TradeData, HISTORY};
+use crate::profile::PRICE_TICK;
+
+//主动买入成交在卖价上，主动卖出成交在买价上，和trades.dat的方向取值相同
+const BUY: u8 = 1;
+const SELL: u8 = 2;
+
+//一根K线内一个价格行的成交量，bid为主动卖出，ask为主动买入
+pub struct FootprintCell {
+    pub price: f64,
+    pub bid: f64,
+    pub ask: f64,
+}
+
+impl FootprintCell {
+    pub fn total(&self) -> f64 {
+        self.bid + self.ask
+    }
+
+    pub fn delta(&self) -> f64 {
+        self.ask - self.bid
+    }
+
+    //格子里的文字“主动卖出×主动买入”
+    pub fn text(&self) -> String {
+        format!("{}×{}", format_value(self.bid), format_value(self.ask))
+    }
+}
+
+//按K线统计的逐价成交量，bars和HISTORY的K线一一对应，每根K线的cells按价格从低到高排列
+//价格行以row_size的整数倍为中心
+pub struct Footprint {
+    pub row_size: f64,
+    pub bars: Vec<Vec<FootprintCell>>,
+}
+
+impl Footprint {
+    pub fn delta(&self, ix: usize) -> f64 {
+        self.bars[ix].iter().map(FootprintCell::delta).sum()
+    }
+}
+
+//逐笔成交文件可选，存在时K线放大到能放下文字后画成足迹图
+//家目录vnpyrs文件夹下的ticks.dat，格式同trades.dat：时间戳、方向（1主动买入，2主动卖出）、价格、数量
+//价格行的高度为最小价格变动的row_ticks倍，配置在[footprint]节，默认为1
+pub static FOOTPRINT: LazyLock<Option<Footprint>> = LazyLock::new(|| {
+    let path = format!("{}/vnpyrs/ticks.dat", get_home_path());
+    if !std::path::Path::new(&path).is_file() {
+        return None;
+    }
+    let row_ticks = CONFIG.parse_or("footprint", "row_ticks", 1, |text| {
+        text.parse::<u32>().ok().filter(|ticks| *ticks > 0)
+    });
+    let row_size = *PRICE_TICK * row_ticks as f64;
+    let (bars, bad_direction) =
+        footprint_bars(&read_trades_file(&path), &HISTORY.datetime, row_size);
+    if bad_direction > 0 {
+        config_error(format!(
+            "ticks.dat中有{}笔成交的方向错误，已跳过",
+            bad_direction
+        ));
+    }
+    Some(Footprint { row_size, bars })
+});
+
+//把逐笔成交按K线和价格行累加，返回每根K线的格子和方向错误的笔数
+//K线的时间为开始时间，第一根K线之前和最后一根K线结束之后的成交不算
+fn footprint_bars(
+    ticks: &[TradeData],
+    datetime: &[NaiveDateTime],
+    row_size: f64,
+) -> (Vec<Vec<FootprintCell>>, usize) {
+    let mut bars: Vec<Vec<FootprintCell>> = datetime.iter().map(|_| Vec::new()).collect();
+    let end = last_bar_end(datetime);
+    let mut bad_direction = 0;
+    for tick in ticks {
+        let ix = datetime.partition_point(|dt| *dt <= tick.datetime);
+        if ix == 0 || end.is_some_and(|end| tick.datetime >= end) {
+            continue;
+        }
+        if tick.direction != BUY && tick.direction != SELL {
+            bad_direction += 1;
+            continue;
+        }
+        let cells = &mut bars[ix - 1];
+        let price = (tick.price / row_size).round() * row_size;
+        let cell = match cells.binary_search_by(|cell| cell.price.total_cmp(&price)) {
+            Ok(i) => &mut cells[i],
+            Err(i) => {
+                cells.insert(
+                    i,
+                    FootprintCell {
+                        price,
+                        bid: 0.0,
+                        ask: 0.0,
+                    },
+                );
+                &mut cells[i]
+            }
+        };
+        if tick.direction == BUY {
+            cell.ask += tick.volume;
+        } else {
+            cell.bid += tick.volume;
+        }
+    }
+    (bars, bad_direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    fn second(i: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+            + Duration::seconds(i)
+    }
+
+    fn tick(seconds: i64, direction: u8, price: f64, volume: f64) -> TradeData {
+        TradeData {
+            datetime: second(seconds),
+            direction,
+            price,
+            volume,
+        }
+    }
+
+    #[test]
+    fn ticks_into_bars_and_rows() {
+        //两根1分钟K线，最后一根在9:02结束
+        let datetime = [second(0), second(60)];
+        let ticks = [
+            //第一根K线之前
+            tick(-1, BUY, 10.0, 1.0),
+            tick(0, BUY, 10.0, 2.0),
+            //0.5一行，10.1归到10.0那一行，10.4归到10.5那一行
+            tick(30, SELL, 10.1, 3.0),
+            tick(59, BUY, 10.4, 4.0),
+            tick(60, SELL, 11.0, 5.0),
+            tick(90, 0, 11.0, 6.0),
+            tick(119, BUY, 11.0, 7.0),
+            //最后一根K线结束之后
+            tick(120, BUY, 11.0, 8.0),
+            tick(600, SELL, 11.0, 9.0),
+        ];
+        let (bars, bad_direction) = footprint_bars(&ticks, &datetime, 0.5);
+        let cells: Vec<Vec<(f64, f64, f64)>> = bars
+            .iter()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|cell| (cell.price, cell.bid, cell.ask))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            [
+                vec![(10.0, 3.0, 2.0), (10.5, 0.0, 4.0)],
+                vec![(11.0, 5.0, 7.0)]
+            ]
+        );
+        assert_eq!(bad_direction, 1);
+    }
+}
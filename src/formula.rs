@@ -0,0 +1,734 @@
+use crate::config::{config_error, parse_color, CONFIG};
+use crate::indicator::{ema, highest, lowest, sma, stddev, wma, Series, SubPane};
+use crate::manager::{HistoryData, HISTORY};
+
+//没有指定颜色时按顺序轮流使用
+const FORMULA_COLORS: [[f32; 4]; 6] = [
+    [0.88, 0.88, 0.88, 1.0],
+    [0.94, 0.75, 0.25, 1.0],
+    [0.75, 0.5, 1.0, 1.0],
+    [0.25, 0.75, 0.25, 1.0],
+    [0.25, 0.75, 1.0, 1.0],
+    [1.0, 0.5, 0.5, 1.0],
+];
+
+#[derive(Clone, Copy)]
+enum Column {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+
+#[derive(Clone, Copy)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+//周期的上限，再大转成usize后做加法会溢出
+const MAX_PERIOD: f64 = u32::MAX as f64;
+
+//带周期参数的函数，周期必须是常数
+#[derive(Clone, Copy)]
+enum WindowFn {
+    Ma,
+    Ema,
+    Wma,
+    Hhv,
+    Llv,
+    Std,
+    Sum,
+    Ref,
+}
+
+enum Expr {
+    Number(f64),
+    Column(Column),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Window(WindowFn, Box<Expr>, usize),
+    Cross(Box<Expr>, Box<Expr>),
+    Abs(Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+//多字符的运算符放在前面，先匹配
+const OPERATORS: [&str; 16] = [
+    ">=", "<=", "<>", "!=", "==", "&&", "||", "+", "-", "*", "/", ">", "<", "=", "(", ")",
+];
+
+//切分出的词和它在表达式中的位置（第几个字符，从1开始）
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let value = number
+                .parse::<f64>()
+                .map_err(|_| format!("第{}个字符：数字“{}”错误", position, number))?;
+            tokens.push((Token::Number(value), position));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let ident: String = chars[start..i].iter().collect();
+            tokens.push((Token::Ident(ident.to_uppercase()), position));
+        } else if c == ',' {
+            tokens.push((Token::Comma, position));
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                return Err(format!("第{}个字符：无法识别“{}”", position, c));
+            };
+            i += op.chars().count();
+            tokens.push((
+                match *op {
+                    "(" => Token::LParen,
+                    ")" => Token::RParen,
+                    op => Token::Op(op),
+                },
+                position,
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+//递归下降解析，优先级从低到高：OR、AND、比较、加减、乘除、负号
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    //当前词的位置，到末尾时指向表达式最后
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(_, position)| *position)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("第{}个字符：{}", self.position(), message))
+    }
+
+    fn eat_op(&mut self, ops: &[&str]) -> Option<&'static str> {
+        let op = match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => *op,
+            Some(Token::Ident(ident)) if ops.contains(&ident.as_str()) => {
+                if ident == "AND" {
+                    "&&"
+                } else {
+                    "||"
+                }
+            }
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn expect(&mut self, token: Token, message: &str) -> Result<(), String> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(message)
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_and()?;
+        while self.eat_op(&["||", "OR"]).is_some() {
+            let right = self.parse_and()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_comparison()?;
+        while self.eat_op(&["&&", "AND"]).is_some() {
+            let right = self.parse_comparison()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let left = self.parse_additive()?;
+        let Some(op) = self.eat_op(&[">", "<", ">=", "<=", "=", "==", "<>", "!="]) else {
+            return Ok(left);
+        };
+        let op = match op {
+            ">" => BinaryOp::Gt,
+            "<" => BinaryOp::Lt,
+            ">=" => BinaryOp::Ge,
+            "<=" => BinaryOp::Le,
+            "=" | "==" => BinaryOp::Eq,
+            _ => BinaryOp::Ne,
+        };
+        let right = self.parse_additive()?;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_multiplicative()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let op = if op == "+" {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_unary()?;
+        while let Some(op) = self.eat_op(&["*", "/"]) {
+            let op = if op == "*" {
+                BinaryOp::Mul
+            } else {
+                BinaryOp::Div
+            };
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.eat_op(&["-"]).is_some() {
+            return Ok(Expr::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        let position = self.position();
+        match self.peek().cloned() {
+            Some(Token::Number(value)) => {
+                self.pos += 1;
+                Ok(Expr::Number(value))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen, "缺少“)”")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(ident)) => {
+                self.pos += 1;
+                if self.peek() != Some(&Token::LParen) {
+                    return match ident.as_str() {
+                        "OPEN" | "O" => Ok(Expr::Column(Column::Open)),
+                        "HIGH" | "H" => Ok(Expr::Column(Column::High)),
+                        "LOW" | "L" => Ok(Expr::Column(Column::Low)),
+                        "CLOSE" | "C" => Ok(Expr::Column(Column::Close)),
+                        "VOLUME" | "VOL" | "V" => Ok(Expr::Column(Column::Volume)),
+                        _ => Err(format!("第{}个字符：未知的变量“{}”", position, ident)),
+                    };
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        let arg_position = self.position();
+                        args.push((self.parse_or()?, arg_position));
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                self.expect(Token::RParen, "缺少“)”")?;
+                call(&ident, args, position)
+            }
+            _ => self.error("缺少数值、变量或函数"),
+        }
+    }
+}
+
+//按函数名和参数生成表达式，检查参数个数和周期
+fn call(name: &str, args: Vec<(Expr, usize)>, position: usize) -> Result<Expr, String> {
+    let window_fn = match name {
+        "MA" | "SMA" => Some(WindowFn::Ma),
+        "EMA" => Some(WindowFn::Ema),
+        "WMA" => Some(WindowFn::Wma),
+        "HHV" | "MAX" => Some(WindowFn::Hhv),
+        "LLV" | "MIN" => Some(WindowFn::Llv),
+        "STD" => Some(WindowFn::Std),
+        "SUM" => Some(WindowFn::Sum),
+        "REF" => Some(WindowFn::Ref),
+        _ => None,
+    };
+    let arg_count = match (window_fn, name) {
+        (Some(_), _) | (None, "CROSS") => 2,
+        (None, "ABS") => 1,
+        (None, "IF") => 3,
+        _ => return Err(format!("第{}个字符：未知的函数“{}”", position, name)),
+    };
+    if args.len() != arg_count {
+        return Err(format!(
+            "第{}个字符：{}需要{}个参数，实际为{}个",
+            position,
+            name,
+            arg_count,
+            args.len()
+        ));
+    }
+    let mut args = args
+        .into_iter()
+        .map(|(expr, position)| (Box::new(expr), position));
+    let mut next = || args.next().unwrap();
+    if let Some(window_fn) = window_fn {
+        let (source, _) = next();
+        let (period, period_position) = next();
+        //REF可以取0，即当前值
+        let min_period = if matches!(window_fn, WindowFn::Ref) {
+            0.0
+        } else {
+            1.0
+        };
+        return match *period {
+            Expr::Number(value) if value.fract() == 0.0 && value > MAX_PERIOD => Err(format!(
+                "第{}个字符：{}的周期不能超过{}",
+                period_position, name, MAX_PERIOD
+            )),
+            Expr::Number(value) if value.fract() == 0.0 && value >= min_period => {
+                Ok(Expr::Window(window_fn, source, value as usize))
+            }
+            _ => Err(format!(
+                "第{}个字符：{}的周期必须是{}整数常数",
+                period_position,
+                name,
+                if min_period == 0.0 { "非负" } else { "正" }
+            )),
+        };
+    }
+    Ok(match name {
+        "CROSS" => Expr::Cross(next().0, next().0),
+        "ABS" => Expr::Abs(next().0),
+        _ => Expr::If(next().0, next().0, next().0),
+    })
+}
+
+fn bool_value(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl Expr {
+    fn eval(&self, history: &HistoryData) -> Vec<f64> {
+        let len = history.close_price.len();
+        match self {
+            Expr::Number(value) => vec![*value; len],
+            Expr::Column(column) => match column {
+                Column::Open => history.open_price.clone(),
+                Column::High => history.high_price.clone(),
+                Column::Low => history.low_price.clone(),
+                Column::Close => history.close_price.clone(),
+                Column::Volume => history.volume.clone(),
+            },
+            Expr::Neg(expr) => expr.eval(history).iter().map(|value| -value).collect(),
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (left.eval(history), right.eval(history));
+                left.iter()
+                    .zip(right.iter())
+                    .map(|(a, b)| binary(*op, *a, *b))
+                    .collect()
+            }
+            Expr::Window(window_fn, source, period) => {
+                let source = source.eval(history);
+                match window_fn {
+                    WindowFn::Ma => sma(&source, *period),
+                    WindowFn::Ema => ema(&source, *period),
+                    WindowFn::Wma => wma(&source, *period),
+                    WindowFn::Hhv => highest(&source, *period),
+                    WindowFn::Llv => lowest(&source, *period),
+                    WindowFn::Std => stddev(&source, *period),
+                    WindowFn::Sum => sma(&source, *period)
+                        .iter()
+                        .map(|value| value * *period as f64)
+                        .collect(),
+                    WindowFn::Ref => (0..len)
+                        .map(|i| {
+                            i.checked_sub(*period)
+                                .map_or(f64::NAN, |previous| source[previous])
+                        })
+                        .collect(),
+                }
+            }
+            //上穿：本根a>b，上一根a<=b
+            Expr::Cross(a, b) => {
+                let (a, b) = (a.eval(history), b.eval(history));
+                (0..len)
+                    .map(|i| {
+                        if i == 0 || a[i].is_nan() || b[i].is_nan() {
+                            return f64::NAN;
+                        }
+                        bool_value(a[i] > b[i] && a[i - 1] <= b[i - 1])
+                    })
+                    .collect()
+            }
+            Expr::Abs(expr) => expr.eval(history).iter().map(|value| value.abs()).collect(),
+            Expr::If(condition, then, otherwise) => {
+                let (condition, then, otherwise) = (
+                    condition.eval(history),
+                    then.eval(history),
+                    otherwise.eval(history),
+                );
+                (0..len)
+                    .map(|i| match condition[i] {
+                        value if value.is_nan() => f64::NAN,
+                        value if value != 0.0 => then[i],
+                        _ => otherwise[i],
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+//比较和逻辑运算的结果为1或0，有NaN时结果为NaN，除以0为NaN
+fn binary(op: BinaryOp, a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        return f64::NAN;
+    }
+    match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div if b == 0.0 => f64::NAN,
+        BinaryOp::Div => a / b,
+        BinaryOp::Gt => bool_value(a > b),
+        BinaryOp::Lt => bool_value(a < b),
+        BinaryOp::Ge => bool_value(a >= b),
+        BinaryOp::Le => bool_value(a <= b),
+        BinaryOp::Eq => bool_value(a == b),
+        BinaryOp::Ne => bool_value(a != b),
+        BinaryOp::And => bool_value(a != 0.0 && b != 0.0),
+        BinaryOp::Or => bool_value(a != 0.0 || b != 0.0),
+    }
+}
+
+//公式定义：名称、所在副图（None表示K线图）、颜色和表达式
+pub struct FormulaDef {
+    pub name: String,
+    pub pane: Option<String>,
+    pub color: Option<[f32; 4]>,
+    expr: Expr,
+}
+
+impl FormulaDef {
+    //head是“名称”或“名称@副图”，body是“表达式”或“表达式, #颜色”
+    pub fn parse(head: &str, body: &str) -> Result<FormulaDef, String> {
+        let (name, pane) = match head.split_once('@') {
+            Some((name, pane)) => (name.trim(), Some(pane.trim().to_string())),
+            None => (head.trim(), None),
+        };
+        let (text, color) = match body.rsplit_once(',') {
+            Some((text, color)) if color.trim().starts_with('#') => (
+                text,
+                Some(parse_color(color).ok_or(format!("颜色“{}”错误", color.trim()))?),
+            ),
+            _ => (body, None),
+        };
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: text.chars().count(),
+        };
+        let expr = parser.parse_or()?;
+        if parser.peek().is_some() {
+            return parser.error("多余的内容");
+        }
+        Ok(FormulaDef {
+            name: if name.is_empty() {
+                text.trim().to_string()
+            } else {
+                name.to_string()
+            },
+            pane: pane.filter(|pane| !pane.is_empty()),
+            color,
+            expr,
+        })
+    }
+
+    //运行时输入的一行：“名称@副图:表达式, #颜色”，名称部分可以省略
+    pub fn parse_input(input: &str) -> Result<FormulaDef, String> {
+        match input.split_once(':') {
+            Some((head, body)) => FormulaDef::parse(head, body),
+            None => FormulaDef::parse("", input),
+        }
+    }
+
+    pub fn eval(&self) -> Vec<f64> {
+        self.expr.eval(&HISTORY)
+    }
+
+    //加到K线图或副图，副图按名称匹配，“MACD”可以匹配“MACD(12,26,9)”，找不到则新建
+    //返回加到的副图下标，加到K线图时返回None
+    pub fn place(
+        &self,
+        overlays: &mut Vec<Series>,
+        sub_panes: &mut Vec<SubPane>,
+        color_ix: usize,
+    ) -> Option<usize> {
+        let series = Series::line(
+            self.name.clone(),
+            self.eval(),
+            self.color
+                .unwrap_or(FORMULA_COLORS[color_ix % FORMULA_COLORS.len()]),
+        );
+        let Some(pane) = &self.pane else {
+            overlays.push(series);
+            return None;
+        };
+        let prefix = format!("{}(", pane);
+        match sub_panes
+            .iter()
+            .position(|sub_pane| sub_pane.name == *pane || sub_pane.name.starts_with(&prefix))
+        {
+            Some(i) => {
+                sub_panes[i].series.push(series);
+                Some(i)
+            }
+            None => {
+                sub_panes.push(SubPane {
+                    name: pane.clone(),
+                    series: vec![series],
+                    range: None,
+                    reference_lines: Vec::new(),
+                    visible: true,
+                });
+                Some(sub_panes.len() - 1)
+            }
+        }
+    }
+}
+
+//配置在[formula]节，每行“名称 = 表达式”，名称写成“名称@副图”则画在副图上，表达式后可加“, #颜色”
+//出错的公式跳过，返回画出的公式个数
+pub fn config_formulas(overlays: &mut Vec<Series>, sub_panes: &mut Vec<SubPane>) -> usize {
+    let items = CONFIG.section("formula").unwrap_or_default();
+    let mut count = 0;
+    for (name, value) in items {
+        match FormulaDef::parse(name, value) {
+            Ok(formula) => {
+                formula.place(overlays, sub_panes, count);
+                count += 1;
+            }
+            Err(err) => config_error(format!(
+                "公式配置“{} = {}”错误：{}，已跳过",
+                name, value, err
+            )),
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(close_price: &[f64]) -> HistoryData {
+        HistoryData {
+            open_price: close_price.to_vec(),
+            high_price: close_price.iter().map(|price| price + 1.0).collect(),
+            low_price: close_price.iter().map(|price| price - 1.0).collect(),
+            close_price: close_price.to_vec(),
+            volume: vec![100.0; close_price.len()],
+            ..Default::default()
+        }
+    }
+
+    //NaN不等于自身，换成None再比较
+    fn eval(text: &str, close_price: &[f64]) -> Vec<Option<f64>> {
+        FormulaDef::parse("", text)
+            .unwrap()
+            .expr
+            .eval(&history(close_price))
+            .into_iter()
+            .map(|value| Some(value).filter(|value| !value.is_nan()))
+            .collect()
+    }
+
+    fn error(text: &str) -> String {
+        match FormulaDef::parse("", text) {
+            Ok(_) => panic!("“{}”应该出错", text),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(eval("1 + 2 * 3 - -4", &[0.0]), [Some(11.0)]);
+        assert_eq!(eval("(1 + 2) * 3", &[0.0]), [Some(9.0)]);
+        assert_eq!(eval("1 + 1 = 2 AND 3 > 2 or 0", &[0.0]), [Some(1.0)]);
+        assert_eq!(eval("2 <> 2 || 1 >= 2", &[0.0]), [Some(0.0)]);
+        assert_eq!(eval("C / 0", &[1.0]), [None]);
+    }
+
+    #[test]
+    fn columns_and_functions() {
+        let close = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(
+            eval("H - low", &close),
+            [Some(2.0), Some(2.0), Some(2.0), Some(2.0)]
+        );
+        assert_eq!(
+            eval("MA(C, 2)", &close),
+            [None, Some(1.5), Some(2.5), Some(3.5)]
+        );
+        assert_eq!(
+            eval("SUM(C, 3)", &close),
+            [None, None, Some(6.0), Some(9.0)]
+        );
+        assert_eq!(
+            eval("HHV(C, 2)", &close),
+            [None, Some(2.0), Some(3.0), Some(4.0)]
+        );
+        assert_eq!(
+            eval("REF(C, 1)", &close),
+            [None, Some(1.0), Some(2.0), Some(3.0)]
+        );
+        assert_eq!(
+            eval("REF(C, 0)", &close),
+            [Some(1.0), Some(2.0), Some(3.0), Some(4.0)]
+        );
+        assert_eq!(
+            eval("IF(C > 2, C, -C)", &close),
+            [Some(-1.0), Some(-2.0), Some(3.0), Some(4.0)]
+        );
+        assert_eq!(
+            eval("CROSS(C, 2.5)", &close),
+            [None, Some(0.0), Some(1.0), Some(0.0)]
+        );
+        assert_eq!(eval("MA(C, 5)", &close), [None, None, None, None]);
+    }
+
+    //除以0等算出来的NaN只影响包含它的窗口，移出窗口后恢复计算
+    #[test]
+    fn windows_skip_nan() {
+        let close = [1.0, 2.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(
+            eval("MA((C-LLV(C,2))/(HHV(C,2)-LLV(C,2)),2)", &close),
+            [None, None, None, None, Some(1.0), Some(1.0)]
+        );
+        let close = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        assert_eq!(
+            eval("EMA(C/(C-3),2)", &close),
+            [
+                None,
+                Some(-1.25),
+                None,
+                None,
+                Some(3.25),
+                Some(3.25 + 2.0 / 3.0 * (2.0 - 3.25))
+            ]
+        );
+        assert_eq!(
+            eval("HHV(C/(C-3),2)", &close),
+            [None, Some(-0.5), None, None, Some(4.0), Some(2.5)]
+        );
+        assert_eq!(
+            eval("STD(C/(C-3),2)", &close),
+            [None, Some(0.75), None, None, Some(0.75), Some(0.25)]
+        );
+        assert_eq!(
+            eval("WMA(REF(C,1),2)", &close[..4]),
+            [None, None, Some(5.0 / 3.0), Some(8.0 / 3.0)]
+        );
+    }
+
+    #[test]
+    fn name_pane_and_color() {
+        let formula = FormulaDef::parse(" DIF @ MY ", "EMA(C,12)-EMA(C,26), #FF8000").unwrap();
+        assert_eq!(formula.name, "DIF");
+        assert_eq!(formula.pane.as_deref(), Some("MY"));
+        assert!(formula.color.is_some());
+        let formula = FormulaDef::parse_input("MA(C,5)").unwrap();
+        assert_eq!(formula.name, "MA(C,5)");
+        assert!(formula.pane.is_none());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(error("1 +"), "第3个字符：缺少数值、变量或函数");
+        assert_eq!(error("(1 + 2"), "第6个字符：缺少“)”");
+        assert_eq!(error("1 2"), "第3个字符：多余的内容");
+        assert_eq!(error("C # 1"), "第3个字符：无法识别“#”");
+        assert_eq!(error("CLOSED"), "第1个字符：未知的变量“CLOSED”");
+        assert_eq!(error("FOO(C)"), "第1个字符：未知的函数“FOO”");
+        assert_eq!(error("MA(C)"), "第1个字符：MA需要2个参数，实际为1个");
+    }
+
+    #[test]
+    fn period_validation() {
+        assert_eq!(error("MA(C, 0)"), "第7个字符：MA的周期必须是正整数常数");
+        assert_eq!(error("MA(C, 2.5)"), "第7个字符：MA的周期必须是正整数常数");
+        assert_eq!(error("MA(C, C)"), "第7个字符：MA的周期必须是正整数常数");
+        assert_eq!(
+            error("REF(C, -1)"),
+            "第8个字符：REF的周期必须是非负整数常数"
+        );
+        assert_eq!(
+            error("HHV(C, 1000000000000000000000000000000)"),
+            format!("第8个字符：HHV的周期不能超过{}", MAX_PERIOD)
+        );
+        let huge = format!("HHV(C, {})", MAX_PERIOD + 1.0);
+        assert_eq!(
+            error(&huge),
+            format!("第8个字符：HHV的周期不能超过{}", MAX_PERIOD)
+        );
+        let limit = format!("LLV(C, {})", MAX_PERIOD);
+        assert_eq!(eval(&limit, &[1.0, 2.0]), [None, None]);
+    }
+}
@@ -0,0 +1,208 @@
+use std::io::{BufReader, Read};
+use std::sync::LazyLock;
+
+use crate::config::{parse_color, CONFIG};
+use crate::manager::{get_home_path, last_bar_end, timestamp_to_datetime, HISTORY};
+use crate::profile::PRICE_TICK;
+
+//按K线统计的盘口挂单量，bars和HISTORY的K线一一对应
+//每根K线是(价格行, 平均挂单量)，按价格行从低到高排列，第k行以k * row_size为中心
+//一根K线内有多个快照时取平均，没有快照的K线沿用上一根，最后一个快照之后不再沿用
+//has_snapshot标记K线内是否有真实的快照，沿用的不算
+pub struct DepthHeatmap {
+    pub row_size: f64,
+    pub bars: Vec<Vec<(i64, f64)>>,
+    has_snapshot: Vec<bool>,
+}
+
+//热力图的颜色：挂单量按百分位截断后在colors的几个颜色之间插值，分成HEATMAP_LEVELS档
+pub struct HeatmapStyle {
+    colors: Vec<[f32; 4]>,
+    opacity: f32,
+    clip: (f64, f64),
+}
+
+pub const HEATMAP_LEVELS: usize = 32;
+
+impl HeatmapStyle {
+    //配置在[heatmap]节：colors为从少到多的颜色，逗号分隔；opacity为不透明度，默认为0.6
+    //clip为“低百分位, 高百分位”，低于低百分位的不画，高于高百分位的按最多的颜色画，默认为0, 98
+    fn from_config() -> Self {
+        let default_colors = vec![
+            [0.06, 0.12, 0.38, 1.0],
+            [0.13, 0.5, 0.75, 1.0],
+            [0.25, 0.75, 0.5, 1.0],
+            [0.94, 0.88, 0.25, 1.0],
+            [1.0, 0.25, 0.13, 1.0],
+        ];
+        let colors = CONFIG.parse_or("heatmap", "colors", default_colors, |text| {
+            let colors: Option<Vec<[f32; 4]>> = text.split(',').map(parse_color).collect();
+            colors.filter(|colors| colors.len() >= 2)
+        });
+        let opacity = CONFIG.parse_or("heatmap", "opacity", 0.6, |text| {
+            text.parse::<f32>()
+                .ok()
+                .filter(|opacity| *opacity > 0.0 && *opacity <= 1.0)
+        });
+        let clip = CONFIG.parse_or("heatmap", "clip", (0.0, 98.0), |text| {
+            let parts: Vec<Option<f64>> = text
+                .split(',')
+                .map(|part| part.trim().parse::<f64>().ok())
+                .collect();
+            match parts[..] {
+                [Some(low), Some(high)] if 0.0 <= low && low < high && high <= 100.0 => {
+                    Some((low, high))
+                }
+                _ => None,
+            }
+        });
+        HeatmapStyle {
+            colors,
+            opacity,
+            clip,
+        }
+    }
+
+    //有快照的K线上所有挂单量的低百分位和高百分位，沿用上一根的不重复计入
+    pub fn clip_range(&self, heatmap: &DepthHeatmap) -> (f64, f64) {
+        let mut values: Vec<f64> = heatmap
+            .bars
+            .iter()
+            .zip(&heatmap.has_snapshot)
+            .filter(|(_, has_snapshot)| **has_snapshot)
+            .flat_map(|(rows, _)| rows)
+            .map(|(_, size)| *size)
+            .filter(|size| *size > 0.0)
+            .collect();
+        if values.is_empty() {
+            return (0.0, 0.0);
+        }
+        values.sort_by(f64::total_cmp);
+        let at = |percent: f64| {
+            let ix = (percent / 100.0 * (values.len() - 1) as f64).round() as usize;
+            values[ix]
+        };
+        (at(self.clip.0), at(self.clip.1))
+    }
+
+    //挂单量所在的档，低于下限时为None
+    pub fn level(&self, size: f64, (low, high): (f64, f64)) -> Option<usize> {
+        if size <= 0.0 || size < low {
+            return None;
+        }
+        if high <= low {
+            return Some(HEATMAP_LEVELS - 1);
+        }
+        let t = ((size - low) / (high - low)).min(1.0);
+        Some((t * (HEATMAP_LEVELS - 1) as f64).round() as usize)
+    }
+
+    pub fn color(&self, level: usize) -> [f32; 4] {
+        let t = level as f32 / (HEATMAP_LEVELS - 1) as f32 * (self.colors.len() - 1) as f32;
+        let i = (t.floor() as usize).min(self.colors.len() - 2);
+        let f = t - i as f32;
+        let (from, to) = (self.colors[i], self.colors[i + 1]);
+        [
+            from[0] + (to[0] - from[0]) * f,
+            from[1] + (to[1] - from[1]) * f,
+            from[2] + (to[2] - from[2]) * f,
+            self.opacity,
+        ]
+    }
+}
+
+pub static HEATMAP_STYLE: LazyLock<HeatmapStyle> = LazyLock::new(HeatmapStyle::from_config);
+
+//盘口快照文件可选，家目录vnpyrs文件夹下的depth.dat，不存在时不画热力图
+//先是版本号和快照数量，每个快照为时间戳、档位数量，然后每档为价格和挂单量，买卖盘不区分
+//价格行的高度为最小价格变动的row_ticks倍，配置在[heatmap]节，默认为1
+pub static DEPTH_HEATMAP: LazyLock<Option<DepthHeatmap>> = LazyLock::new(|| {
+    let home_path = get_home_path();
+    let file = std::fs::File::open(format!("{}/vnpyrs/depth.dat", home_path)).ok()?;
+    let row_ticks = CONFIG.parse_or("heatmap", "row_ticks", 1, |text| {
+        text.parse::<u32>().ok().filter(|ticks| *ticks > 0)
+    });
+    let row_size = *PRICE_TICK * row_ticks as f64;
+    let mut bars: Vec<Vec<(i64, f64)>> = HISTORY.datetime.iter().map(|_| Vec::new()).collect();
+    let mut snapshot_count = vec![0usize; bars.len()];
+    let end = last_bar_end(&HISTORY.datetime);
+    let mut reader = BufReader::new(file);
+    let mut buf = [0u8; 8];
+    let error_string = format!("读取文件{}/vnpyrs/depth.dat失败", home_path);
+    reader.read_exact(&mut buf).expect(&error_string); //读取版本号
+    if u64::from_le_bytes(buf) != 0 {
+        panic!("请升级版本");
+    }
+    reader.read_exact(&mut buf).expect(&error_string);
+    let count = u64::from_le_bytes(buf);
+    for _ in 0..count {
+        reader.read_exact(&mut buf).expect(&error_string);
+        let datetime = timestamp_to_datetime(u64::from_le_bytes(buf));
+        reader.read_exact(&mut buf).expect(&error_string);
+        let level_count = u64::from_le_bytes(buf);
+        //K线的时间为开始时间，第一根K线之前和最后一根K线结束之后的快照不算，但档位还是要读完
+        let mut ix = HISTORY.datetime.partition_point(|dt| *dt <= datetime);
+        if end.is_some_and(|end| datetime >= end) {
+            ix = 0;
+        }
+        if ix > 0 {
+            snapshot_count[ix - 1] += 1;
+        }
+        for _ in 0..level_count {
+            reader.read_exact(&mut buf).expect(&error_string);
+            let price = f64::from_le_bytes(buf);
+            reader.read_exact(&mut buf).expect(&error_string);
+            let size = f64::from_le_bytes(buf);
+            if ix == 0 {
+                continue;
+            }
+            let rows = &mut bars[ix - 1];
+            let row = (price / row_size).round() as i64;
+            match rows.binary_search_by_key(&row, |(row, _)| *row) {
+                Ok(i) => rows[i].1 += size,
+                Err(i) => rows.insert(i, (row, size)),
+            }
+        }
+    }
+    let last = snapshot_count.iter().rposition(|count| *count > 0)?;
+    for i in 0..=last {
+        if snapshot_count[i] > 0 {
+            for (_, size) in bars[i].iter_mut() {
+                *size /= snapshot_count[i] as f64;
+            }
+        } else if i > 0 {
+            bars[i] = bars[i - 1].clone();
+        }
+    }
+    Some(DepthHeatmap {
+        row_size,
+        bars,
+        has_snapshot: snapshot_count.iter().map(|count| *count > 0).collect(),
+    })
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_range_skips_carried_bars() {
+        let style = HeatmapStyle {
+            colors: vec![[0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]],
+            opacity: 1.0,
+            clip: (0.0, 50.0),
+        };
+        //第1、2根沿用第0根，算进去的话中位数会是10
+        let heatmap = DepthHeatmap {
+            row_size: 1.0,
+            bars: vec![
+                vec![(0, 10.0)],
+                vec![(0, 10.0)],
+                vec![(0, 10.0)],
+                vec![(0, 100.0)],
+            ],
+            has_snapshot: vec![true, false, false, true],
+        };
+        assert_eq!(style.clip_range(&heatmap), (10.0, 100.0));
+    }
+}
@@ -0,0 +1,862 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::config::{config_error, parse_color, CONFIG};
+use crate::manager::HISTORY;
+
+//指标序列的画法
+#[derive(Clone, Copy, PartialEq)]
+pub enum SeriesStyle {
+    Line,
+    //从0画竖线，正值用color，负值用down_color
+    Histogram { down_color: [f32; 4] },
+    //每个有效值画一个点
+    Dots,
+}
+
+//指标序列，未计算出来的位置为NaN
+pub struct Series {
+    pub name: String,
+    pub values: Vec<f64>,
+    pub color: [f32; 4],
+    pub style: SeriesStyle,
+}
+
+impl Series {
+    pub fn line(name: String, values: Vec<f64>, color: [f32; 4]) -> Self {
+        Series {
+            name,
+            values,
+            color,
+            style: SeriesStyle::Line,
+        }
+    }
+
+    pub fn value_at(&self, ix: i64) -> f64 {
+        self.values.get(ix as usize).copied().unwrap_or(f64::NAN)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MaType {
+    Sma,
+    Ema,
+    Wma,
+}
+
+impl MaType {
+    pub fn parse(text: &str) -> Option<MaType> {
+        match text.trim().to_uppercase().as_str() {
+            "SMA" | "MA" => Some(MaType::Sma),
+            "EMA" => Some(MaType::Ema),
+            "WMA" => Some(MaType::Wma),
+            _ => None,
+        }
+    }
+
+    pub fn compute(&self, source: &[f64], period: usize) -> Vec<f64> {
+        match self {
+            MaType::Sma => sma(source, period),
+            MaType::Ema => ema(source, period),
+            MaType::Wma => wma(source, period),
+        }
+    }
+}
+
+//窗口内NaN（包括除以0等算出来的无效值）的个数，窗口里有NaN时结果为NaN，NaN移出窗口后恢复计算
+//开头的NaN（例如MACD的DEA）也这样跳过，相当于从第一个有效值开始算
+struct MissingCount {
+    period: usize,
+    count: usize,
+}
+
+impl MissingCount {
+    fn new(period: usize) -> Self {
+        MissingCount { period, count: 0 }
+    }
+
+    //第i个值进入窗口，返回移出窗口的值，无效值进出时当作0
+    fn push(&mut self, source: &[f64], i: usize) -> (f64, f64) {
+        let value = if source[i].is_finite() {
+            source[i]
+        } else {
+            self.count += 1;
+            0.0
+        };
+        let removed = match i.checked_sub(self.period) {
+            Some(old) if source[old].is_finite() => source[old],
+            Some(_) => {
+                self.count -= 1;
+                0.0
+            }
+            None => 0.0,
+        };
+        (value, removed)
+    }
+
+    //第i个值进入后窗口是否已满且全是有效值
+    fn is_valid(&self, i: usize) -> bool {
+        i + 1 >= self.period && self.count == 0
+    }
+}
+
+//简单移动平均
+pub fn sma(source: &[f64], period: usize) -> Vec<f64> {
+    let mut result = vec![f64::NAN; source.len()];
+    if period == 0 {
+        return result;
+    }
+    let mut missing = MissingCount::new(period);
+    let mut sum = 0.0;
+    for (i, output) in result.iter_mut().enumerate() {
+        let (value, removed) = missing.push(source, i);
+        sum += value - removed;
+        if missing.is_valid(i) {
+            *output = sum / period as f64;
+        }
+    }
+    result
+}
+
+//指数移动平均，和talib一样用前period个值的简单平均作为初值
+//遇到NaN时结果为NaN，之后重新取period个有效值的简单平均作为初值
+pub fn ema(source: &[f64], period: usize) -> Vec<f64> {
+    let mut result = vec![f64::NAN; source.len()];
+    if period == 0 {
+        return result;
+    }
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut valid_count = 0;
+    let mut value = 0.0;
+    for (output, &price) in result.iter_mut().zip(source) {
+        if !price.is_finite() {
+            valid_count = 0;
+            value = 0.0;
+            continue;
+        }
+        valid_count += 1;
+        if valid_count < period {
+            value += price;
+            continue;
+        }
+        if valid_count == period {
+            value = (value + price) / period as f64;
+        } else {
+            value += alpha * (price - value);
+        }
+        *output = value;
+    }
+    result
+}
+
+//加权移动平均，最近的值权重为period，最早的值权重为1
+pub fn wma(source: &[f64], period: usize) -> Vec<f64> {
+    let mut result = vec![f64::NAN; source.len()];
+    if period == 0 {
+        return result;
+    }
+    let denominator = (period * (period + 1)) as f64 / 2.0;
+    let mut missing = MissingCount::new(period);
+    let mut sum = 0.0;
+    let mut weighted_sum = 0.0;
+    for (i, output) in result.iter_mut().enumerate() {
+        let (value, removed) = missing.push(source, i);
+        //窗口右移一格，原有每个值的权重都减1
+        weighted_sum += period as f64 * value - sum;
+        sum += value - removed;
+        if missing.is_valid(i) {
+            *output = weighted_sum / denominator;
+        }
+    }
+    result
+}
+
+//滚动标准差（总体标准差，和talib一致）
+pub fn stddev(source: &[f64], period: usize) -> Vec<f64> {
+    let mut result = vec![f64::NAN; source.len()];
+    if period == 0 {
+        return result;
+    }
+    let mut missing = MissingCount::new(period);
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for (i, output) in result.iter_mut().enumerate() {
+        let (value, removed) = missing.push(source, i);
+        sum += value - removed;
+        sum_squares += value * value - removed * removed;
+        if missing.is_valid(i) {
+            let mean = sum / period as f64;
+            *output = (sum_squares / period as f64 - mean * mean).max(0.0).sqrt();
+        }
+    }
+    result
+}
+
+//滚动最大值，用单调队列，每个值只进出队列一次
+pub fn highest(source: &[f64], period: usize) -> Vec<f64> {
+    rolling_extreme(source, period, |new, old| new >= old)
+}
+
+//滚动最小值
+pub fn lowest(source: &[f64], period: usize) -> Vec<f64> {
+    rolling_extreme(source, period, |new, old| new <= old)
+}
+
+//队列里存下标，队首是窗口内的极值，新值比队尾“更极”时弹出队尾
+fn rolling_extreme(source: &[f64], period: usize, replaces: fn(f64, f64) -> bool) -> Vec<f64> {
+    let mut result = vec![f64::NAN; source.len()];
+    if period == 0 {
+        return result;
+    }
+    let mut missing = MissingCount::new(period);
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for i in 0..source.len() {
+        missing.push(source, i);
+        if source[i].is_finite() {
+            while queue
+                .back()
+                .is_some_and(|back| replaces(source[i], source[*back]))
+            {
+                queue.pop_back();
+            }
+            queue.push_back(i);
+        }
+        if queue.front().is_some_and(|front| *front + period <= i) {
+            queue.pop_front();
+        }
+        if missing.is_valid(i) {
+            result[i] = source[queue[0]];
+        }
+    }
+    result
+}
+
+//真实波幅，第一根K线没有前收盘价，取最高价减最低价
+pub fn true_range(high: &[f64], low: &[f64], close: &[f64]) -> Vec<f64> {
+    let mut result = Vec::with_capacity(high.len());
+    for i in 0..high.len() {
+        let range = high[i] - low[i];
+        if i == 0 {
+            result.push(range);
+        } else {
+            let previous_close = close[i - 1];
+            result.push(
+                range
+                    .max((high[i] - previous_close).abs())
+                    .max((low[i] - previous_close).abs()),
+            );
+        }
+    }
+    result
+}
+
+//平均真实波幅，Wilder平滑，初值为前period个真实波幅的简单平均
+pub fn atr(high: &[f64], low: &[f64], close: &[f64], period: usize) -> Vec<f64> {
+    let tr = true_range(high, low, close);
+    let mut result = vec![f64::NAN; tr.len()];
+    if period == 0 || tr.len() < period {
+        return result;
+    }
+    let mut value = tr[..period].iter().sum::<f64>() / period as f64;
+    result[period - 1] = value;
+    for i in period..tr.len() {
+        value = (value * (period - 1) as f64 + tr[i]) / period as f64;
+        result[i] = value;
+    }
+    result
+}
+
+//历史波动率：对数收益率的滚动标准差，按每年bars_per_year根K线年化，单位为百分比
+pub fn historical_volatility(close: &[f64], period: usize, bars_per_year: usize) -> Vec<f64> {
+    let mut returns = vec![f64::NAN; close.len()];
+    for i in 1..close.len() {
+        returns[i] = (close[i] / close[i - 1]).ln();
+    }
+    let scale = (bars_per_year as f64).sqrt() * 100.0;
+    stddev(&returns, period)
+        .iter()
+        .map(|value| value * scale)
+        .collect()
+}
+
+//成交量加权平均价，典型价格(最高+最低+收盘)/3按成交量加权，从start开始累计，resets(i)为true时重新累计
+//累计成交量为0时取典型价格
+fn cumulative_vwap(
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    volume: &[f64],
+    start: usize,
+    resets: impl Fn(usize) -> bool,
+) -> Vec<f64> {
+    let mut result = vec![f64::NAN; close.len()];
+    let mut price_volume = 0.0;
+    let mut total_volume = 0.0;
+    for i in start..close.len() {
+        if i > start && resets(i) {
+            price_volume = 0.0;
+            total_volume = 0.0;
+        }
+        let typical_price = (high[i] + low[i] + close[i]) / 3.0;
+        price_volume += typical_price * volume[i];
+        total_volume += volume[i];
+        result[i] = if total_volume > 0.0 {
+            price_volume / total_volume
+        } else {
+            typical_price
+        };
+    }
+    result
+}
+
+//交易时段的开始时间，例如期货夜盘的21:00，K线时间减去它之后的日期相同就属于同一个交易日
+pub fn session_key(datetime: NaiveDateTime, session_start: NaiveTime) -> NaiveDate {
+    (datetime - (session_start - NaiveTime::MIN)).date()
+}
+
+//交易日的开始时间，配置在[vwap]节的session_start，默认00:00
+pub fn session_start() -> NaiveTime {
+    CONFIG.parse_or("vwap", "session_start", NaiveTime::MIN, |text| {
+        NaiveTime::parse_from_str(text, "%H:%M").ok()
+    })
+}
+
+//按交易日把K线分段
+pub fn session_ranges(datetime: &[NaiveDateTime], session_start: NaiveTime) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for i in 0..datetime.len() {
+        match ranges.last_mut() {
+            Some(range)
+                if session_key(datetime[i], session_start)
+                    == session_key(datetime[i - 1], session_start) =>
+            {
+                range.end = i + 1
+            }
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+//每个交易日重新累计的VWAP
+pub fn session_vwap(
+    datetime: &[NaiveDateTime],
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    volume: &[f64],
+    session_start: NaiveTime,
+) -> Vec<f64> {
+    cumulative_vwap(high, low, close, volume, 0, |i| {
+        session_key(datetime[i], session_start) != session_key(datetime[i - 1], session_start)
+    })
+}
+
+//从anchor开始累计、不再重置的VWAP
+pub fn anchored_vwap(
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    volume: &[f64],
+    anchor: usize,
+) -> Vec<f64> {
+    cumulative_vwap(high, low, close, volume, anchor, |_| false)
+}
+
+//通道指标的中轨、上轨、下轨
+pub struct Band {
+    pub middle: Vec<f64>,
+    pub upper: Vec<f64>,
+    pub lower: Vec<f64>,
+}
+
+impl Band {
+    fn new(middle: Vec<f64>, width: &[f64], multiplier: f64) -> Self {
+        let upper = middle
+            .iter()
+            .zip(width)
+            .map(|(m, w)| m + multiplier * w)
+            .collect();
+        let lower = middle
+            .iter()
+            .zip(width)
+            .map(|(m, w)| m - multiplier * w)
+            .collect();
+        Band {
+            middle,
+            upper,
+            lower,
+        }
+    }
+}
+
+//布林带：中轨为SMA，上下轨为中轨加减multiplier倍标准差
+pub fn bollinger(close: &[f64], period: usize, multiplier: f64) -> Band {
+    Band::new(sma(close, period), &stddev(close, period), multiplier)
+}
+
+//肯特纳通道：中轨为EMA，上下轨为中轨加减multiplier倍ATR
+pub fn keltner(high: &[f64], low: &[f64], close: &[f64], period: usize, multiplier: f64) -> Band {
+    Band::new(
+        ema(close, period),
+        &atr(high, low, close, period),
+        multiplier,
+    )
+}
+
+//保留最多4位小数，去掉末尾的0
+pub fn format_value(value: f64) -> String {
+    if !value.is_finite() {
+        return "--".to_string();
+    }
+    let text = format!("{:.4}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+const DEFAULT_MA: [(&str, MaType, usize, [f32; 4]); 4] = [
+    ("MA5", MaType::Sma, 5, [0.88, 0.88, 0.88, 1.0]),
+    ("MA10", MaType::Sma, 10, [0.94, 0.75, 0.25, 1.0]),
+    ("MA20", MaType::Sma, 20, [0.75, 0.5, 1.0, 1.0]),
+    ("MA60", MaType::Sma, 60, [0.25, 0.75, 0.25, 1.0]),
+];
+
+//均线，配置在[ma]节，每行“名称 = 类型, 周期, 颜色”，例如“EMA12 = EMA, 12, #FF8000”，格式错误的行跳过
+pub fn moving_averages() -> Vec<Series> {
+    let Some(items) = CONFIG.section("ma") else {
+        return DEFAULT_MA
+            .iter()
+            .map(|(name, ma_type, period, color)| {
+                Series::line(
+                    name.to_string(),
+                    ma_type.compute(&HISTORY.close_price, *period),
+                    *color,
+                )
+            })
+            .collect();
+    };
+    items
+        .iter()
+        .filter_map(|(name, value)| {
+            let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+            let ma_type = fields.first().and_then(|text| MaType::parse(text));
+            let period = fields
+                .get(1)
+                .and_then(|text| text.parse::<usize>().ok())
+                .filter(|period| *period > 0);
+            let color = fields
+                .get(2)
+                .map_or(Some([1.0; 4]), |text| parse_color(text));
+            match (ma_type, period, color) {
+                (Some(ma_type), Some(period), Some(color)) => Some(Series::line(
+                    name.clone(),
+                    ma_type.compute(&HISTORY.close_price, period),
+                    color,
+                )),
+                _ => {
+                    config_error(format!("均线配置“{} = {}”格式错误，已跳过", name, value));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+//通道填充，upper和lower是上下轨在overlays中的位置
+pub struct BandFill {
+    pub upper: usize,
+    pub lower: usize,
+    pub color: [f32; 4],
+}
+
+//填充色的透明度
+const BAND_FILL_ALPHA: f32 = 0.15;
+
+//通道指标，配置在[band]节，每行“名称 = 类型, 周期, 倍数, 颜色”，类型可以是BOLL或KELTNER
+//默认只画BOLL(20, 2)，通道的三条线追加到overlays后面，格式错误的行跳过
+pub fn bands(overlays: &mut Vec<Series>) -> Vec<BandFill> {
+    let default_items = [("BOLL".to_string(), "BOLL, 20, 2, #FFC000".to_string())];
+    let items = CONFIG.section("band").unwrap_or(&default_items);
+    let mut fills = Vec::new();
+    for (name, value) in items {
+        let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+        let kind = fields.first().map(|text| text.to_uppercase());
+        let period = fields
+            .get(1)
+            .and_then(|text| text.parse::<usize>().ok())
+            .filter(|period| *period > 0);
+        let multiplier = fields.get(2).and_then(|text| text.parse::<f64>().ok());
+        let color = fields
+            .get(3)
+            .map_or(Some([1.0; 4]), |text| parse_color(text));
+        let (band, color) = match (kind.as_deref(), period, multiplier, color) {
+            (Some("BOLL"), Some(period), Some(multiplier), Some(color)) => {
+                (bollinger(&HISTORY.close_price, period, multiplier), color)
+            }
+            (Some("KELTNER"), Some(period), Some(multiplier), Some(color)) => (
+                keltner(
+                    &HISTORY.high_price,
+                    &HISTORY.low_price,
+                    &HISTORY.close_price,
+                    period,
+                    multiplier,
+                ),
+                color,
+            ),
+            _ => {
+                config_error(format!("通道配置“{} = {}”格式错误，已跳过", name, value));
+                continue;
+            }
+        };
+        let upper = overlays.len();
+        overlays.push(Series::line(format!("{}上轨", name), band.upper, color));
+        overlays.push(Series::line(format!("{}中轨", name), band.middle, color));
+        overlays.push(Series::line(format!("{}下轨", name), band.lower, color));
+        fills.push(BandFill {
+            upper,
+            lower: upper + 2,
+            color: [color[0], color[1], color[2], BAND_FILL_ALPHA],
+        });
+    }
+    fills
+}
+
+//交易时段VWAP，配置在[vwap]节：session_vwap为true或false，默认只有日内数据才画；
+//session_start为交易日的开始时间，默认00:00，期货夜盘可设为21:00
+pub fn vwap_overlay() -> Option<Series> {
+    let intraday = HISTORY
+        .datetime
+        .windows(2)
+        .any(|pair| pair[0].date() == pair[1].date());
+    let enabled = CONFIG.parse_or("vwap", "session_vwap", intraday, |text| {
+        text.to_lowercase().parse::<bool>().ok()
+    });
+    if !enabled {
+        return None;
+    }
+    let session_start = session_start();
+    Some(Series::line(
+        "VWAP".to_string(),
+        session_vwap(
+            &HISTORY.datetime,
+            &HISTORY.high_price,
+            &HISTORY.low_price,
+            &HISTORY.close_price,
+            &HISTORY.volume,
+            session_start,
+        ),
+        [1.0, 0.5, 0.75, 1.0],
+    ))
+}
+
+//锚定VWAP，从anchor这根K线开始
+pub fn anchored_vwap_overlay(anchor: usize) -> Series {
+    Series::line(
+        format!("AVWAP({})", HISTORY.datetime[anchor].format("%m-%d %H:%M")),
+        anchored_vwap(
+            &HISTORY.high_price,
+            &HISTORY.low_price,
+            &HISTORY.close_price,
+            &HISTORY.volume,
+            anchor,
+        ),
+        [0.5, 0.8, 1.0, 1.0],
+    )
+}
+
+//副图，画在成交量下面，和K线图共用横轴
+pub struct SubPane {
+    pub name: String,
+    pub series: Vec<Series>,
+    //固定的纵轴范围，None表示按可见范围内的值自动缩放
+    pub range: Option<(f64, f64)>,
+    //参考线，例如MACD的零轴
+    pub reference_lines: Vec<f64>,
+    pub visible: bool,
+}
+
+impl SubPane {
+    //left_ix到right_ix之间的纵轴范围，参考线总在范围内，有柱状图时0也在范围内
+    pub fn value_range(&self, left_ix: i64, right_ix: i64) -> (f64, f64) {
+        if let Some(range) = self.range {
+            return range;
+        }
+        let mut min_value = f64::INFINITY;
+        let mut max_value = f64::NEG_INFINITY;
+        if self
+            .series
+            .iter()
+            .any(|series| matches!(series.style, SeriesStyle::Histogram { .. }))
+        {
+            (min_value, max_value) = (0.0, 0.0);
+        }
+        for value in self
+            .reference_lines
+            .iter()
+            .chain(self.series.iter().flat_map(|series| {
+                let right = (right_ix as usize + 1).min(series.values.len());
+                let left = (left_ix as usize).min(right);
+                series.values[left..right].iter()
+            }))
+        {
+            if value.is_finite() {
+                min_value = min_value.min(*value);
+                max_value = max_value.max(*value);
+            }
+        }
+        if !min_value.is_finite() {
+            (-1.0, 1.0)
+        } else if min_value == max_value {
+            (min_value - 1.0, max_value + 1.0)
+        } else {
+            (min_value, max_value)
+        }
+    }
+}
+
+//MACD：DIF为快慢EMA之差，DEA为DIF的EMA，柱为两者之差的2倍
+pub fn macd(name: String, close: &[f64], fast: usize, slow: usize, signal: usize) -> SubPane {
+    let dif: Vec<f64> = ema(close, fast)
+        .iter()
+        .zip(ema(close, slow))
+        .map(|(fast, slow)| fast - slow)
+        .collect();
+    let dea = ema(&dif, signal);
+    let histogram = dif.iter().zip(&dea).map(|(d, e)| 2.0 * (d - e)).collect();
+    SubPane {
+        name,
+        series: vec![
+            Series::line("DIF".to_string(), dif, [0.88, 0.88, 0.88, 1.0]),
+            Series::line("DEA".to_string(), dea, [0.94, 0.75, 0.25, 1.0]),
+            Series {
+                name: "MACD".to_string(),
+                values: histogram,
+                color: [1.0, 0.0, 0.0, 1.0],
+                style: SeriesStyle::Histogram {
+                    down_color: [0.0, 1.0, 0.0, 1.0],
+                },
+            },
+        ],
+        range: None,
+        reference_lines: vec![0.0],
+        visible: true,
+    }
+}
+
+//相对强弱指标，涨跌幅用Wilder平滑，初值为前period个涨跌幅的简单平均，和talib一致
+pub fn rsi(close: &[f64], period: usize) -> Vec<f64> {
+    let mut result = vec![f64::NAN; close.len()];
+    if period == 0 || close.len() <= period {
+        return result;
+    }
+    let mut gain = 0.0;
+    let mut loss = 0.0;
+    for i in 1..close.len() {
+        let change = close[i] - close[i - 1];
+        let (up, down) = (change.max(0.0), (-change).max(0.0));
+        if i <= period {
+            gain += up / period as f64;
+            loss += down / period as f64;
+        } else {
+            gain = (gain * (period - 1) as f64 + up) / period as f64;
+            loss = (loss * (period - 1) as f64 + down) / period as f64;
+        }
+        if i >= period {
+            result[i] = if gain + loss > 0.0 {
+                100.0 * gain / (gain + loss)
+            } else {
+                0.0
+            };
+        }
+    }
+    result
+}
+
+//未成熟随机值，收盘价在period根K线最高价和最低价之间的位置，0到100
+pub fn rsv(high: &[f64], low: &[f64], close: &[f64], period: usize) -> Vec<f64> {
+    let highest = highest(high, period);
+    let lowest = lowest(low, period);
+    close
+        .iter()
+        .zip(highest.iter().zip(&lowest))
+        .map(|(c, (h, l))| {
+            if h > l {
+                (c - l) / (h - l) * 100.0
+            } else if h == l {
+                50.0
+            } else {
+                f64::NAN
+            }
+        })
+        .collect()
+}
+
+//KDJ：K为RSV的1/m1平滑，D为K的1/m2平滑，初值都是50，J = 3K - 2D
+pub fn kdj(
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    period: usize,
+    m1: usize,
+    m2: usize,
+) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let rsv = rsv(high, low, close, period);
+    let mut k = vec![f64::NAN; rsv.len()];
+    let mut d = vec![f64::NAN; rsv.len()];
+    let mut j = vec![f64::NAN; rsv.len()];
+    let (m1, m2) = (m1.max(1) as f64, m2.max(1) as f64);
+    let (mut k_value, mut d_value) = (50.0, 50.0);
+    for i in 0..rsv.len() {
+        if rsv[i].is_nan() {
+            continue;
+        }
+        k_value += (rsv[i] - k_value) / m1;
+        d_value += (k_value - d_value) / m2;
+        k[i] = k_value;
+        d[i] = d_value;
+        j[i] = 3.0 * k_value - 2.0 * d_value;
+    }
+    (k, d, j)
+}
+
+//慢速随机指标：%K为RSV的SMA，%D为%K的SMA
+pub fn stochastic(
+    high: &[f64],
+    low: &[f64],
+    close: &[f64],
+    period: usize,
+    k_period: usize,
+    d_period: usize,
+) -> (Vec<f64>, Vec<f64>) {
+    let k = sma(&rsv(high, low, close, period), k_period);
+    let d = sma(&k, d_period);
+    (k, d)
+}
+
+//0到100的摆动指标副图
+fn oscillator(name: String, series: Vec<Series>, reference_lines: Vec<f64>) -> SubPane {
+    SubPane {
+        name,
+        series,
+        range: Some((0.0, 100.0)),
+        reference_lines,
+        visible: true,
+    }
+}
+
+//副图指标，配置在[pane]节，每行“名称 = 类型, 参数..., hide”，末尾写hide则启动时隐藏
+//类型和参数：MACD, 快线, 慢线, 信号线；RSI, 周期；KDJ, 周期, M1, M2；STOCH, 周期, %K周期, %D周期；
+//ATR, 周期；HV, 周期, 每年K线数；格式错误的行跳过
+pub fn sub_panes() -> Vec<SubPane> {
+    let default_items = [
+        ("MACD".to_string(), "MACD, 12, 26, 9".to_string()),
+        ("RSI".to_string(), "RSI, 14, hide".to_string()),
+        ("KDJ".to_string(), "KDJ, 9, 3, 3, hide".to_string()),
+        ("STOCH".to_string(), "STOCH, 14, 3, 3, hide".to_string()),
+        ("ATR".to_string(), "ATR, 14, hide".to_string()),
+        ("HV".to_string(), "HV, 20, 252, hide".to_string()),
+    ];
+    let items = CONFIG.section("pane").unwrap_or(&default_items);
+    items
+        .iter()
+        .filter_map(|(name, value)| {
+            let pane = sub_pane(name, value);
+            if pane.is_none() {
+                config_error(format!("副图配置“{} = {}”格式错误，已跳过", name, value));
+            }
+            pane
+        })
+        .collect()
+}
+
+fn sub_pane(name: &str, value: &str) -> Option<SubPane> {
+    let mut fields: Vec<&str> = value.split(',').map(str::trim).collect();
+    let visible = !fields
+        .last()
+        .is_some_and(|field| field.eq_ignore_ascii_case("hide"));
+    if !visible {
+        fields.pop();
+    }
+    let kind = fields.first()?.to_uppercase();
+    let params = fields[1..]
+        .iter()
+        .map(|text| text.parse::<usize>().ok().filter(|param| *param > 0))
+        .collect::<Option<Vec<usize>>>()?;
+    let name = format!("{}({})", name, fields[1..].join(","));
+    let (high, low, close) = (
+        &HISTORY.high_price,
+        &HISTORY.low_price,
+        &HISTORY.close_price,
+    );
+    let mut pane = match (kind.as_str(), params.as_slice()) {
+        ("MACD", [fast, slow, signal]) => macd(name, close, *fast, *slow, *signal),
+        ("RSI", [period]) => oscillator(
+            name,
+            vec![Series::line(
+                "RSI".to_string(),
+                rsi(close, *period),
+                [0.94, 0.75, 0.25, 1.0],
+            )],
+            vec![30.0, 70.0],
+        ),
+        ("KDJ", [period, m1, m2]) => {
+            let (k, d, j) = kdj(high, low, close, *period, *m1, *m2);
+            oscillator(
+                name,
+                vec![
+                    Series::line("K".to_string(), k, [0.88, 0.88, 0.88, 1.0]),
+                    Series::line("D".to_string(), d, [0.94, 0.75, 0.25, 1.0]),
+                    Series::line("J".to_string(), j, [0.75, 0.5, 1.0, 1.0]),
+                ],
+                vec![20.0, 80.0],
+            )
+        }
+        ("STOCH", [period, k_period, d_period]) => {
+            let (k, d) = stochastic(high, low, close, *period, *k_period, *d_period);
+            oscillator(
+                name,
+                vec![
+                    Series::line("%K".to_string(), k, [0.88, 0.88, 0.88, 1.0]),
+                    Series::line("%D".to_string(), d, [0.94, 0.75, 0.25, 1.0]),
+                ],
+                vec![20.0, 80.0],
+            )
+        }
+        ("ATR", [period]) => SubPane {
+            name,
+            series: vec![
+                Series {
+                    name: "TR".to_string(),
+                    values: true_range(high, low, close),
+                    color: [0.5, 0.5, 0.5, 1.0],
+                    style: SeriesStyle::Histogram {
+                        down_color: [0.5, 0.5, 0.5, 1.0],
+                    },
+                },
+                Series::line(
+                    "ATR".to_string(),
+                    atr(high, low, close, *period),
+                    [0.94, 0.75, 0.25, 1.0],
+                ),
+            ],
+            range: None,
+            reference_lines: Vec::new(),
+            visible: true,
+        },
+        ("HV", [period, bars_per_year]) => SubPane {
+            name,
+            series: vec![Series::line(
+                "HV%".to_string(),
+                historical_volatility(close, *period, *bars_per_year),
+                [0.25, 0.75, 1.0, 1.0],
+            )],
+            range: None,
+            reference_lines: Vec::new(),
+            visible: true,
+        },
+        _ => return None,
+    };
+    pane.visible = visible;
+    Some(pane)
+}
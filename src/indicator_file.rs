@@ -0,0 +1,250 @@
+use crate::config::{config_error, parse_color};
+use crate::indicator::{Series, SeriesStyle, SubPane};
+use crate::manager::{get_home_path, timestamp_to_datetime, HISTORY};
+
+//画在K线图上的指标所属的pane名
+const MAIN_PANE: &str = "main";
+
+//策略输出的指标序列，格式为csv，开头几行是表头，第一列为行名：
+//name行是每列的名称，必须有；pane行是所在的图，main表示画在K线图上，其他名称会新建副图，同名的列画在同一个副图；
+//style行是画法，可以是line、histogram或dots；color行是颜色，柱状图可以写“正值颜色/负值颜色”
+//之后每行第一列是K线的Unix时间戳（秒），和K线时间对不上的行会被跳过，空格子表示没有值
+//返回每列所在的pane和序列，以及跳过的行数
+fn parse_indicator_csv(
+    text: &str,
+    bar_count: usize,
+    bar_ix: impl Fn(u64) -> Option<usize>,
+) -> Result<(Vec<(String, Series)>, usize), String> {
+    let mut names: Option<Vec<String>> = None;
+    let mut panes = Vec::new();
+    let mut styles = Vec::new();
+    let mut colors = Vec::new();
+    let mut values: Vec<Vec<f64>> = Vec::new();
+    let mut skipped = 0;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut cells = line.split(',').map(str::trim);
+        let key = cells.next().unwrap_or_default();
+        let cells: Vec<&str> = cells.collect();
+        if key.eq_ignore_ascii_case("name") {
+            values = vec![vec![f64::NAN; bar_count]; cells.len()];
+            names = Some(cells.iter().map(|cell| cell.to_string()).collect());
+            continue;
+        }
+        let Some(names) = &names else {
+            return Err(format!("第{}行之前缺少name行", i + 1));
+        };
+        if cells.len() > names.len() {
+            return Err(format!("第{}行的列数比name行多", i + 1));
+        }
+        match key.to_lowercase().as_str() {
+            "pane" => panes = cells.iter().map(|cell| cell.to_string()).collect(),
+            "style" => {
+                styles = cells
+                    .iter()
+                    .map(|cell| parse_style(cell).ok_or(format!("第{}行画法“{}”错误", i + 1, cell)))
+                    .collect::<Result<_, _>>()?
+            }
+            "color" => {
+                colors = cells
+                    .iter()
+                    .map(|cell| {
+                        parse_colors(cell).ok_or(format!("第{}行颜色“{}”错误", i + 1, cell))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            _ => {
+                let timestamp = key
+                    .parse::<u64>()
+                    .map_err(|_| format!("第{}行时间戳“{}”错误", i + 1, key))?;
+                let Some(ix) = bar_ix(timestamp) else {
+                    skipped += 1;
+                    continue;
+                };
+                for (column, cell) in cells.iter().enumerate() {
+                    if !cell.is_empty() {
+                        values[column][ix] = cell
+                            .parse::<f64>()
+                            .map_err(|_| format!("第{}行数值“{}”错误", i + 1, cell))?;
+                    }
+                }
+            }
+        }
+    }
+    let Some(names) = names else {
+        return Ok((Vec::new(), skipped));
+    };
+    let columns = names
+        .into_iter()
+        .zip(values)
+        .enumerate()
+        .map(|(column, (name, values))| {
+            let pane = panes
+                .get(column)
+                .filter(|pane| !pane.is_empty())
+                .cloned()
+                .unwrap_or(MAIN_PANE.to_string());
+            let (color, down_color) = colors.get(column).copied().unwrap_or(([1.0; 4], [1.0; 4]));
+            let style = match styles.get(column) {
+                Some(SeriesStyle::Histogram { .. }) => SeriesStyle::Histogram { down_color },
+                Some(style) => *style,
+                None => SeriesStyle::Line,
+            };
+            (
+                pane,
+                Series {
+                    name,
+                    values,
+                    color,
+                    style,
+                },
+            )
+        })
+        .collect();
+    Ok((columns, skipped))
+}
+
+fn parse_style(text: &str) -> Option<SeriesStyle> {
+    match text.to_lowercase().as_str() {
+        "" | "line" => Some(SeriesStyle::Line),
+        "histogram" => Some(SeriesStyle::Histogram {
+            down_color: [1.0; 4],
+        }),
+        "dots" => Some(SeriesStyle::Dots),
+        _ => None,
+    }
+}
+
+//“颜色”或“正值颜色/负值颜色”，空格子为白色
+fn parse_colors(text: &str) -> Option<([f32; 4], [f32; 4])> {
+    if text.is_empty() {
+        return Some(([1.0; 4], [1.0; 4]));
+    }
+    match text.split_once('/') {
+        Some((up, down)) => Some((parse_color(up)?, parse_color(down)?)),
+        None => {
+            let color = parse_color(text)?;
+            Some((color, color))
+        }
+    }
+}
+
+//读取家目录下的vnpyrs/indicators.csv，返回K线图上的指标和新建的副图，文件不存在或格式错误时都为空
+pub fn load_indicator_file() -> (Vec<Series>, Vec<SubPane>) {
+    let path = format!("{}/vnpyrs/indicators.csv", get_home_path());
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return (Vec::new(), Vec::new());
+    };
+    let columns = match parse_indicator_csv(&text, HISTORY.datetime.len(), |timestamp| {
+        HISTORY
+            .datetime
+            .binary_search(&timestamp_to_datetime(timestamp))
+            .ok()
+    }) {
+        Ok((columns, skipped)) => {
+            if skipped > 0 {
+                config_error(format!("指标文件有{}行的时间和K线对不上，已跳过", skipped));
+            }
+            columns
+        }
+        Err(err) => {
+            config_error(format!("指标文件{}{}，不画文件中的指标", path, err));
+            return (Vec::new(), Vec::new());
+        }
+    };
+    let mut overlays = Vec::new();
+    let mut sub_panes: Vec<SubPane> = Vec::new();
+    for (pane, series) in columns {
+        if pane.eq_ignore_ascii_case(MAIN_PANE) {
+            overlays.push(series);
+        } else if let Some(sub_pane) = sub_panes.iter_mut().find(|sub_pane| sub_pane.name == pane) {
+            sub_pane.series.push(series);
+        } else {
+            sub_panes.push(SubPane {
+                name: pane,
+                series: vec![series],
+                range: None,
+                reference_lines: Vec::new(),
+                visible: true,
+            });
+        }
+    }
+    (overlays, sub_panes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //三根K线的时间戳为100、101、102
+    fn parse(text: &str) -> Result<(Vec<(String, Series)>, usize), String> {
+        parse_indicator_csv(text, 3, |timestamp| {
+            (100..103)
+                .contains(&timestamp)
+                .then(|| (timestamp - 100) as usize)
+        })
+    }
+
+    //NaN不等于自身，换成None再比较
+    fn values(series: &Series) -> Vec<Option<f64>> {
+        series
+            .values
+            .iter()
+            .map(|value| Some(*value).filter(|value| !value.is_nan()))
+            .collect()
+    }
+
+    #[test]
+    fn header_rows_and_values() {
+        let text = "name, MID, DIF, SIG
+pane, main, MACD
+style, line, histogram, dots
+color, #FF0000, #00FF00/#0000FF
+
+100, 1.5, , 2
+101, , -1, 3
+999, 7, 7, 7
+102, 2.5, 1,
+";
+        let (columns, skipped) = parse(text).unwrap();
+        assert_eq!(skipped, 1);
+        let panes: Vec<&str> = columns.iter().map(|(pane, _)| pane.as_str()).collect();
+        assert_eq!(panes, ["main", "MACD", "main"]);
+        let names: Vec<&str> = columns
+            .iter()
+            .map(|(_, series)| series.name.as_str())
+            .collect();
+        assert_eq!(names, ["MID", "DIF", "SIG"]);
+        let (mid, dif, sig) = (&columns[0].1, &columns[1].1, &columns[2].1);
+        assert!(mid.style == SeriesStyle::Line);
+        assert_eq!(mid.color, [1.0, 0.0, 0.0, 1.0]);
+        assert!(
+            dif.style
+                == SeriesStyle::Histogram {
+                    down_color: [0.0, 0.0, 1.0, 1.0]
+                }
+        );
+        assert_eq!(dif.color, [0.0, 1.0, 0.0, 1.0]);
+        assert!(sig.style == SeriesStyle::Dots);
+        assert_eq!(sig.color, [1.0; 4]);
+        assert_eq!(values(mid), [Some(1.5), None, Some(2.5)]);
+        assert_eq!(values(dif), [None, Some(-1.0), Some(1.0)]);
+        assert_eq!(values(sig), [Some(2.0), Some(3.0), None]);
+    }
+
+    #[test]
+    fn malformed_rows() {
+        let error = |text: &str| parse(text).err().unwrap();
+        assert_eq!(error("pane, main\nname, A"), "第1行之前缺少name行");
+        assert_eq!(error("name, A\n100, 1, 2"), "第2行的列数比name行多");
+        assert_eq!(error("name, A\nstyle, bar"), "第2行画法“bar”错误");
+        assert_eq!(error("name, A\ncolor, red"), "第2行颜色“red”错误");
+        assert_eq!(error("name, A\n\n9:00, 1"), "第3行时间戳“9:00”错误");
+        assert_eq!(error("name, A\n100, x"), "第2行数值“x”错误");
+        let (columns, skipped) = parse("").unwrap();
+        assert!(columns.is_empty());
+        assert_eq!(skipped, 0);
+    }
+}
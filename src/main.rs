@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use crate::app::App;
 use winit::error::EventLoopError;
 use winit::event_loop::{ControlFlow, EventLoop};
@@ -6,8 +8,42 @@ mod app;
 mod wgpu_ctx;
 mod vertex;
 mod manager;
+mod export;
+mod config;
+mod indicator;
+mod indicator_file;
+mod formula;
+mod script;
+mod price_chart;
+mod profile;
+mod compare;
+mod spread;
+mod footprint;
+mod heatmap;
 
 fn main() -> Result<(), EventLoopError> {
+    //脚本的print和debug、配置错误写到日志，RUST_LOG=info时输出到终端
+    env_logger::init();
+    //vnpyrs-chart export [输出目录]：只导出交易对和统计，不打开窗口
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("export") {
+        let dir = args
+            .get(2)
+            .map(PathBuf::from)
+            .unwrap_or_else(export::default_export_dir);
+        match export::export_trade_pairs(&dir) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("已导出{}", path.display());
+                }
+            }
+            Err(err) => {
+                eprintln!("导出失败：{err}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Wait);
     let mut app = App::default();
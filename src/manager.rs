@@ -1,14 +1,33 @@
 use std::io::{BufReader, Read};
+use std::ops::Range;
 use std::sync::LazyLock;
 
-use chrono::{DateTime, Local, NaiveDateTime};
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta};
 use glam::Vec4;
 
-use crate::vertex::{CandleVertex, TradePairVertex, Vertex, VolumeVertex};
+use crate::compare::{CompareMode, CompareView, COMPARE};
+use crate::config::{config_error, take_config_errors, CONFIG};
+use crate::footprint::{FootprintCell, FOOTPRINT};
+use crate::formula::{config_formulas, FormulaDef};
+use crate::heatmap::{DEPTH_HEATMAP, HEATMAP_STYLE};
+use crate::indicator::{
+    anchored_vwap_overlay, atr, bands, format_value, moving_averages, sub_panes, vwap_overlay,
+    BandFill, Series, SubPane,
+};
+use crate::indicator_file::load_indicator_file;
+use crate::price_chart::{PriceChart, POINT_FIGURE, RANGE_BARS, RENKO};
+use crate::profile::{volume_profile, Profile, TpoSession, TPO_SESSIONS};
+use crate::script::{run_scripts, ScriptText};
+use crate::spread::SPREAD;
+use crate::vertex::{
+    BarVertex, CandleVertex, ColorVertex, FootprintVertex, OhlcVertex, OrderVertex, TpoVertex,
+    TradePairVertex, Vertex, VolumeVertex, BAR_ABOVE_PREV_CLOSE, BAR_DOWN, BAR_UP,
+};
 
 pub const MIN_BAR_COUNT: i64 = 50;
+const FOOTPRINT_MIN_BAR_COUNT: i64 = 8;
 
-fn get_home_path() -> String {
+pub fn get_home_path() -> String {
     let win = std::env::var("USERPROFILE");
     let unix = std::env::var("HOME");
     if win.is_ok() {
@@ -28,11 +47,208 @@ pub struct Manager {
     pub max_price_view: f64,
     pub max_volume_view: f64,
     pub cursor_ix: i64,
+    pub cursor_brick: usize, //不按时间画的图中光标所在的砖块或列
     pub cursor_price: Option<f64>,
     pub cursor_volume: Option<f64>,
     pub current_cursor_position: (f64, f64),
     pub pressed_position: Option<(f64, f64)>,
     pub pressed_left_right_ix: Option<(i64, i64)>,
+    pub show_trade_list: bool,
+    pub trade_sort: TradeSort,
+    pub trade_list_order: Vec<usize>,
+    pub trade_list_scroll: usize,
+    pub selected_trade: Option<usize>,
+    pub hover_trade: Option<(usize, TradeHover)>,
+    pub show_orders: bool,
+    pub show_atr_stop: bool,
+    pub status_message: Option<String>,
+    pub overlays: Vec<Series>,
+    pub band_fills: Vec<BandFill>,
+    pub vwap_anchor: Option<(i64, usize)>, //锚定VWAP的起点和它在overlays中的位置
+    pub sub_panes: Vec<SubPane>,
+    pub sub_views: Vec<Option<(f64, f64)>>, //每个副图可见范围的最小值和最大值，隐藏的副图为None
+    pub cursor_sub: Option<(usize, f64)>,   //光标所在的副图和对应的值
+    pub formula_input: Option<String>,      //正在输入的公式，不在输入状态时为None
+    pub formula_count: usize,
+    pub script_overlays: Range<usize>, //脚本输出在overlays中的位置，重新加载时整段替换
+    pub script_sub_panes: Range<usize>,
+    pub script_texts: Vec<ScriptText>,
+    pub chart_style: ChartStyle,
+    pub price_scale: PriceScale,
+    pub show_volume_profile: bool,
+    pub volume_profile: Option<Profile>,
+    pub volume_profile_key: Option<(i64, i64)>, //算成交量分布时的可见范围
+    pub compare_mode: Option<CompareMode>,      //对比品种的画法，None为不画
+    pub compare_view: Option<CompareView>,
+    pub show_depth_heatmap: bool, //有盘口快照时默认显示
+}
+
+//主图的画法：K线、收盘价折线、面积图、美国线
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ChartStyle {
+    #[default]
+    Candle,
+    HeikinAshi,
+    Line,
+    Area,
+    Ohlc,
+    Renko,
+    RangeBar,
+    PointFigure,
+    Tpo,
+}
+
+impl ChartStyle {
+    //配置在[chart]节的style，默认为K线
+    fn from_config() -> Self {
+        let style = CONFIG.parse_or("chart", "style", ChartStyle::Candle, |text| match text {
+            "candle" => Some(ChartStyle::Candle),
+            "heikin_ashi" => Some(ChartStyle::HeikinAshi),
+            "line" => Some(ChartStyle::Line),
+            "area" => Some(ChartStyle::Area),
+            "ohlc" => Some(ChartStyle::Ohlc),
+            "renko" => Some(ChartStyle::Renko),
+            "range_bar" => Some(ChartStyle::RangeBar),
+            "point_figure" => Some(ChartStyle::PointFigure),
+            "tpo" => Some(ChartStyle::Tpo),
+            _ => None,
+        });
+        if !style.is_available() {
+            if style == ChartStyle::Tpo {
+                config_error(format!("不是日内数据，画不了{}，改为画K线", style.name()));
+            } else {
+                config_error(format!("价格变动太小，生成不了{}，改为画K线", style.name()));
+            }
+            return ChartStyle::Candle;
+        }
+        style
+    }
+
+    //切换到下一种画法，跳过价格变动太小、一块砖也生成不了的图
+    pub fn next(self) -> Self {
+        let mut style = self;
+        loop {
+            style = match style {
+                ChartStyle::Candle => ChartStyle::HeikinAshi,
+                ChartStyle::HeikinAshi => ChartStyle::Line,
+                ChartStyle::Line => ChartStyle::Area,
+                ChartStyle::Area => ChartStyle::Ohlc,
+                ChartStyle::Ohlc => ChartStyle::Renko,
+                ChartStyle::Renko => ChartStyle::RangeBar,
+                ChartStyle::RangeBar => ChartStyle::PointFigure,
+                ChartStyle::PointFigure => ChartStyle::Tpo,
+                ChartStyle::Tpo => ChartStyle::Candle,
+            };
+            if style.is_available() {
+                return style;
+            }
+        }
+    }
+
+    //市场轮廓要有至少一个交易日分成多个时段
+    fn is_available(self) -> bool {
+        if self == ChartStyle::Tpo {
+            return TPO_SESSIONS.iter().any(|session| session.periods.len() > 1);
+        }
+        self.price_chart()
+            .is_none_or(|chart| !chart.history.close_price.is_empty())
+    }
+
+    //不按时间画的图，横轴为砖块或列的序号
+    pub fn price_chart(self) -> Option<&'static PriceChart> {
+        match self {
+            ChartStyle::Renko => Some(&RENKO),
+            ChartStyle::RangeBar => Some(&RANGE_BARS),
+            ChartStyle::PointFigure => Some(&POINT_FIGURE),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChartStyle::Candle => "K线",
+            ChartStyle::HeikinAshi => "平均K线",
+            ChartStyle::Line => "折线",
+            ChartStyle::Area => "面积图",
+            ChartStyle::Ohlc => "美国线",
+            ChartStyle::Renko => "砖块图",
+            ChartStyle::RangeBar => "等幅K线",
+            ChartStyle::PointFigure => "点数图",
+            ChartStyle::Tpo => "市场轮廓",
+        }
+    }
+}
+
+//K线图的价格纵轴：线性、对数、相对第一根可见K线收盘价的涨跌幅
+//涨跌幅和价格是线性关系，只有刻度和光标上的文字不同
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum PriceScale {
+    #[default]
+    Linear,
+    Log,
+    Percent,
+}
+
+impl PriceScale {
+    //配置在[chart]节的scale，默认为线性
+    fn from_config() -> Self {
+        let scale = CONFIG.parse_or("chart", "scale", PriceScale::Linear, |text| match text {
+            "linear" => Some(PriceScale::Linear),
+            "log" => Some(PriceScale::Log),
+            "percent" => Some(PriceScale::Percent),
+            _ => None,
+        });
+        if !scale.is_available() {
+            config_error("有小于等于0的价格，不能用对数纵轴，改为线性".to_string());
+            return PriceScale::Linear;
+        }
+        scale
+    }
+
+    //切换到下一种纵轴，有小于等于0的价格时跳过对数
+    pub fn next(self) -> Self {
+        let mut scale = self;
+        loop {
+            scale = match scale {
+                PriceScale::Linear => PriceScale::Log,
+                PriceScale::Log => PriceScale::Percent,
+                PriceScale::Percent => PriceScale::Linear,
+            };
+            if scale.is_available() {
+                return scale;
+            }
+        }
+    }
+
+    fn is_available(self) -> bool {
+        self != PriceScale::Log || HISTORY.low_price.iter().all(|price| *price > 0.0)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PriceScale::Linear => "线性",
+            PriceScale::Log => "对数",
+            PriceScale::Percent => "涨跌幅",
+        }
+    }
+}
+
+//交易列表的排序方式
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum TradeSort {
+    #[default]
+    TimeAsc,
+    TimeDesc,
+    PnlAsc,
+    PnlDesc,
+}
+
+//鼠标悬停在交易对的开仓三角、平仓三角或连线上
+#[derive(Clone, Copy, PartialEq)]
+pub enum TradeHover {
+    Open,
+    Close,
+    Line,
 }
 
 impl Manager {
@@ -41,20 +257,319 @@ impl Manager {
             panic!("没有数据");
         }
         let right_ix = HISTORY.high_price.len() as i64 - 1;
-        Manager {
+        let mut overlays = moving_averages();
+        let band_fills = bands(&mut overlays);
+        overlays.extend(vwap_overlay());
+        //策略输出的指标
+        let (file_overlays, file_sub_panes) = load_indicator_file();
+        overlays.extend(file_overlays);
+        let mut sub_panes = sub_panes();
+        sub_panes.extend(file_sub_panes);
+        let formula_count = config_formulas(&mut overlays, &mut sub_panes);
+        let mut manager = Manager {
             right_ix,
+            trade_list_order: (0..TRADE_PAIRS.len()).collect(),
+            show_orders: true,
+            script_overlays: overlays.len()..overlays.len(),
+            script_sub_panes: sub_panes.len()..sub_panes.len(),
+            overlays,
+            band_fills,
+            sub_panes,
+            formula_count,
+            chart_style: ChartStyle::from_config(),
+            price_scale: PriceScale::from_config(),
+            compare_mode: COMPARE.as_ref().map(|compare| compare.mode),
+            show_depth_heatmap: DEPTH_HEATMAP.is_some(),
             ..Default::default()
+        };
+        let errors = manager.load_scripts();
+        if !errors.is_empty() {
+            manager.status_message = Some(format!("脚本出错：{}", errors.join("；")));
+        }
+        manager.report_config_errors();
+        manager
+    }
+
+    //chart.ini和indicators.csv的错误在用到时才发现，出错的项已经用默认值代替，这里接在状态提示后面
+    pub fn report_config_errors(&mut self) {
+        let errors = take_config_errors();
+        if errors.is_empty() {
+            return;
+        }
+        let message = format!("配置出错：{}", errors.join("；"));
+        self.status_message = Some(match self.status_message.take() {
+            Some(status_message) => format!("{}  {}", status_message, message),
+            None => message,
+        });
+    }
+
+    //运行脚本，替换上次脚本的输出，返回出错信息
+    //锚定VWAP和运行时输入的公式在脚本输出后面，锚定VWAP的位置要跟着移动
+    fn load_scripts(&mut self) -> Vec<String> {
+        let output = run_scripts();
+        let old_end = self.script_overlays.end;
+        let start = self.script_overlays.start;
+        let count = output.overlays.len();
+        self.overlays
+            .splice(self.script_overlays.clone(), output.overlays);
+        self.script_overlays = start..start + count;
+        if let Some((_, position)) = &mut self.vwap_anchor {
+            if *position >= old_end {
+                *position = *position - old_end + start + count;
+            }
+        }
+        let start = self.script_sub_panes.start;
+        let count = output.sub_panes.len();
+        self.sub_panes
+            .splice(self.script_sub_panes.clone(), output.sub_panes);
+        self.script_sub_panes = start..start + count;
+        self.script_texts = output.texts;
+        output.errors
+    }
+
+    pub fn reload_scripts(&mut self) {
+        let errors = self.load_scripts();
+        self.status_message = Some(if errors.is_empty() {
+            "脚本已重新加载".to_string()
+        } else {
+            format!("脚本出错：{}", errors.join("；"))
+        });
+    }
+
+    //图例和信息栏显示光标所在K线的指标值，没有光标时显示最右边的K线
+    pub fn legend_ix(&self, cursor_show: bool) -> i64 {
+        if cursor_show {
+            self.cursor_ix
+        } else {
+            self.right_ix
+        }
+    }
+
+    //信息栏中K线价格之后的项数
+    pub fn info_item_count(&self) -> usize {
+        let chart_bar_count = if self.cursor_chart_bar().is_some() {
+            4
+        } else {
+            0
+        };
+        self.overlays.len() + chart_bar_count
+    }
+
+    //主图不是真实K线时，光标所在的平均K线、砖块或列，信息栏中显示它的价格
+    pub fn cursor_chart_bar(&self) -> Option<(&'static str, &'static HistoryData, usize)> {
+        let label = match self.chart_style {
+            ChartStyle::HeikinAshi => return Some(("HA", &HEIKIN_ASHI, self.cursor_ix as usize)),
+            ChartStyle::Renko => "Renko",
+            ChartStyle::RangeBar => "Range",
+            ChartStyle::PointFigure => "P&F",
+            _ => return None,
+        };
+        let chart = self.chart_style.price_chart()?;
+        let brick = self.cursor_brick.min(chart.brick_bar.len() - 1);
+        Some((label, &chart.history, brick))
+    }
+
+    //光标移到主图横轴上的x处，不按时间画的图x是砖块或列，光标所在K线取它完成时的K线
+    //最右边的砖块可能在可见范围之后才完成，K线限制在可见范围内
+    pub fn set_cursor_x(&mut self, x: i64) {
+        match self.chart_style.price_chart() {
+            Some(chart) => {
+                self.cursor_brick = (x.max(0) as usize).min(chart.brick_bar.len() - 1);
+                self.cursor_ix =
+                    (chart.brick_bar[self.cursor_brick] as i64).clamp(self.left_ix, self.right_ix);
+            }
+            None => self.cursor_ix = x.min(HISTORY.datetime.len() as i64 - 1),
+        }
+    }
+
+    //主图的横轴范围，不按时间画的图为可见K线所在的砖块或列
+    pub fn chart_x_range(&self) -> (i64, i64) {
+        match self.chart_style.price_chart() {
+            Some(chart) => (
+                chart.bar_brick[self.left_ix as usize] as i64,
+                chart.bar_brick[self.right_ix as usize] as i64,
+            ),
+            None => (self.left_ix, self.right_ix),
+        }
+    }
+
+    //和可见范围有交集的交易日
+    pub fn visible_tpo_sessions(&self) -> &'static [TpoSession] {
+        let sessions = TPO_SESSIONS.as_slice();
+        let first = sessions.partition_point(|session| session.bars.end as i64 <= self.left_ix);
+        let last = sessions.partition_point(|session| session.bars.start as i64 <= self.right_ix);
+        &sessions[first..last.max(first)]
+    }
+
+    //市场轮廓放大到每个字母的方块能放下一个字时画字母，否则画方块
+    pub fn tpo_letters_fit(&self, width: f32, height: f32) -> bool {
+        let bar_width = width as f64 / (self.right_ix - self.left_ix + 1) as f64;
+        self.visible_tpo_sessions().iter().all(|session| {
+            let profile = &session.profile;
+            let row_height = (self.price_fraction(profile.row_low(profile.poc + 1))
+                - self.price_fraction(profile.row_low(profile.poc)))
+                * height as f64;
+            session.column_width() * bar_width >= 10.0 && row_height >= 14.0
+        })
+    }
+
+    //有逐笔成交时画K线的地方改画足迹图，要放大到每根K线能放下格子里的文字，否则照常画K线
+    pub fn footprint_fits(&self, width: f32, height: f32) -> bool {
+        let Some(footprint) = FOOTPRINT.as_ref() else {
+            return false;
+        };
+        if self.chart_style != ChartStyle::Candle {
+            return false;
+        }
+        let bar_width = width as f64 / (self.right_ix - self.left_ix + 1) as f64;
+        let row_height = (self.price_fraction(self.max_price_view)
+            - self.price_fraction(self.max_price_view - footprint.row_size))
+            * height as f64;
+        if bar_width < 40.0 || row_height < 14.0 {
+            return false;
+        }
+        //“×”按两个字宽算
+        let chars = footprint.bars[self.left_ix as usize..=self.right_ix as usize]
+            .iter()
+            .flatten()
+            .map(|cell| cell.text().chars().count() + 1)
+            .max()
+            .unwrap_or(0);
+        bar_width * 0.8 >= chars as f64 * 8.0 + 4.0
+    }
+
+    //K线图纵轴上的位置（0为底，1为顶）对应的价格
+    pub fn price_at(&self, fraction: f64) -> f64 {
+        if self.price_scale == PriceScale::Log {
+            let min = self.min_price_view.ln();
+            let max = self.max_price_view.ln();
+            (min + fraction * (max - min)).exp()
+        } else {
+            self.min_price_view + fraction * (self.max_price_view - self.min_price_view)
+        }
+    }
+
+    //价格在K线图纵轴上的位置，price_at的反函数
+    pub fn price_fraction(&self, price: f64) -> f64 {
+        if self.price_scale == PriceScale::Log {
+            let min = self.min_price_view.ln();
+            let max = self.max_price_view.ln();
+            (price.ln() - min) / (max - min)
+        } else {
+            (price - self.min_price_view) / (self.max_price_view - self.min_price_view)
+        }
+    }
+
+    //纵轴刻度和光标上显示的价格，涨跌幅纵轴显示相对第一根可见K线收盘价的百分比
+    pub fn price_label(&self, price: f64) -> String {
+        match self.price_scale {
+            PriceScale::Linear => price.to_string(),
+            PriceScale::Log => format_value(price),
+            PriceScale::Percent => {
+                let (left, _) = self.chart_x_range();
+                let base = match self.chart_style.price_chart() {
+                    Some(chart) => chart.history.close_price[left as usize],
+                    None => HISTORY.close_price[left as usize],
+                };
+                format!("{:+.2}%", (price / base - 1.0) * 100.0)
+            }
         }
     }
 
     pub fn update_maxmin_by_left_right_ix(&mut self) {
         (self.min_price_view, self.max_price_view) = get_price_range(self.left_ix, self.right_ix);
+        if let Some(chart) = self.chart_style.price_chart() {
+            let (left, right) = self.chart_x_range();
+            (self.min_price_view, self.max_price_view) =
+                get_history_price_range(&chart.history, left, right);
+        }
+        //平均K线的开盘价可能超出真实K线的范围
+        if self.chart_style == ChartStyle::HeikinAshi {
+            let (min_price, max_price) =
+                get_history_price_range(&HEIKIN_ASHI, self.left_ix, self.right_ix);
+            self.min_price_view = self.min_price_view.min(min_price);
+            self.max_price_view = self.max_price_view.max(max_price);
+        }
+        self.update_compare_view();
         self.max_volume_view = get_volume_max(self.left_ix, self.right_ix);
+        self.sub_views = self
+            .sub_panes
+            .iter()
+            .map(|pane| {
+                pane.visible
+                    .then(|| pane.value_range(self.left_ix, self.right_ix))
+            })
+            .collect();
+        self.update_volume_profile();
+    }
+
+    //对比品种只画在按时间的主图上，按涨跌幅画时纵轴范围要包含它
+    fn update_compare_view(&mut self) {
+        self.compare_view = None;
+        let Some(compare) = COMPARE.as_ref() else {
+            return;
+        };
+        let Some(mode) = self.compare_mode else {
+            return;
+        };
+        if self.chart_style.price_chart().is_some() {
+            return;
+        }
+        let (left, right) = (self.left_ix as usize, self.right_ix as usize);
+        self.compare_view = compare.view(mode, left, right);
+        if let Some(CompareView::Percent { .. }) = self.compare_view {
+            for price in compare.close_price[left..=right].iter() {
+                if let Some(y) = self.compare_y(*price).filter(|y| y.is_finite()) {
+                    self.min_price_view = self.min_price_view.min(y);
+                    self.max_price_view = self.max_price_view.max(y);
+                }
+            }
+        }
+    }
+
+    //对比品种的价格换算成主图纵轴上的价格，副坐标轴上下各留5%
+    pub fn compare_y(&self, price: f64) -> Option<f64> {
+        match self.compare_view? {
+            CompareView::Percent { base, price: main } => Some(main * price / base),
+            CompareView::Axis { min, max } => {
+                let fraction = if max > min {
+                    (price - min) / (max - min)
+                } else {
+                    0.5
+                };
+                Some(self.price_at(0.05 + 0.9 * fraction))
+            }
+        }
+    }
+
+    //成交量分布按可见范围内的真实K线统计，平均K线和砖块图等也一样；可见范围没变时不重算
+    fn update_volume_profile(&mut self) {
+        if !self.show_volume_profile {
+            self.volume_profile = None;
+            self.volume_profile_key = None;
+            return;
+        }
+        let key = (self.left_ix, self.right_ix);
+        if self.volume_profile_key == Some(key) {
+            return;
+        }
+        self.volume_profile_key = Some(key);
+        self.volume_profile =
+            volume_profile(&HISTORY, self.left_ix as usize, self.right_ix as usize);
+    }
+
+    //有逐笔成交时可以放大到更少的K线，好看清足迹图
+    fn min_bar_count(&self) -> i64 {
+        if FOOTPRINT.is_some() {
+            FOOTPRINT_MIN_BAR_COUNT
+        } else {
+            MIN_BAR_COUNT
+        }
     }
 
     //放大
     pub fn zoom_in(&mut self) {
-        if self.right_ix - self.left_ix < MIN_BAR_COUNT {
+        if self.right_ix - self.left_ix < self.min_bar_count() {
             return;
         }
         let bar_count = self.right_ix - self.left_ix + 1;
@@ -70,7 +585,7 @@ impl Manager {
 
     //以cursor_ix为轴放大
     pub fn zoom_in_by(&mut self) {
-        if self.right_ix - self.left_ix < MIN_BAR_COUNT {
+        if self.right_ix - self.left_ix < self.min_bar_count() {
             return;
         }
         let bar_count_left = self.cursor_ix - self.left_ix + 1;
@@ -88,14 +603,88 @@ impl Manager {
         self.right_ix += (bar_count_right as f32 * 0.25) as i64;
         self.right_ix = self.right_ix.min(HISTORY.datetime.len() as i64 - 1);
     }
+
+    //交易列表排序
+    pub fn sort_trade_list(&mut self, trade_sort: TradeSort) {
+        self.trade_sort = trade_sort;
+        let mut order: Vec<usize> = (0..TRADE_PAIRS.len()).collect();
+        match trade_sort {
+            TradeSort::TimeAsc => order.sort_by_key(|i| TRADE_PAIRS[*i].open_ix),
+            TradeSort::TimeDesc => order.sort_by_key(|i| -TRADE_PAIRS[*i].open_ix),
+            TradeSort::PnlAsc => {
+                order.sort_by(|x, y| TRADE_PAIRS[*x].pnl().total_cmp(&TRADE_PAIRS[*y].pnl()))
+            }
+            TradeSort::PnlDesc => {
+                order.sort_by(|x, y| TRADE_PAIRS[*y].pnl().total_cmp(&TRADE_PAIRS[*x].pnl()))
+            }
+        }
+        self.trade_list_order = order;
+        self.trade_list_scroll = 0;
+    }
+
+    //交易列表滚动，visible_rows为可见行数
+    pub fn scroll_trade_list(&mut self, delta_rows: i64, visible_rows: usize) {
+        let max_scroll = self.trade_list_order.len().saturating_sub(visible_rows) as i64;
+        self.trade_list_scroll =
+            (self.trade_list_scroll as i64 + delta_rows).clamp(0, max_scroll) as usize;
+    }
+
+    //运行时输入的公式，加到副图时返回副图下标，加到K线图时返回None
+    pub fn add_formula(&mut self, input: &str) -> Result<Option<usize>, String> {
+        let formula = FormulaDef::parse_input(input)?;
+        let sub_pane_ix =
+            formula.place(&mut self.overlays, &mut self.sub_panes, self.formula_count);
+        self.formula_count += 1;
+        Ok(sub_pane_ix)
+    }
+
+    //锚定VWAP，右键点击的K线为起点，再点同一根K线取消
+    //取消时从overlays中删除，通道填充的下标都在它前面，不受影响
+    pub fn set_vwap_anchor(&mut self, ix: i64) {
+        match self.vwap_anchor {
+            Some((anchor, position)) if anchor == ix => {
+                self.overlays.remove(position);
+                self.vwap_anchor = None;
+            }
+            Some((_, position)) => {
+                self.overlays[position] = anchored_vwap_overlay(ix as usize);
+                self.vwap_anchor = Some((ix, position));
+            }
+            None => {
+                self.overlays.push(anchored_vwap_overlay(ix as usize));
+                self.vwap_anchor = Some((ix, self.overlays.len() - 1));
+            }
+        }
+    }
+
+    //选中交易对，并把它移到图表中央
+    pub fn select_trade(&mut self, pair_ix: usize) {
+        let d = &TRADE_PAIRS[pair_ix];
+        let last_ix = HISTORY.datetime.len() as i64 - 1;
+        let span = (self.right_ix - self.left_ix)
+            .max(d.holding_bars() + MIN_BAR_COUNT)
+            .min(last_ix);
+        let center = (d.open_ix + d.close_ix) / 2;
+        self.left_ix = (center - span / 2).max(0);
+        self.right_ix = self.left_ix + span;
+        if self.right_ix > last_ix {
+            self.right_ix = last_ix;
+            self.left_ix = self.right_ix - span;
+        }
+        self.selected_trade = Some(pair_ix);
+    }
 }
 
 pub fn get_price_range(left_ix: i64, right_ix: i64) -> (f64, f64) {
-    let max_price = *HISTORY.high_price[left_ix as usize..=right_ix as usize]
+    get_history_price_range(&HISTORY, left_ix, right_ix)
+}
+
+fn get_history_price_range(history: &HistoryData, left_ix: i64, right_ix: i64) -> (f64, f64) {
+    let max_price = *history.high_price[left_ix as usize..=right_ix as usize]
         .iter()
         .max_by(|x, y| x.partial_cmp(y).unwrap())
         .unwrap();
-    let min_price = *HISTORY.low_price[left_ix as usize..=right_ix as usize]
+    let min_price = *history.low_price[left_ix as usize..=right_ix as usize]
         .iter()
         .min_by(|x, y| x.partial_cmp(y).unwrap())
         .unwrap();
@@ -109,6 +698,7 @@ pub fn get_volume_max(left_ix: i64, right_ix: i64) -> f64 {
         .unwrap()
 }
 
+#[derive(Default)]
 pub struct HistoryData {
     // pub timestamp: Vec<u64>,
     pub datetime: Vec<NaiveDateTime>,
@@ -118,14 +708,44 @@ pub struct HistoryData {
     pub close_price: Vec<f64>,
     pub volume: Vec<f64>,
 }
-pub static HISTORY: LazyLock<HistoryData> = LazyLock::new(|| {
-    let home_path = get_home_path();
-    let mut reader = BufReader::new(
-        std::fs::File::open(format!("{}/vnpyrs/history.dat", home_path))
-            .expect(&format!("打开文件{}/vnpyrs/history.dat失败", home_path)),
-    );
+//配置了[spread]节时为两条腿合成的价差或比价
+pub static HISTORY: LazyLock<HistoryData> = LazyLock::new(|| match SPREAD.as_ref() {
+    Some(spread) => spread.take_history(),
+    None => read_history_file(&format!("{}/vnpyrs/history.dat", get_home_path())),
+});
+
+//最后一根K线的结束时间，K线周期取相邻K线时间差的最小值，只有一根K线时为None
+pub fn last_bar_end(datetime: &[NaiveDateTime]) -> Option<NaiveDateTime> {
+    let interval = datetime
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|interval| *interval > TimeDelta::zero())
+        .min()?;
+    Some(*datetime.last()? + interval)
+}
+
+//配置中的文件路径，相对路径按家目录下的vnpyrs文件夹
+pub fn data_file_path(text: &str) -> String {
+    if std::path::Path::new(text).is_absolute() {
+        text.to_string()
+    } else {
+        format!("{}/vnpyrs/{}", get_home_path(), text)
+    }
+}
+
+//配置中的文件路径去掉目录和扩展名，用作默认名称
+pub fn data_file_name(text: &str) -> String {
+    std::path::Path::new(text)
+        .file_stem()
+        .map_or(text.to_string(), |stem| stem.to_string_lossy().to_string())
+}
+
+//读取K线文件，格式同history.dat
+pub fn read_history_file(path: &str) -> HistoryData {
+    let mut reader =
+        BufReader::new(std::fs::File::open(path).expect(&format!("打开文件{}失败", path)));
     let mut buf = [0u8; 8];
-    let error_string = format!("读取文件{}/vnpyrs/history.dat失败", home_path);
+    let error_string = format!("读取文件{}失败", path);
     reader.read_exact(&mut buf).expect(&error_string); //读取版本号
     if u64::from_le_bytes(buf) != 0 {
         panic!("请升级版本");
@@ -171,7 +791,42 @@ pub static HISTORY: LazyLock<HistoryData> = LazyLock::new(|| {
         close_price,
         volume,
     }
-});
+}
+
+impl HistoryData {
+    //平均K线：收盘价为四价平均，开盘价为上一根平均K线开盘价和收盘价的平均，最高最低包含开盘收盘
+    pub fn heikin_ashi(&self) -> HistoryData {
+        let count = self.close_price.len();
+        let mut open_price: Vec<f64> = Vec::with_capacity(count);
+        let mut high_price: Vec<f64> = Vec::with_capacity(count);
+        let mut low_price: Vec<f64> = Vec::with_capacity(count);
+        let mut close_price: Vec<f64> = Vec::with_capacity(count);
+        for i in 0..count {
+            let close =
+                (self.open_price[i] + self.high_price[i] + self.low_price[i] + self.close_price[i])
+                    / 4.0;
+            let open = if i == 0 {
+                (self.open_price[0] + self.close_price[0]) / 2.0
+            } else {
+                (open_price[i - 1] + close_price[i - 1]) / 2.0
+            };
+            open_price.push(open);
+            high_price.push(self.high_price[i].max(open).max(close));
+            low_price.push(self.low_price[i].min(open).min(close));
+            close_price.push(close);
+        }
+        HistoryData {
+            datetime: self.datetime.clone(),
+            open_price,
+            high_price,
+            low_price,
+            close_price,
+            volume: self.volume.clone(),
+        }
+    }
+}
+
+pub static HEIKIN_ASHI: LazyLock<HistoryData> = LazyLock::new(|| HISTORY.heikin_ashi());
 
 const LONG: u8 = 1;
 const SHORT: u8 = 2;
@@ -186,15 +841,22 @@ pub struct TradeData {
 }
 
 pub static TRADES: LazyLock<Vec<TradeData>> = LazyLock::new(|| {
+    let trades = read_trades_file(&format!("{}/vnpyrs/trades.dat", get_home_path()));
+    //价差的主图上，加上两条腿的成交合成的价差成交
+    match SPREAD.as_ref() {
+        Some(spread) => spread.trades(trades),
+        None => trades,
+    }
+});
+
+//读取成交文件，格式同trades.dat
+pub fn read_trades_file(path: &str) -> Vec<TradeData> {
     let mut trades = Vec::new();
-    let home_path = get_home_path();
-    let mut reader = BufReader::new(
-        std::fs::File::open(format!("{}/vnpyrs/trades.dat", home_path))
-            .expect(&format!("打开文件{}/vnpyrs/trades.dat失败", home_path)),
-    );
+    let mut reader =
+        BufReader::new(std::fs::File::open(path).expect(&format!("打开文件{}失败", path)));
     let mut buf = [0u8; 8];
     let mut buf1 = [0u8; 1];
-    let error_string = format!("读取文件{}/vnpyrs/trades.dat失败", home_path);
+    let error_string = format!("读取文件{}失败", path);
     reader.read_exact(&mut buf).expect(&error_string); //读取版本号
     if u64::from_le_bytes(buf) != 0 {
         panic!("请升级版本");
@@ -227,8 +889,87 @@ pub static TRADES: LazyLock<Vec<TradeData>> = LazyLock::new(|| {
         });
     }
     trades
+}
+
+//委托状态
+pub const ORDER_WORKING: u8 = 1;
+pub const ORDER_PARTIAL: u8 = 2;
+pub const ORDER_FILLED: u8 = 3;
+//委托类型
+pub const ORDER_STOP: u8 = 2;
+
+#[derive(Clone, Copy)]
+pub struct OrderData {
+    pub submit_dt: NaiveDateTime,
+    //None表示到回测结束时仍未结束
+    pub end_dt: Option<NaiveDateTime>,
+    pub order_type: u8,
+    pub status: u8,
+    pub price: f64,
+}
+
+//委托文件可选，不存在时不画委托
+pub static ORDERS: LazyLock<Vec<OrderData>> = LazyLock::new(|| {
+    let mut orders = Vec::new();
+    let home_path = get_home_path();
+    let Ok(file) = std::fs::File::open(format!("{}/vnpyrs/orders.dat", home_path)) else {
+        return orders;
+    };
+    let mut reader = BufReader::new(file);
+    let mut buf = [0u8; 8];
+    let mut buf1 = [0u8; 1];
+    let error_string = format!("读取文件{}/vnpyrs/orders.dat失败", home_path);
+    reader.read_exact(&mut buf).expect(&error_string); //读取版本号
+    if u64::from_le_bytes(buf) != 0 {
+        panic!("请升级版本");
+    }
+    reader.read_exact(&mut buf).expect(&error_string);
+    let count = u64::from_le_bytes(buf);
+    for _ in 0..count {
+        reader.read_exact(&mut buf).expect(&error_string);
+        let submit_dt = timestamp_to_datetime(u64::from_le_bytes(buf));
+
+        reader.read_exact(&mut buf).expect(&error_string);
+        let end_timestamp = u64::from_le_bytes(buf);
+        let end_dt = if end_timestamp == 0 {
+            None
+        } else {
+            Some(timestamp_to_datetime(end_timestamp))
+        };
+
+        //方向暂不使用
+        reader.read_exact(&mut buf1).expect(&error_string);
+
+        reader.read_exact(&mut buf1).expect(&error_string);
+        let order_type = u8::from_le_bytes(buf1);
+
+        reader.read_exact(&mut buf1).expect(&error_string);
+        let status = u8::from_le_bytes(buf1);
+
+        reader.read_exact(&mut buf).expect(&error_string);
+        let price = f64::from_le_bytes(buf);
+
+        //数量暂不使用
+        reader.read_exact(&mut buf).expect(&error_string);
+
+        orders.push(OrderData {
+            submit_dt,
+            end_dt,
+            order_type,
+            status,
+            price,
+        });
+    }
+    orders
 });
 
+pub fn timestamp_to_datetime(timestamp: u64) -> NaiveDateTime {
+    let local_datetime: DateTime<Local> = DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap()
+        .into();
+    local_datetime.naive_local()
+}
+
 pub struct TradePair {
     pub open_dt: NaiveDateTime,
     pub open_price: f64,
@@ -236,6 +977,93 @@ pub struct TradePair {
     pub close_price: f64,
     pub direction: u8,
     pub volume: f64,
+    pub open_ix: i64,
+    pub close_ix: i64,
+}
+
+impl TradePair {
+    pub fn is_long(&self) -> bool {
+        self.direction == LONG
+    }
+
+    //按价格计算的盈亏，未乘合约乘数
+    pub fn pnl(&self) -> f64 {
+        if self.direction == LONG {
+            (self.close_price - self.open_price) * self.volume
+        } else {
+            (self.open_price - self.close_price) * self.volume
+        }
+    }
+
+    pub fn holding_bars(&self) -> i64 {
+        self.close_ix - self.open_ix
+    }
+
+    pub fn holding_duration(&self) -> String {
+        format_duration(self.close_dt - self.open_dt)
+    }
+
+    fn action_name(&self, is_open: bool) -> &'static str {
+        match (self.is_long(), is_open) {
+            (true, true) => "买入开仓Buy",
+            (true, false) => "卖出平仓Sell",
+            (false, true) => "卖出开仓Short",
+            (false, false) => "买入平仓Cover",
+        }
+    }
+
+    fn leg_text(&self, is_open: bool) -> String {
+        let (datetime, price) = if is_open {
+            (self.open_dt, self.open_price)
+        } else {
+            (self.close_dt, self.close_price)
+        };
+        format!(
+            "{}\n时间 {}\n价格 {}\n数量 {}",
+            self.action_name(is_open),
+            datetime.format("%Y-%m-%d %H:%M"),
+            price,
+            self.volume
+        )
+    }
+
+    //悬停提示的文字
+    pub fn tooltip(&self, hover: TradeHover) -> String {
+        let legs = match hover {
+            TradeHover::Open => format!("{}\n对应 {}", self.leg_text(true), self.leg_text(false)),
+            TradeHover::Close => format!("{}\n对应 {}", self.leg_text(false), self.leg_text(true)),
+            TradeHover::Line => format!(
+                "{}\n{}\n{}",
+                if self.pnl() >= 0.0 {
+                    "盈利交易"
+                } else {
+                    "亏损交易"
+                },
+                self.leg_text(true),
+                self.leg_text(false)
+            ),
+        };
+        format!(
+            "{}\n盈亏 {:.2}\n持仓 {}根K线 {}",
+            legs,
+            self.pnl(),
+            self.holding_bars(),
+            self.holding_duration()
+        )
+    }
+}
+
+pub fn format_duration(duration: TimeDelta) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    if days > 0 {
+        format!("{}天{}小时{}分", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}小时{}分", hours, minutes)
+    } else {
+        format!("{}分", minutes)
+    }
 }
 
 pub const MY_EPSILON: f64 = 0.0000000001;
@@ -268,6 +1096,8 @@ pub fn generate_trade_pairs() -> Vec<TradePair> {
                 close_price: trade.price,
                 direction: open_trade.direction,
                 volume: close_volume,
+                open_ix: -1,
+                close_ix: -1,
             };
             trade_pairs.push(d);
 
@@ -286,209 +1116,406 @@ pub fn generate_trade_pairs() -> Vec<TradePair> {
     trade_pairs
 }
 
-pub static TRADE_PAIRS: LazyLock<Vec<TradePair>> = LazyLock::new(|| generate_trade_pairs());
+pub static TRADE_PAIRS: LazyLock<Vec<TradePair>> = LazyLock::new(|| {
+    let mut trade_pairs = generate_trade_pairs();
+    let mut start_ix = 0;
+    for d in trade_pairs.iter_mut() {
+        d.open_ix = search_ix_by_dt(d.open_dt, start_ix);
+        assert!(d.open_ix >= 0);
+        d.close_ix = search_ix_by_dt(d.close_dt, start_ix);
+        assert!(d.close_ix >= 0);
+        start_ix = d.open_ix as usize;
+    }
+    trade_pairs
+});
+
+pub static CANDLE_VERTEX: LazyLock<CandleVertex> = LazyLock::new(|| make_candle_vertex(&HISTORY));
+
+pub static HEIKIN_ASHI_VERTEX: LazyLock<CandleVertex> =
+    LazyLock::new(|| make_candle_vertex(&HEIKIN_ASHI));
+
+pub fn make_candle_vertex(history: &HistoryData) -> CandleVertex {
+    let mut vertex = CandleVertex::default();
+    for i in 0..history.close_price.len() {
+        let open_price = history.open_price[i];
+        let close_price = history.close_price[i];
+        //第一根没有上一根，和开盘价比
+        let prev_close = if i > 0 {
+            history.close_price[i - 1]
+        } else {
+            open_price
+        };
+        let flags = bar_flags(open_price, close_price, prev_close);
+        let x = i as f32;
+        let top = open_price.max(close_price);
+        let bottom = open_price.min(close_price);
+        push_bar_body(
+            &mut vertex.body,
+            &mut vertex.outline,
+            x,
+            bottom as f32,
+            top as f32,
+            flags,
+        );
+        //影线只画到实体边上，空心时不穿过实体
+        let point = |y: f64| BarVertex {
+            position: [x, y as f32],
+            offset: 0.0,
+            flags,
+        };
+        vertex.wick.extend(&[
+            point(history.high_price[i]),
+            point(top),
+            point(bottom),
+            point(history.low_price[i]),
+        ]);
+    }
+    vertex
+}
+
+fn bar_flags(open_price: f64, close_price: f64, prev_close: f64) -> u32 {
+    let mut flags = if close_price > open_price {
+        BAR_UP
+    } else if close_price < open_price {
+        BAR_DOWN
+    } else {
+        0
+    };
+    if close_price > prev_close {
+        flags |= BAR_ABOVE_PREV_CLOSE;
+    }
+    flags
+}
 
-pub static CANDLE_VERTEX: LazyLock<CandleVertex> = LazyLock::new(|| {
-    let mut open_iter = HISTORY.open_price.iter();
-    let mut high_iter = HISTORY.high_price.iter();
-    let mut low_iter = HISTORY.low_price.iter();
-    let mut close_iter = HISTORY.close_price.iter();
+//实体的6个顶点为两个三角形，边框的8个顶点按LineList围一圈，左右边在着色器里展开
+fn push_bar_body(
+    body: &mut Vec<BarVertex>,
+    outline: &mut Vec<BarVertex>,
+    x: f32,
+    bottom: f32,
+    top: f32,
+    flags: u32,
+) {
+    let point = |offset: f32, y: f32| BarVertex {
+        position: [x, y],
+        offset,
+        flags,
+    };
+    body.extend(&[
+        point(-1.0, top),
+        point(-1.0, bottom),
+        point(1.0, top),
+        point(1.0, top),
+        point(-1.0, bottom),
+        point(1.0, bottom),
+    ]);
+    outline.extend(&[
+        point(-1.0, top),
+        point(1.0, top),
+        point(1.0, top),
+        point(1.0, bottom),
+        point(1.0, bottom),
+        point(-1.0, bottom),
+        point(-1.0, bottom),
+        point(-1.0, top),
+    ]);
+}
+
+//收盘价折线，按LineStrip画
+pub static CLOSE_LINE_VERTEX: LazyLock<Vec<Vertex>> = LazyLock::new(|| {
+    HISTORY
+        .close_price
+        .iter()
+        .enumerate()
+        .map(|(i, close_price)| Vertex {
+            position: [i as f32, *close_price as f32],
+        })
+        .collect()
+});
+
+//面积图，按TriangleStrip画，收盘价和全部K线的最低价交替，填充到图的底部
+pub static AREA_VERTEX: LazyLock<Vec<Vertex>> = LazyLock::new(|| {
+    let base = HISTORY
+        .low_price
+        .iter()
+        .fold(f64::INFINITY, |min, low_price| min.min(*low_price)) as f32;
+    let mut area = Vec::new();
+    for (i, close_price) in HISTORY.close_price.iter().enumerate() {
+        area.extend(&[
+            Vertex {
+                position: [i as f32, *close_price as f32],
+            },
+            Vertex {
+                position: [i as f32, base],
+            },
+        ]);
+    }
+    area
+});
+
+//美国线：最高到最低的竖线，左边横线为开盘价，右边横线为收盘价
+pub static OHLC_VERTEX: LazyLock<OhlcVertex> = LazyLock::new(|| {
     let mut up = Vec::new();
     let mut down = Vec::new();
-    let mut down_hl = Vec::new();
     let mut stay = Vec::new();
-    let mut i = 0f32;
-    while let Some(open_price) = open_iter.next() {
-        let high_price = high_iter.next().unwrap();
-        let low_price = low_iter.next().unwrap();
-        let close_price = close_iter.next().unwrap();
-        if close_price > open_price {
-            up.extend(&[
-                Vertex {
-                    position: [i - 0.4, *close_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *close_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *close_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i - 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i - 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i - 0.4, *close_price as f32],
-                },
-                Vertex {
-                    position: [i, *high_price as f32],
-                },
-                Vertex {
-                    position: [i, *close_price as f32],
-                },
-                Vertex {
-                    position: [i, *low_price as f32],
-                },
-                Vertex {
-                    position: [i, *open_price as f32],
-                },
-            ]);
+    for i in 0..HISTORY.close_price.len() {
+        let open_price = HISTORY.open_price[i];
+        let close_price = HISTORY.close_price[i];
+        let target = if close_price > open_price {
+            &mut up
         } else if close_price < open_price {
-            down.extend(&[
-                Vertex {
-                    position: [i - 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i - 0.4, *close_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i - 0.4, *close_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *close_price as f32],
-                },
-            ]);
-            down_hl.extend(&[
-                Vertex {
-                    position: [i, *high_price as f32],
-                },
-                Vertex {
-                    position: [i, *low_price as f32],
-                },
-            ]);
+            &mut down
         } else {
-            stay.extend(&[
-                Vertex {
-                    position: [i - 0.4, *open_price as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *close_price as f32],
-                },
-                Vertex {
-                    position: [i, *high_price as f32],
-                },
-                Vertex {
-                    position: [i, *low_price as f32],
-                },
-            ]);
-        }
-        i += 1.0;
-    }
-    CandleVertex {
-        up,
-        down,
-        down_hl,
-        stay,
+            &mut stay
+        };
+        let x = i as f32;
+        target.extend(&[
+            Vertex {
+                position: [x, HISTORY.high_price[i] as f32],
+            },
+            Vertex {
+                position: [x, HISTORY.low_price[i] as f32],
+            },
+            Vertex {
+                position: [x - 0.4, open_price as f32],
+            },
+            Vertex {
+                position: [x, open_price as f32],
+            },
+            Vertex {
+                position: [x, close_price as f32],
+            },
+            Vertex {
+                position: [x + 0.4, close_price as f32],
+            },
+        ]);
     }
+    OhlcVertex { up, down, stay }
 });
 
+//成交量柱按对应K线的涨跌和样式画
 pub static VOLUME_VERTEX: LazyLock<VolumeVertex> = LazyLock::new(|| {
-    let mut open_iter = HISTORY.open_price.iter();
-    let mut close_iter = HISTORY.close_price.iter();
-    let mut volume_iter = HISTORY.volume.iter();
-    let mut up = Vec::new();
-    let mut down = Vec::new();
-    let mut stay = Vec::new();
-    let mut i = 0f32;
-    while let Some(open_price) = open_iter.next() {
-        let close_price = close_iter.next().unwrap();
-        let volume = volume_iter.next().unwrap();
-        if close_price > open_price {
-            up.extend(&[
-                Vertex {
-                    position: [i - 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i + 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i - 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i - 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i - 0.4, *volume as f32],
-                },
+    let mut body = Vec::new();
+    let mut outline = Vec::new();
+    for i in 0..HISTORY.close_price.len() {
+        let open_price = HISTORY.open_price[i];
+        let close_price = HISTORY.close_price[i];
+        let prev_close = if i > 0 {
+            HISTORY.close_price[i - 1]
+        } else {
+            open_price
+        };
+        let flags = bar_flags(open_price, close_price, prev_close);
+        push_bar_body(
+            &mut body,
+            &mut outline,
+            i as f32,
+            0.0,
+            HISTORY.volume[i] as f32,
+            flags,
+        );
+    }
+    VolumeVertex { body, outline }
+});
+
+//市场轮廓每个字母画成一个方块，颜色从早到晚由蓝变红；横线为POC和价值区上下沿，交易日左边的竖线为初始平衡区
+pub static TPO_VERTEX: LazyLock<TpoVertex> = LazyLock::new(|| {
+    let mut blocks = Vec::new();
+    let mut lines = Vec::new();
+    for session in TPO_SESSIONS.iter() {
+        let width = session.column_width();
+        let row_size = session.profile.row_size;
+        let last_period = session.periods.len().saturating_sub(1).max(1) as f32;
+        for (period, row, k) in session.blocks() {
+            let t = period as f32 / last_period;
+            let color = [0.3 + 0.7 * t, 0.6 - 0.1 * t, 1.0 - 0.7 * t, 1.0];
+            let (x, y) = session.block_position(row, k);
+            let (x1, x2) = (x as f32, (x + width * 0.9) as f32);
+            let (y1, y2) = ((y + row_size * 0.05) as f32, (y + row_size * 0.95) as f32);
+            let point = |x: f32, y: f32| ColorVertex {
+                position: [x, y],
+                color,
+            };
+            blocks.extend([
+                point(x1, y2),
+                point(x1, y1),
+                point(x2, y2),
+                point(x2, y2),
+                point(x1, y1),
+                point(x2, y1),
             ]);
-        } else if close_price < open_price {
-            down.extend(&[
-                Vertex {
-                    position: [i - 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i - 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i + 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i - 0.4, 0.0],
+        }
+        let left = session.bars.start as f32 - 0.5;
+        let right = session.bars.end as f32 - 0.5;
+        for (price, color) in [
+            (session.profile.poc_price(), [1.0, 0.8, 0.2, 1.0]),
+            (session.profile.value_area_high(), [0.55, 0.65, 0.9, 1.0]),
+            (session.profile.value_area_low(), [0.55, 0.65, 0.9, 1.0]),
+        ] {
+            lines.extend([
+                ColorVertex {
+                    position: [left, price as f32],
+                    color,
                 },
-                Vertex {
-                    position: [i + 0.4, 0.0],
+                ColorVertex {
+                    position: [right, price as f32],
+                    color,
                 },
             ]);
+        }
+        let (ib_low, ib_high) = session.initial_balance;
+        for price in [ib_high, ib_low] {
+            lines.push(ColorVertex {
+                position: [left, price as f32],
+                color: [1.0, 1.0, 1.0, 1.0],
+            });
+        }
+    }
+    TpoVertex { blocks, lines }
+});
+
+//足迹图每根K线左边画一根细K线，右边每个价格行画一个格子
+//格子按主动买入多画红色、主动卖出多画青色，成交量越多越不透明，成交量最多的一行画黄框
+pub static FOOTPRINT_VERTEX: LazyLock<FootprintVertex> = LazyLock::new(|| {
+    let Some(footprint) = FOOTPRINT.as_ref() else {
+        return FootprintVertex::default();
+    };
+    let mut cells = Vec::new();
+    let mut lines = Vec::new();
+    let up_color = [1.0, 0.068, 0.068, 1.0];
+    let down_color = [0.0, 1.0, 1.0, 1.0];
+    let rect = |cells: &mut Vec<ColorVertex>, x1: f32, x2: f32, y1: f32, y2: f32, color| {
+        let point = |x: f32, y: f32| ColorVertex {
+            position: [x, y],
+            color,
+        };
+        cells.extend([
+            point(x1, y2),
+            point(x1, y1),
+            point(x2, y2),
+            point(x2, y2),
+            point(x1, y1),
+            point(x2, y1),
+        ]);
+    };
+    let line = |lines: &mut Vec<ColorVertex>, from: [f32; 2], to: [f32; 2], color| {
+        lines.extend([
+            ColorVertex {
+                position: from,
+                color,
+            },
+            ColorVertex {
+                position: to,
+                color,
+            },
+        ]);
+    };
+    let half_row = footprint.row_size / 2.0;
+    for (i, bar_cells) in footprint.bars.iter().enumerate() {
+        let x = i as f32;
+        let (open, close) = (HISTORY.open_price[i], HISTORY.close_price[i]);
+        let color = if close > open {
+            up_color
+        } else if close < open {
+            down_color
         } else {
-            stay.extend(&[
-                Vertex {
-                    position: [i - 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, *volume as f32],
-                },
-                Vertex {
-                    position: [i + 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i + 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i - 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i - 0.4, 0.0],
-                },
-                Vertex {
-                    position: [i - 0.4, *volume as f32],
-                },
-            ]);
+            [1.0, 1.0, 1.0, 1.0]
+        };
+        let (bottom, top) = (open.min(close) as f32, open.max(close) as f32);
+        rect(&mut cells, x - 0.45, x - 0.39, bottom, top, color);
+        line(&mut lines, [x - 0.45, bottom], [x - 0.39, bottom], color);
+        line(
+            &mut lines,
+            [x - 0.42, HISTORY.low_price[i] as f32],
+            [x - 0.42, HISTORY.high_price[i] as f32],
+            color,
+        );
+        let max_total = bar_cells
+            .iter()
+            .map(FootprintCell::total)
+            .fold(0.0, f64::max);
+        if max_total <= 0.0 {
+            continue;
+        }
+        for cell in bar_cells {
+            let [r, g, b, _] = if cell.delta() >= 0.0 {
+                up_color
+            } else {
+                down_color
+            };
+            let alpha = 0.1 + 0.5 * (cell.total() / max_total) as f32;
+            let y1 = (cell.price - half_row * 0.9) as f32;
+            let y2 = (cell.price + half_row * 0.9) as f32;
+            rect(&mut cells, x - 0.35, x + 0.45, y1, y2, [r, g, b, alpha]);
+            if cell.total() == max_total {
+                let poc_color = [1.0, 0.8, 0.2, 1.0];
+                let (x1, x2) = (x - 0.35, x + 0.45);
+                line(&mut lines, [x1, y1], [x2, y1], poc_color);
+                line(&mut lines, [x2, y1], [x2, y2], poc_color);
+                line(&mut lines, [x2, y2], [x1, y2], poc_color);
+                line(&mut lines, [x1, y2], [x1, y1], poc_color);
+            }
         }
-        i += 1.0;
     }
-    VolumeVertex { up, down, stay }
+    FootprintVertex { cells, lines }
 });
 
-pub static TRADE_PAIRS_VERTEX: LazyLock<TradePairVertex> = LazyLock::new(|| {
+//盘口热力图每根K线的每个价格行画一个方块，同一价格行上相邻K线的颜色档相同时合成一个方块
+pub static HEATMAP_VERTEX: LazyLock<Vec<ColorVertex>> = LazyLock::new(|| {
+    let Some(heatmap) = DEPTH_HEATMAP.as_ref() else {
+        return Vec::new();
+    };
+    let style = &*HEATMAP_STYLE;
+    let clip = style.clip_range(heatmap);
+    //(价格行, K线, 颜色档)
+    let mut cells: Vec<(i64, usize, usize)> = Vec::new();
+    for (i, rows) in heatmap.bars.iter().enumerate() {
+        for (row, size) in rows {
+            if let Some(level) = style.level(*size, clip) {
+                cells.push((*row, i, level));
+            }
+        }
+    }
+    cells.sort_unstable();
+    let half_row = heatmap.row_size / 2.0;
+    let mut vertex = Vec::new();
+    let mut k = 0;
+    while k < cells.len() {
+        let (row, start, level) = cells[k];
+        let mut end = start;
+        k += 1;
+        while k < cells.len() && cells[k] == (row, end + 1, level) {
+            end += 1;
+            k += 1;
+        }
+        let color = style.color(level);
+        let (x1, x2) = (start as f32 - 0.5, end as f32 + 0.5);
+        let price = row as f64 * heatmap.row_size;
+        let (y1, y2) = ((price - half_row) as f32, (price + half_row) as f32);
+        let point = |x: f32, y: f32| ColorVertex {
+            position: [x, y],
+            color,
+        };
+        vertex.extend([
+            point(x1, y2),
+            point(x1, y1),
+            point(x2, y2),
+            point(x2, y2),
+            point(x1, y1),
+            point(x2, y1),
+        ]);
+    }
+    vertex
+});
+
+pub static TRADE_PAIRS_VERTEX: LazyLock<TradePairVertex> =
+    LazyLock::new(|| make_trade_pairs_vertex(&HISTORY, |ix| ix));
+
+//bar_x把开平仓的K线序号转成图上的横坐标，三角画在该位置的最高最低价外
+pub fn make_trade_pairs_vertex(
+    history: &HistoryData,
+    bar_x: impl Fn(usize) -> usize,
+) -> TradePairVertex {
     let mut profit = Vec::new();
     let mut loss = Vec::new();
     let mut buy = Vec::new();
@@ -499,13 +1526,9 @@ pub static TRADE_PAIRS_VERTEX: LazyLock<TradePairVertex> = LazyLock::new(|| {
     let mut sell_text = Vec::new();
     let mut short_text = Vec::new();
     let mut cover_text = Vec::new();
-    let mut start_ix = 0;
     for d in TRADE_PAIRS.iter() {
-        let open_ix = search_ix_by_dt(d.open_dt, start_ix);
-        assert!(open_ix >= 0);
-        let close_ix = search_ix_by_dt(d.close_dt, start_ix);
-        assert!(close_ix >= 0);
-        start_ix = open_ix as usize;
+        let open_ix = bar_x(d.open_ix as usize);
+        let close_ix = bar_x(d.close_ix as usize);
         let open_price = d.open_price;
         let close_price = d.close_price;
 
@@ -541,8 +1564,8 @@ pub static TRADE_PAIRS_VERTEX: LazyLock<TradePairVertex> = LazyLock::new(|| {
 
         //交易对三角
         if d.direction == LONG {
-            let open_y = HISTORY.low_price[open_ix as usize];
-            let close_y = HISTORY.high_price[close_ix as usize];
+            let open_y = history.low_price[open_ix];
+            let close_y = history.high_price[close_ix];
             buy.extend(
                 &[Vertex {
                     position: [open_ix as f32, open_y as f32],
@@ -564,8 +1587,8 @@ pub static TRADE_PAIRS_VERTEX: LazyLock<TradePairVertex> = LazyLock::new(|| {
                 format!("{}", d.volume),
             ));
         } else {
-            let open_y = HISTORY.high_price[open_ix as usize];
-            let close_y = HISTORY.low_price[close_ix as usize];
+            let open_y = history.high_price[open_ix];
+            let close_y = history.low_price[close_ix];
             short.extend(
                 &[Vertex {
                     position: [open_ix as f32, open_y as f32],
@@ -600,8 +1623,101 @@ pub static TRADE_PAIRS_VERTEX: LazyLock<TradePairVertex> = LazyLock::new(|| {
         short_text,
         cover_text,
     }
+}
+
+pub static ORDERS_VERTEX: LazyLock<OrderVertex> = LazyLock::new(|| {
+    let mut working = Vec::new();
+    let mut filled = Vec::new();
+    let mut cancelled = Vec::new();
+    let mut stop_working = Vec::new();
+    let mut stop_filled = Vec::new();
+    let mut stop_cancelled = Vec::new();
+    let last_ix = HISTORY.datetime.len() as i64 - 1;
+    for order in ORDERS.iter() {
+        let start_ix = floor_ix_by_dt(order.submit_dt);
+        let end_ix = match order.end_dt {
+            Some(end_dt) => floor_ix_by_dt(end_dt),
+            None => last_ix,
+        };
+        //同一根K线内结束的委托也画出K线宽度
+        let (x1, x2) = if end_ix > start_ix {
+            (start_ix as f32, end_ix as f32)
+        } else {
+            (start_ix as f32 - 0.4, start_ix as f32 + 0.4)
+        };
+        let segment = [
+            Vertex {
+                position: [x1, order.price as f32],
+            },
+            Vertex {
+                position: [x2, order.price as f32],
+            },
+        ];
+        let is_stop = order.order_type == ORDER_STOP;
+        match (order.status, is_stop) {
+            (ORDER_WORKING | ORDER_PARTIAL, false) => working.extend(&segment),
+            (ORDER_WORKING | ORDER_PARTIAL, true) => stop_working.extend(&segment),
+            (ORDER_FILLED, false) => filled.extend(&segment),
+            (ORDER_FILLED, true) => stop_filled.extend(&segment),
+            (_, false) => cancelled.extend(&segment),
+            (_, true) => stop_cancelled.extend(&segment),
+        }
+    }
+    OrderVertex {
+        working,
+        filled,
+        cancelled,
+        stop_working,
+        stop_filled,
+        stop_cancelled,
+    }
+});
+
+//ATR止损线，配置在[trade]节的atr_stop_period（默认14）和atr_stop_multiplier（默认2）
+//多头画在开仓价下方，空头画在开仓价上方，距离为开仓K线的ATR乘以倍数，从开仓画到平仓
+pub static ATR_STOP_VERTEX: LazyLock<Vec<Vertex>> = LazyLock::new(|| {
+    let period = CONFIG.parse_or("trade", "atr_stop_period", 14, |text| {
+        text.parse::<usize>().ok().filter(|period| *period > 0)
+    });
+    let multiplier = CONFIG.parse_or("trade", "atr_stop_multiplier", 2.0, |text| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|multiplier| multiplier.is_finite())
+    });
+    let atr = atr(
+        &HISTORY.high_price,
+        &HISTORY.low_price,
+        &HISTORY.close_price,
+        period,
+    );
+    let mut vertex = Vec::new();
+    for d in TRADE_PAIRS.iter() {
+        let distance = multiplier * atr[d.open_ix as usize];
+        if !distance.is_finite() {
+            continue;
+        }
+        let stop_price = if d.is_long() {
+            d.open_price - distance
+        } else {
+            d.open_price + distance
+        };
+        vertex.extend(&[
+            Vertex {
+                position: [d.open_ix as f32, stop_price as f32],
+            },
+            Vertex {
+                position: [d.close_ix.max(d.open_ix + 1) as f32, stop_price as f32],
+            },
+        ]);
+    }
+    vertex
 });
 
+//不超过datetime的最后一根K线，用于时间不和K线对齐的数据
+pub fn floor_ix_by_dt(datetime: NaiveDateTime) -> i64 {
+    (HISTORY.datetime.partition_point(|dt| *dt <= datetime) as i64 - 1).max(0)
+}
+
 fn search_ix_by_dt(datetime: NaiveDateTime, mut start_ix: usize) -> i64 {
     while start_ix < HISTORY.datetime.len() {
         if HISTORY.datetime[start_ix] == datetime {
@@ -0,0 +1,436 @@
+use std::sync::LazyLock;
+
+use crate::config::CONFIG;
+use crate::indicator::atr;
+use crate::manager::{make_candle_vertex, make_trade_pairs_vertex, HistoryData, HISTORY};
+use crate::vertex::{CandleVertex, OhlcVertex, TradePairVertex, Vertex};
+
+//只按价格变动、不按时间画的图：砖块图、等幅K线和点数图
+//history中每根是一块砖、一根等幅K线或一列，时间为完成时所在K线的时间，最后一根可能还没走完
+//bar_brick是每根真实K线结束时正在形成的砖块或列，即收盘价所在的那一根，用来对齐可见范围和交易
+//砖块图没走完的砖块不画，最后几根K线算在最后一块上；brick_bar是每块完成时所在的K线
+//砖块和等幅K线按K线样式画在candle_vertex里，点数图的X和O画在line_vertex里
+pub struct PriceChart {
+    pub history: HistoryData,
+    pub bar_brick: Vec<usize>,
+    pub brick_bar: Vec<usize>,
+    pub candle_vertex: CandleVertex,
+    pub line_vertex: OhlcVertex,
+    pub trade_vertex: TradePairVertex,
+}
+
+//砖块图，按收盘价生成，反转需要走出两块的距离
+pub static RENKO: LazyLock<PriceChart> = LazyLock::new(|| {
+    let bricks = renko_bricks(&HISTORY, box_size("renko_box"));
+    PriceChart::new(bricks, |history| {
+        (make_candle_vertex(history), OhlcVertex::default())
+    })
+});
+
+//等幅K线，每根的最高最低价相差固定的幅度
+pub static RANGE_BARS: LazyLock<PriceChart> = LazyLock::new(|| {
+    let bricks = range_bricks(&HISTORY, box_size("range"));
+    PriceChart::new(bricks, |history| {
+        (make_candle_vertex(history), OhlcVertex::default())
+    })
+});
+
+//点数图，反向走出pf_reversal格时换列，默认为3
+pub static POINT_FIGURE: LazyLock<PriceChart> = LazyLock::new(|| {
+    let size = box_size("pf_box");
+    let reversal = CONFIG.parse_or("price_chart", "pf_reversal", 3, |text| {
+        text.parse::<u32>().ok().filter(|reversal| *reversal > 0)
+    }) as f64;
+    let bricks = point_figure_columns(&HISTORY, size, reversal);
+    PriceChart::new(bricks, |history| {
+        (CandleVertex::default(), point_figure_vertex(history, size))
+    })
+});
+
+fn renko_bricks(source: &HistoryData, size: f64) -> BrickBuilder<'_> {
+    let mut builder = BrickBuilder::new(source);
+    if size > 0.0 {
+        let start = (source.close_price[0] / size).floor() * size;
+        let (mut top, mut bottom) = (start, start);
+        for (i, close_price) in source.close_price.iter().enumerate() {
+            builder.add_volume(i);
+            while *close_price >= top + size {
+                builder.push(i, top, top + size, top, top + size);
+                bottom = top;
+                top += size;
+            }
+            while *close_price <= bottom - size {
+                builder.push(i, bottom, bottom, bottom - size, bottom - size);
+                top = bottom;
+                bottom -= size;
+            }
+            builder.end_bar();
+        }
+    }
+    builder.finish()
+}
+
+//K线内的价格路径按阳线“开、低、高、收”，阴线“开、高、低、收”估计，正在走的一根也画出来
+fn range_bricks(source: &HistoryData, range: f64) -> BrickBuilder<'_> {
+    let mut builder = BrickBuilder::new(source);
+    if range > 0.0 {
+        let mut open = source.open_price[0];
+        let (mut high, mut low) = (open, open);
+        for i in 0..source.close_price.len() {
+            builder.add_volume(i);
+            let path = if source.close_price[i] >= source.open_price[i] {
+                [
+                    source.open_price[i],
+                    source.low_price[i],
+                    source.high_price[i],
+                    source.close_price[i],
+                ]
+            } else {
+                [
+                    source.open_price[i],
+                    source.high_price[i],
+                    source.low_price[i],
+                    source.close_price[i],
+                ]
+            };
+            for price in path {
+                //一次价格变动可能走完好几根
+                while price - low >= range {
+                    builder.push(i, open, low + range, low, low + range);
+                    open = low + range;
+                    (high, low) = (open, open);
+                }
+                while high - price >= range {
+                    builder.push(i, open, high, high - range, high - range);
+                    open = high - range;
+                    (high, low) = (open, open);
+                }
+                high = high.max(price);
+                low = low.min(price);
+            }
+            builder.end_bar();
+        }
+        let last = source.close_price.len() - 1;
+        builder.push(last, open, high, low, source.close_price[last]);
+    }
+    builder.finish()
+}
+
+//点数图，按最高最低价生成：X列向上，O列向下，反向走出reversal格时换列
+//上涨列的开盘价和最低价为底，收盘价和最高价为顶，下跌列相反，正在走的一列也画出来
+fn point_figure_columns(source: &HistoryData, size: f64, reversal: f64) -> BrickBuilder<'_> {
+    let mut builder = BrickBuilder::new(source);
+    if size > 0.0 {
+        //价格换算成格子边界，留一点余量避免浮点误差少算一格
+        let floor = |price: f64| (price / size + 1e-9).floor() * size;
+        let ceil = |price: f64| (price / size - 1e-9).ceil() * size;
+        let start = floor(source.close_price[0]);
+        //(是否上涨, 顶, 底)
+        let mut column: Option<(bool, f64, f64)> = None;
+        for i in 0..source.close_price.len() {
+            builder.add_volume(i);
+            let high_price = source.high_price[i];
+            let low_price = source.low_price[i];
+            column = match column {
+                None if high_price >= start + size => Some((true, floor(high_price), start)),
+                None if low_price <= start - size => Some((false, start, ceil(low_price))),
+                None => None,
+                Some((true, top, bottom)) => {
+                    if high_price >= top + size {
+                        Some((true, floor(high_price), bottom))
+                    } else if low_price <= top - reversal * size {
+                        builder.push(i, bottom, top, bottom, top);
+                        Some((false, top - size, ceil(low_price)))
+                    } else {
+                        Some((true, top, bottom))
+                    }
+                }
+                Some((false, top, bottom)) => {
+                    if low_price <= bottom - size {
+                        Some((false, top, ceil(low_price)))
+                    } else if high_price >= bottom + reversal * size {
+                        builder.push(i, top, top, bottom, bottom);
+                        Some((true, floor(high_price), bottom + size))
+                    } else {
+                        Some((false, top, bottom))
+                    }
+                }
+            };
+            builder.end_bar();
+        }
+        let last = source.close_price.len() - 1;
+        match column {
+            Some((true, top, bottom)) => builder.push(last, bottom, top, bottom, top),
+            Some((false, top, bottom)) => builder.push(last, top, top, bottom, bottom),
+            None => (),
+        }
+    }
+    builder.finish()
+}
+
+//配置在[price_chart]节，值为“格子大小”或“ATR, 周期”，默认为ATR, 14，ATR取最后一根K线的值
+fn box_size(key: &str) -> f64 {
+    //Ok为格子大小，Err为ATR的周期
+    let size = CONFIG.parse_or("price_chart", key, Err(14), |text| {
+        let mut parts = text.split(',').map(str::trim);
+        let first = parts.next().unwrap_or_default();
+        let size = if first.eq_ignore_ascii_case("atr") {
+            let period = match parts.next() {
+                Some(period) => period.parse::<usize>().ok()?,
+                None => 14,
+            };
+            if period == 0 {
+                return None;
+            }
+            Err(period)
+        } else {
+            Ok(first.parse::<f64>().ok().filter(|size| *size > 0.0)?)
+        };
+        parts.next().is_none().then_some(size)
+    });
+    match size {
+        Ok(size) => size,
+        Err(period) => {
+            //K线不够一个周期时用全部K线
+            let period = period.min(HISTORY.close_price.len());
+            let values = atr(
+                &HISTORY.high_price,
+                &HISTORY.low_price,
+                &HISTORY.close_price,
+                period,
+            );
+            values.last().copied().unwrap_or_default()
+        }
+    }
+}
+
+struct BrickBuilder<'a> {
+    source: &'a HistoryData,
+    history: HistoryData,
+    bar_brick: Vec<usize>,
+    brick_bar: Vec<usize>,
+    volume: f64, //还没算进砖块的成交量
+}
+
+impl<'a> BrickBuilder<'a> {
+    fn new(source: &'a HistoryData) -> Self {
+        BrickBuilder {
+            source,
+            history: HistoryData::default(),
+            bar_brick: Vec::new(),
+            brick_bar: Vec::new(),
+            volume: 0.0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.history.close_price.len()
+    }
+
+    fn add_volume(&mut self, bar_ix: usize) {
+        self.volume += self.source.volume[bar_ix];
+    }
+
+    fn push(&mut self, bar_ix: usize, open: f64, high: f64, low: f64, close: f64) {
+        self.history.datetime.push(self.source.datetime[bar_ix]);
+        self.history.open_price.push(open);
+        self.history.high_price.push(high);
+        self.history.low_price.push(low);
+        self.history.close_price.push(close);
+        self.history.volume.push(self.volume);
+        self.brick_bar.push(bar_ix);
+        self.volume = 0.0;
+    }
+
+    //一根K线处理完，记录它结束时正在形成的砖块或列
+    fn end_bar(&mut self) {
+        self.bar_brick.push(self.len());
+    }
+
+    //最后没画出来的砖块算在最后一块上，没有生成任何砖块时所有K线都对应0，history为空
+    fn finish(mut self) -> Self {
+        let last = self.len().saturating_sub(1);
+        for brick in self.bar_brick.iter_mut() {
+            *brick = (*brick).min(last);
+        }
+        self
+    }
+}
+
+impl PriceChart {
+    fn new(
+        bricks: BrickBuilder,
+        make_vertex: impl FnOnce(&HistoryData) -> (CandleVertex, OhlcVertex),
+    ) -> PriceChart {
+        let (candle_vertex, line_vertex) = make_vertex(&bricks.history);
+        let trade_vertex = if bricks.history.close_price.is_empty() {
+            TradePairVertex::default()
+        } else {
+            make_trade_pairs_vertex(&bricks.history, |ix| bricks.bar_brick[ix])
+        };
+        PriceChart {
+            history: bricks.history,
+            bar_brick: bricks.bar_brick,
+            brick_bar: bricks.brick_bar,
+            candle_vertex,
+            line_vertex,
+            trade_vertex,
+        }
+    }
+}
+
+//点数图每格画一个X或O：X为两条对角线，画在up里；O为菱形，画在down里
+fn point_figure_vertex(history: &HistoryData, size: f64) -> OhlcVertex {
+    let mut up = Vec::new();
+    let mut down = Vec::new();
+    for i in 0..history.close_price.len() {
+        let x = i as f32;
+        let rising = history.close_price[i] > history.open_price[i];
+        let count = ((history.high_price[i] - history.low_price[i]) / size).round() as usize;
+        for k in 0..count.max(1) {
+            let y1 = (history.low_price[i] + k as f64 * size) as f32;
+            let y2 = (history.low_price[i] + (k + 1) as f64 * size) as f32;
+            let point = |x: f32, y: f32| Vertex { position: [x, y] };
+            if rising {
+                up.extend(&[
+                    point(x - 0.4, y1),
+                    point(x + 0.4, y2),
+                    point(x - 0.4, y2),
+                    point(x + 0.4, y1),
+                ]);
+            } else {
+                let y = (y1 + y2) / 2.0;
+                down.extend(&[
+                    point(x, y2),
+                    point(x + 0.4, y),
+                    point(x + 0.4, y),
+                    point(x, y1),
+                    point(x, y1),
+                    point(x - 0.4, y),
+                    point(x - 0.4, y),
+                    point(x, y2),
+                ]);
+            }
+        }
+    }
+    OhlcVertex {
+        up,
+        down,
+        stay: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    //(开, 高, 低, 收)，每根成交量为1，时间间隔1分钟
+    fn history(bars: &[(f64, f64, f64, f64)]) -> HistoryData {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        HistoryData {
+            datetime: (0..bars.len())
+                .map(|i| start + Duration::minutes(i as i64))
+                .collect(),
+            open_price: bars.iter().map(|bar| bar.0).collect(),
+            high_price: bars.iter().map(|bar| bar.1).collect(),
+            low_price: bars.iter().map(|bar| bar.2).collect(),
+            close_price: bars.iter().map(|bar| bar.3).collect(),
+            volume: vec![1.0; bars.len()],
+        }
+    }
+
+    fn closes(close_price: &[f64]) -> HistoryData {
+        let bars: Vec<_> = close_price
+            .iter()
+            .map(|price| (*price, *price, *price, *price))
+            .collect();
+        history(&bars)
+    }
+
+    fn ohlc(history: &HistoryData) -> Vec<(f64, f64, f64, f64)> {
+        (0..history.close_price.len())
+            .map(|i| {
+                (
+                    history.open_price[i],
+                    history.high_price[i],
+                    history.low_price[i],
+                    history.close_price[i],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renko() {
+        let source = closes(&[10.2, 11.5, 12.1, 12.4, 9.6]);
+        let bricks = renko_bricks(&source, 1.0);
+        //反转要走出两块，12.4回到9.6只出一块
+        assert_eq!(
+            ohlc(&bricks.history),
+            [
+                (10.0, 11.0, 10.0, 11.0),
+                (11.0, 12.0, 11.0, 12.0),
+                (11.0, 11.0, 10.0, 10.0)
+            ]
+        );
+        assert_eq!(bricks.history.volume, [2.0, 1.0, 2.0]);
+        assert_eq!(bricks.brick_bar, [1, 2, 4]);
+        assert_eq!(bricks.history.datetime[2], source.datetime[4]);
+        //K线结束时正在形成的砖块，最后没走完的算在最后一块上
+        assert_eq!(bricks.bar_brick, [0, 1, 2, 2, 2]);
+    }
+
+    #[test]
+    fn renko_without_bricks() {
+        let source = closes(&[10.0, 10.5, 9.8]);
+        let bricks = renko_bricks(&source, 5.0);
+        assert!(bricks.history.close_price.is_empty());
+        assert!(bricks.brick_bar.is_empty());
+        assert_eq!(bricks.bar_brick, [0, 0, 0]);
+    }
+
+    #[test]
+    fn range_bars() {
+        let source = history(&[
+            (10.0, 11.0, 9.5, 10.5),
+            (10.5, 12.0, 10.4, 11.8),
+            (11.8, 11.9, 9.0, 9.2),
+        ]);
+        let bricks = range_bricks(&source, 2.0);
+        //最后一根还没走完，也画出来
+        assert_eq!(
+            ohlc(&bricks.history),
+            [
+                (10.0, 11.5, 9.5, 11.5),
+                (11.5, 12.0, 10.0, 10.0),
+                (10.0, 10.0, 9.0, 9.2)
+            ]
+        );
+        assert_eq!(bricks.history.volume, [2.0, 1.0, 0.0]);
+        assert_eq!(bricks.brick_bar, [1, 2, 2]);
+        assert_eq!(bricks.bar_brick, [0, 1, 2]);
+    }
+
+    #[test]
+    fn point_figure() {
+        let source = history(&[
+            (10.2, 10.5, 9.8, 10.2),
+            (10.2, 12.3, 10.2, 12.0),
+            (12.0, 12.5, 10.9, 11.0),
+            (11.0, 11.0, 8.5, 9.0),
+            (9.0, 9.5, 7.2, 7.5),
+        ]);
+        let columns = point_figure_columns(&source, 1.0, 3.0);
+        //X列从10到12，跌到8.5时反转成O列，最后一列还没走完
+        assert_eq!(
+            ohlc(&columns.history),
+            [(10.0, 12.0, 10.0, 12.0), (11.0, 11.0, 8.0, 8.0)]
+        );
+        assert_eq!(columns.brick_bar, [3, 4]);
+        assert_eq!(columns.bar_brick, [0, 0, 0, 1, 1]);
+    }
+}
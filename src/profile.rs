@@ -0,0 +1,452 @@
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use chrono::{NaiveTime, TimeDelta};
+
+use crate::config::CONFIG;
+use crate::indicator::{session_key, session_ranges, session_start};
+use crate::manager::{HistoryData, HISTORY};
+
+//最小价格变动，配置在[chart]节的price_tick，没配置时取所有价格中相邻两个不同价格的最小差
+pub static PRICE_TICK: LazyLock<f64> = LazyLock::new(|| {
+    let configured = CONFIG.parse_or("chart", "price_tick", None, |text| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|tick| *tick > 0.0)
+            .map(Some)
+    });
+    if let Some(tick) = configured {
+        return tick;
+    }
+    let mut prices: Vec<f64> = [
+        &HISTORY.open_price,
+        &HISTORY.high_price,
+        &HISTORY.low_price,
+        &HISTORY.close_price,
+    ]
+    .into_iter()
+    .flatten()
+    .copied()
+    .collect();
+    prices.sort_by(f64::total_cmp);
+    let max_price = prices.iter().fold(0f64, |max, price| max.max(price.abs()));
+    //浮点误差造成的极小差值不算
+    let epsilon = max_price * 1e-9;
+    let tick = prices
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|diff| *diff > epsilon)
+        .fold(f64::INFINITY, f64::min);
+    if tick.is_finite() {
+        (tick * 1e8).round().max(1.0) / 1e8
+    } else {
+        1.0
+    }
+});
+
+//价值区占总量的比例，配置在[profile]节的value_area，单位为百分比，默认为70
+fn value_area_ratio() -> f64 {
+    CONFIG.parse_or("profile", "value_area", 0.7, |text| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+            .map(|percent| percent / 100.0)
+    })
+}
+
+//[profile]节中的正整数配置
+fn profile_count(key: &str, default: usize) -> usize {
+    CONFIG.parse_or("profile", key, default, |text| {
+        text.parse::<usize>().ok().filter(|count| *count > 0)
+    })
+}
+
+//成交量分布最多的行数，行高为最小价格变动的整数倍，配置在[profile]节的volume_rows，默认为100
+static VOLUME_ROWS: LazyLock<usize> = LazyLock::new(|| profile_count("volume_rows", 100));
+
+//按价格分行统计的分布，第i行的价格范围为[base + i * row_size, base + (i + 1) * row_size)
+//poc为最多的一行，value_area为从poc向两边扩展、合计达到价值区比例的行范围
+pub struct Profile {
+    pub base: f64,
+    pub row_size: f64,
+    pub values: Vec<f64>,
+    pub poc: usize,
+    pub value_area: (usize, usize),
+}
+
+impl Profile {
+    fn new(base: f64, row_size: f64, values: Vec<f64>, ratio: f64) -> Self {
+        let (poc, value_area) = value_area(&values, ratio);
+        Profile {
+            base,
+            row_size,
+            values,
+            poc,
+            value_area,
+        }
+    }
+
+    pub fn row_low(&self, row: usize) -> f64 {
+        self.base + row as f64 * self.row_size
+    }
+
+    pub fn row_middle(&self, row: usize) -> f64 {
+        self.row_low(row) + self.row_size / 2.0
+    }
+
+    pub fn poc_price(&self) -> f64 {
+        self.row_middle(self.poc)
+    }
+
+    pub fn value_area_high(&self) -> f64 {
+        self.row_low(self.value_area.1 + 1)
+    }
+
+    pub fn value_area_low(&self) -> f64 {
+        self.row_low(self.value_area.0)
+    }
+
+    pub fn max_value(&self) -> f64 {
+        self.values[self.poc]
+    }
+}
+
+//从最多的一行开始，每次把上下相邻两行中较多的一行加进来，直到达到比例
+fn value_area(values: &[f64], ratio: f64) -> (usize, (usize, usize)) {
+    let poc = values.iter().enumerate().fold(
+        0,
+        |poc, (i, value)| if *value > values[poc] { i } else { poc },
+    );
+    let target = values.iter().sum::<f64>() * ratio;
+    let (mut low, mut high) = (poc, poc);
+    let mut sum = values[poc];
+    while sum < target && (low > 0 || high + 1 < values.len()) {
+        let below = if low > 0 { values[low - 1] } else { -1.0 };
+        let above = if high + 1 < values.len() {
+            values[high + 1]
+        } else {
+            -1.0
+        };
+        if above >= below {
+            high += 1;
+            sum += above;
+        } else {
+            low -= 1;
+            sum += below;
+        }
+    }
+    (poc, (low, high))
+}
+
+//行高为最小价格变动的整数倍，使行数不超过max_rows
+fn row_layout(min_price: f64, max_price: f64, max_rows: usize, tick: f64) -> (f64, f64, usize) {
+    let ticks = ((max_price - min_price) / tick).ceil().max(1.0);
+    let row_size = (ticks / max_rows as f64).ceil().max(1.0) * tick;
+    let base = (min_price / row_size).floor() * row_size;
+    let rows = (((max_price - base) / row_size).floor() as usize + 1).max(1);
+    (base, row_size, rows)
+}
+
+//可见范围内的成交量分布
+pub fn volume_profile(history: &HistoryData, left: usize, right: usize) -> Option<Profile> {
+    build_volume_profile(
+        history,
+        left..right + 1,
+        *PRICE_TICK,
+        *VOLUME_ROWS,
+        value_area_ratio(),
+    )
+}
+
+//每根K线的成交量按最高最低价之间的长度平均分到各行
+fn build_volume_profile(
+    history: &HistoryData,
+    range: Range<usize>,
+    tick: f64,
+    max_rows: usize,
+    ratio: f64,
+) -> Option<Profile> {
+    let min_price = history.low_price[range.clone()]
+        .iter()
+        .fold(f64::INFINITY, |min, price| min.min(*price));
+    let max_price = history.high_price[range.clone()]
+        .iter()
+        .fold(f64::NEG_INFINITY, |max, price| max.max(*price));
+    if !min_price.is_finite() || !max_price.is_finite() {
+        return None;
+    }
+    let (base, row_size, rows) = row_layout(min_price, max_price, max_rows, tick);
+    let row_of = |price: f64| (((price - base) / row_size).floor().max(0.0) as usize).min(rows - 1);
+    let mut values = vec![0.0; rows];
+    for i in range {
+        let (low_price, high_price) = (history.low_price[i], history.high_price[i]);
+        let volume = history.volume[i];
+        let (first, last) = (row_of(low_price), row_of(high_price));
+        if first == last {
+            values[first] += volume;
+            continue;
+        }
+        for (row, value) in values.iter_mut().enumerate().take(last + 1).skip(first) {
+            let row_low = base + row as f64 * row_size;
+            let overlap = (row_low + row_size).min(high_price) - row_low.max(low_price);
+            *value += volume * overlap.max(0.0) / (high_price - low_price);
+        }
+    }
+    if values.iter().all(|value| *value <= 0.0) {
+        return None;
+    }
+    Some(Profile::new(base, row_size, values, ratio))
+}
+
+//一个交易日的市场轮廓，每个时段（默认30分钟）是一个字母，碰到的价格行各记一次
+//periods为每个时段碰到的行范围，没有K线的时段为None；initial_balance为开头几个时段的最低价和最高价
+pub struct TpoSession {
+    pub bars: Range<usize>,
+    pub profile: Profile,
+    pub periods: Vec<Option<(usize, usize)>>,
+    pub initial_balance: (f64, f64),
+}
+
+impl TpoSession {
+    //按时间画在这个交易日的K线范围内，每个时段占一列宽
+    pub fn column_width(&self) -> f64 {
+        self.bars.len() as f64 / self.periods.len() as f64
+    }
+
+    //每行的字母按时段顺序从左往右挤在一起，返回(时段, 行, 在这一行的第几个)
+    pub fn blocks(&self) -> Vec<(usize, usize, usize)> {
+        let mut counts = vec![0; self.profile.values.len()];
+        let mut blocks = Vec::new();
+        for (period, rows) in self.periods.iter().enumerate() {
+            let Some((first, last)) = rows else {
+                continue;
+            };
+            for (row, count) in counts.iter_mut().enumerate().take(last + 1).skip(*first) {
+                blocks.push((period, row, *count));
+                *count += 1;
+            }
+        }
+        blocks
+    }
+
+    //第k个字母的左下角
+    pub fn block_position(&self, row: usize, k: usize) -> (f64, f64) {
+        (
+            self.bars.start as f64 - 0.5 + k as f64 * self.column_width(),
+            self.profile.row_low(row),
+        )
+    }
+}
+
+//市场轮廓的配置，时段长度tpo_period（分钟，默认30），初始平衡区的时段数initial_balance（默认2），最高的一天不超过tpo_rows行（默认40）
+struct TpoSettings {
+    period: TimeDelta,
+    initial_balance: usize,
+    max_rows: usize,
+    session_start: NaiveTime,
+    tick: f64,
+    value_area_ratio: f64,
+}
+
+impl TpoSettings {
+    fn from_config() -> Self {
+        TpoSettings {
+            period: TimeDelta::minutes(profile_count("tpo_period", 30) as i64),
+            initial_balance: profile_count("initial_balance", 2),
+            max_rows: profile_count("tpo_rows", 40),
+            session_start: session_start(),
+            tick: *PRICE_TICK,
+            value_area_ratio: value_area_ratio(),
+        }
+    }
+}
+
+//按交易日生成的市场轮廓，所有交易日的行高相同，行的边界对齐
+pub static TPO_SESSIONS: LazyLock<Vec<TpoSession>> =
+    LazyLock::new(|| tpo_sessions(&HISTORY, &TpoSettings::from_config()));
+
+fn tpo_sessions(history: &HistoryData, settings: &TpoSettings) -> Vec<TpoSession> {
+    let TpoSettings {
+        period,
+        initial_balance,
+        max_rows,
+        session_start,
+        ..
+    } = *settings;
+    let sessions = session_ranges(&history.datetime, session_start);
+    let max_range = sessions
+        .iter()
+        .map(|bars| {
+            let low = history.low_price[bars.clone()]
+                .iter()
+                .fold(f64::INFINITY, |min, price| min.min(*price));
+            let high = history.high_price[bars.clone()]
+                .iter()
+                .fold(f64::NEG_INFINITY, |max, price| max.max(*price));
+            high - low
+        })
+        .fold(0.0, f64::max);
+    let (_, row_size, _) = row_layout(0.0, max_range, max_rows, settings.tick);
+    let mut tpo_sessions = Vec::new();
+    for bars in sessions {
+        //时段按交易日开始时间划分，第一根K线所在的时段为A
+        let first_time = history.datetime[bars.start];
+        let start = session_key(first_time, session_start).and_time(session_start);
+        let first_period = (first_time - start).num_seconds() / period.num_seconds();
+        let period_of = |i: usize| {
+            ((history.datetime[i] - start).num_seconds() / period.num_seconds() - first_period)
+                as usize
+        };
+        let period_count = period_of(bars.end - 1) + 1;
+        let mut period_ranges = vec![None; period_count];
+        for i in bars.clone() {
+            let range: &mut Option<(f64, f64)> = &mut period_ranges[period_of(i)];
+            let (low, high) = range.unwrap_or((f64::INFINITY, f64::NEG_INFINITY));
+            *range = Some((
+                low.min(history.low_price[i]),
+                high.max(history.high_price[i]),
+            ));
+        }
+        let low = period_ranges
+            .iter()
+            .flatten()
+            .fold(f64::INFINITY, |min, range| min.min(range.0));
+        let high = period_ranges
+            .iter()
+            .flatten()
+            .fold(f64::NEG_INFINITY, |max, range| max.max(range.1));
+        let base = (low / row_size).floor() * row_size;
+        let rows = ((high - base) / row_size).floor() as usize + 1;
+        let row_of =
+            |price: f64| (((price - base) / row_size).floor().max(0.0) as usize).min(rows - 1);
+        let mut values = vec![0.0; rows];
+        let periods: Vec<Option<(usize, usize)>> = period_ranges
+            .iter()
+            .map(|range| {
+                range.map(|(low, high)| {
+                    let (first, last) = (row_of(low), row_of(high));
+                    for value in &mut values[first..=last] {
+                        *value += 1.0;
+                    }
+                    (first, last)
+                })
+            })
+            .collect();
+        let initial_balance = period_ranges
+            .iter()
+            .take(initial_balance)
+            .flatten()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), range| {
+                (min.min(range.0), max.max(range.1))
+            });
+        tpo_sessions.push(TpoSession {
+            bars,
+            profile: Profile::new(base, row_size, values, settings.value_area_ratio),
+            periods,
+            initial_balance,
+        });
+    }
+    tpo_sessions
+}
+
+//时段的字母，A到Z之后是a到z，再从A开始
+pub fn tpo_letter(period: usize) -> char {
+    let letters = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    letters[period % letters.len()] as char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    fn time(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    //每根K线为(时间, 最低价, 最高价, 成交量)
+    fn history(bars: &[(NaiveDateTime, f64, f64, f64)]) -> HistoryData {
+        HistoryData {
+            datetime: bars.iter().map(|bar| bar.0).collect(),
+            open_price: bars.iter().map(|bar| bar.1).collect(),
+            high_price: bars.iter().map(|bar| bar.2).collect(),
+            low_price: bars.iter().map(|bar| bar.1).collect(),
+            close_price: bars.iter().map(|bar| bar.2).collect(),
+            volume: bars.iter().map(|bar| bar.3).collect(),
+        }
+    }
+
+    #[test]
+    fn value_area_grows_towards_larger_side() {
+        //合计16，70%为11.2：5，加3，加2，上下都是1时先加上面，再加下面的1达到12
+        assert_eq!(
+            value_area(&[1.0, 2.0, 5.0, 3.0, 1.0, 0.0, 4.0], 0.7),
+            (2, (0, 4))
+        );
+        assert_eq!(value_area(&[0.0, 3.0, 0.0], 1.0), (1, (1, 1)));
+        //一样多时第一行为POC
+        assert_eq!(value_area(&[1.0, 1.0], 1.0), (0, (0, 1)));
+    }
+
+    #[test]
+    fn volume_split_by_price_overlap() {
+        let history = history(&[
+            (time(2, 9, 0), 10.0, 12.0, 20.0),
+            (time(2, 9, 1), 11.0, 11.0, 5.0),
+            (time(2, 9, 2), 10.0, 11.0, 10.0),
+        ]);
+        let profile = build_volume_profile(&history, 0..3, 1.0, 100, 0.7).unwrap();
+        assert_eq!(profile.base, 10.0);
+        assert_eq!(profile.row_size, 1.0);
+        assert_eq!(profile.values, [20.0, 15.0, 0.0]);
+        assert_eq!(profile.poc_price(), 10.5);
+        assert_eq!(profile.value_area_low(), 10.0);
+        assert_eq!(profile.value_area_high(), 12.0);
+        //行数不超过max_rows时行高取最小价格变动的整数倍
+        let profile = build_volume_profile(&history, 0..3, 1.0, 1, 0.7).unwrap();
+        assert_eq!(profile.row_size, 2.0);
+        assert_eq!(profile.values, [35.0, 0.0]);
+        //只有最后一根K线
+        let profile = build_volume_profile(&history, 2..3, 1.0, 100, 0.7).unwrap();
+        assert_eq!(profile.values, [10.0, 0.0]);
+    }
+
+    #[test]
+    fn tpo_sessions_split_at_session_start() {
+        //交易日从21:00开始，2日夜盘和3日日盘是同一个交易日
+        let history = history(&[
+            (time(2, 21, 0), 10.0, 12.0, 1.0),
+            (time(2, 21, 10), 11.0, 13.0, 1.0),
+            (time(3, 9, 0), 12.0, 12.0, 1.0),
+            (time(3, 21, 0), 20.0, 21.0, 1.0),
+        ]);
+        let settings = TpoSettings {
+            period: TimeDelta::minutes(30),
+            initial_balance: 1,
+            max_rows: 40,
+            session_start: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+            tick: 1.0,
+            value_area_ratio: 0.7,
+        };
+        let sessions = tpo_sessions(&history, &settings);
+        assert_eq!(sessions.len(), 2);
+        let first = &sessions[0];
+        assert_eq!(first.bars, 0..3);
+        //21:00开始的A时段和12小时后的第25个时段，中间没有K线
+        assert_eq!(first.periods.len(), 25);
+        assert_eq!(first.periods[0], Some((0, 3)));
+        assert_eq!(first.periods[24], Some((2, 2)));
+        assert!(first.periods[1..24].iter().all(Option::is_none));
+        assert_eq!(first.profile.values, [1.0, 1.0, 2.0, 1.0]);
+        assert_eq!(first.profile.poc, 2);
+        assert_eq!(first.profile.value_area, (1, 3));
+        assert_eq!(first.initial_balance, (10.0, 13.0));
+        let second = &sessions[1];
+        assert_eq!(second.bars, 3..4);
+        assert_eq!(second.profile.base, 20.0);
+        assert_eq!(second.periods, [Some((0, 1))]);
+    }
+}
@@ -0,0 +1,353 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use glam::Vec4;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, INT};
+
+use crate::config::parse_color;
+use crate::formula::FormulaDef;
+use crate::indicator::{atr, ema, highest, lowest, sma, stddev, wma, Series, SeriesStyle, SubPane};
+use crate::manager::{get_home_path, HISTORY, TRADE_PAIRS};
+
+//脚本的运算次数上限，防止死循环卡住图表
+const MAX_OPERATIONS: u64 = 100_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_STRING_SIZE: usize = 1 << 20;
+//数组长度上限，至少要能放下所有K线
+const MIN_ARRAY_SIZE: usize = 1 << 20;
+
+//脚本在K线图上画的文字，位置和交易文字一样用K线下标和价格表示
+pub struct ScriptText {
+    pub position: Vec4,
+    pub text: String,
+    pub color: [f32; 4],
+}
+
+//所有脚本的输出，出错的脚本只记录错误，不输出任何东西
+#[derive(Default)]
+pub struct ScriptOutput {
+    pub overlays: Vec<Series>,
+    pub sub_panes: Vec<SubPane>,
+    pub texts: Vec<ScriptText>,
+    pub errors: Vec<String>,
+}
+
+//单个脚本调用plot、marker和text的结果
+#[derive(Default)]
+struct Collected {
+    series: Vec<(Option<String>, Series)>,
+    markers: Vec<(usize, f64, [f32; 4])>,
+    texts: Vec<ScriptText>,
+}
+
+type FnResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn to_f64(value: &Dynamic) -> f64 {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|value| value as f64))
+        .unwrap_or(f64::NAN)
+}
+
+fn to_values(array: &Array) -> Vec<f64> {
+    array.iter().map(to_f64).collect()
+}
+
+fn to_array(values: Vec<f64>) -> Array {
+    values.into_iter().map(Dynamic::from_float).collect()
+}
+
+fn to_ix(ix: INT) -> FnResult<usize> {
+    if ix < 0 || ix as usize >= HISTORY.datetime.len() {
+        return Err(format!("K线下标{}超出范围", ix).into());
+    }
+    Ok(ix as usize)
+}
+
+fn to_color(text: &str) -> FnResult<[f32; 4]> {
+    parse_color(text).ok_or_else(|| format!("颜色“{}”错误", text).into())
+}
+
+fn map_string(map: &Map, key: &str) -> FnResult<Option<String>> {
+    match map.get(key) {
+        None => Ok(None),
+        Some(value) => value
+            .clone()
+            .into_string()
+            .map(Some)
+            .map_err(|_| format!("{}必须是字符串", key).into()),
+    }
+}
+
+//plot(#{name: "名称", values: 数组, pane: "副图", style: "line", color: "#RRGGBB", down_color: "#RRGGBB"})
+//pane不写或为main时画在K线图上，style可以是line、histogram或dots
+fn plot(map: &Map) -> FnResult<(Option<String>, Series)> {
+    let name = map_string(map, "name")?.ok_or("plot缺少name")?;
+    let Some(values) = map
+        .get("values")
+        .and_then(|value| value.read_lock::<Array>())
+    else {
+        return Err(format!("{}缺少values数组", name).into());
+    };
+    let bar_count = HISTORY.datetime.len();
+    if values.len() > bar_count {
+        return Err(format!("{}的长度{}超过了K线数量{}", name, values.len(), bar_count).into());
+    }
+    let mut values = to_values(&values);
+    values.resize(bar_count, f64::NAN);
+    let color = match map_string(map, "color")? {
+        Some(text) => to_color(&text)?,
+        None => [1.0; 4],
+    };
+    let style = match map_string(map, "style")?.as_deref() {
+        None | Some("line") => SeriesStyle::Line,
+        Some("histogram") => SeriesStyle::Histogram {
+            down_color: match map_string(map, "down_color")? {
+                Some(text) => to_color(&text)?,
+                None => color,
+            },
+        },
+        Some("dots") => SeriesStyle::Dots,
+        Some(style) => return Err(format!("画法“{}”错误", style).into()),
+    };
+    let pane = map_string(map, "pane")?.filter(|pane| !pane.is_empty() && pane != "main");
+    Ok((
+        pane,
+        Series {
+            name,
+            values,
+            color,
+            style,
+        },
+    ))
+}
+
+//输入给脚本的交易对，每个是一个对象
+fn trade_array() -> Array {
+    TRADE_PAIRS
+        .iter()
+        .map(|pair| {
+            let mut map = Map::new();
+            map.insert("open_ix".into(), Dynamic::from_int(pair.open_ix));
+            map.insert("close_ix".into(), Dynamic::from_int(pair.close_ix));
+            map.insert(
+                "direction".into(),
+                Dynamic::from(if pair.is_long() { "long" } else { "short" }.to_string()),
+            );
+            map.insert("open_price".into(), Dynamic::from_float(pair.open_price));
+            map.insert("close_price".into(), Dynamic::from_float(pair.close_price));
+            map.insert("volume".into(), Dynamic::from_float(pair.volume));
+            map.insert("pnl".into(), Dynamic::from_float(pair.pnl()));
+            Dynamic::from_map(map)
+        })
+        .collect()
+}
+
+//沙盒：不能import其他文件，不能eval，限制运算次数、调用深度和字符串长度，print输出到日志
+fn create_engine(collected: &Rc<RefCell<Collected>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MIN_ARRAY_SIZE.max(HISTORY.datetime.len()))
+        .on_print(|text| log::info!("{}", text))
+        .on_debug(|text, _, position| log::debug!("{:?}：{}", position, text));
+
+    //计算函数，和图表内置指标的算法相同
+    engine
+        .register_fn("sma", |source: Array, period: INT| {
+            to_array(sma(&to_values(&source), period.max(0) as usize))
+        })
+        .register_fn("ema", |source: Array, period: INT| {
+            to_array(ema(&to_values(&source), period.max(0) as usize))
+        })
+        .register_fn("wma", |source: Array, period: INT| {
+            to_array(wma(&to_values(&source), period.max(0) as usize))
+        })
+        .register_fn("highest", |source: Array, period: INT| {
+            to_array(highest(&to_values(&source), period.max(0) as usize))
+        })
+        .register_fn("lowest", |source: Array, period: INT| {
+            to_array(lowest(&to_values(&source), period.max(0) as usize))
+        })
+        .register_fn("stddev", |source: Array, period: INT| {
+            to_array(stddev(&to_values(&source), period.max(0) as usize))
+        })
+        .register_fn(
+            "atr",
+            |high: Array, low: Array, close: Array, period: INT| {
+                to_array(atr(
+                    &to_values(&high),
+                    &to_values(&low),
+                    &to_values(&close),
+                    period.max(0) as usize,
+                ))
+            },
+        )
+        .register_fn("formula", |text: &str| -> FnResult<Array> {
+            let formula = FormulaDef::parse("", text).map_err(|err| format!("公式{}", err))?;
+            Ok(to_array(formula.eval()))
+        });
+
+    //输出函数
+    let c = collected.clone();
+    engine.register_fn("plot", move |map: Map| -> FnResult<()> {
+        c.borrow_mut().series.push(plot(&map)?);
+        Ok(())
+    });
+    let c = collected.clone();
+    engine.register_fn("marker", move |ix: INT, price: Dynamic| -> FnResult<()> {
+        c.borrow_mut()
+            .markers
+            .push((to_ix(ix)?, to_f64(&price), [1.0, 1.0, 1.0, 1.0]));
+        Ok(())
+    });
+    let c = collected.clone();
+    engine.register_fn(
+        "marker",
+        move |ix: INT, price: Dynamic, color: &str| -> FnResult<()> {
+            c.borrow_mut()
+                .markers
+                .push((to_ix(ix)?, to_f64(&price), to_color(color)?));
+            Ok(())
+        },
+    );
+    let c = collected.clone();
+    engine.register_fn(
+        "text",
+        move |ix: INT, price: Dynamic, text: &str| -> FnResult<()> {
+            c.borrow_mut().texts.push(ScriptText {
+                position: Vec4::new(to_ix(ix)? as f32, to_f64(&price) as f32, 0.0, 1.0),
+                text: text.to_string(),
+                color: [1.0, 1.0, 1.0, 1.0],
+            });
+            Ok(())
+        },
+    );
+    let c = collected.clone();
+    engine.register_fn(
+        "text",
+        move |ix: INT, price: Dynamic, text: &str, color: &str| -> FnResult<()> {
+            c.borrow_mut().texts.push(ScriptText {
+                position: Vec4::new(to_ix(ix)? as f32, to_f64(&price) as f32, 0.0, 1.0),
+                text: text.to_string(),
+                color: to_color(color)?,
+            });
+            Ok(())
+        },
+    );
+    engine
+}
+
+//K线数据放在脚本的常量里：open、high、low、close、volume、datetime和trades
+fn create_scope() -> Scope<'static> {
+    let mut scope = Scope::new();
+    scope
+        .push_constant("open", to_array(HISTORY.open_price.clone()))
+        .push_constant("high", to_array(HISTORY.high_price.clone()))
+        .push_constant("low", to_array(HISTORY.low_price.clone()))
+        .push_constant("close", to_array(HISTORY.close_price.clone()))
+        .push_constant("volume", to_array(HISTORY.volume.clone()))
+        .push_constant(
+            "datetime",
+            HISTORY
+                .datetime
+                .iter()
+                .map(|dt| Dynamic::from(dt.format("%Y-%m-%d %H:%M:%S").to_string()))
+                .collect::<Array>(),
+        )
+        .push_constant("trades", trade_array());
+    scope
+}
+
+fn run_script(path: PathBuf, scope: &Scope<'static>) -> Result<Collected, String> {
+    let collected = Rc::new(RefCell::new(Collected::default()));
+    let engine = create_engine(&collected);
+    let ast = engine.compile_file(path).map_err(|err| err.to_string())?;
+    engine
+        .run_ast_with_scope(&mut scope.clone(), &ast)
+        .map_err(|err| err.to_string())?;
+    //引擎里注册的函数持有collected的引用，释放引擎后才能取出
+    drop(engine);
+    Rc::into_inner(collected)
+        .map(RefCell::into_inner)
+        .ok_or_else(|| "脚本运行后仍有对输出的引用，无法取出输出".to_string())
+}
+
+//运行家目录vnpyrs/scripts文件夹下的所有.rhai脚本，按文件名排序，文件夹不存在时什么也不做
+//脚本画的副图只包含同一个脚本里pane相同的序列，不会加到内置的副图上
+pub fn run_scripts() -> ScriptOutput {
+    let mut output = ScriptOutput::default();
+    let dir = PathBuf::from(format!("{}/vnpyrs/scripts", get_home_path()));
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return output;
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return output;
+    }
+    let scope = create_scope();
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let collected = match run_script(path, &scope) {
+            Ok(collected) => collected,
+            Err(err) => {
+                log::error!("脚本{}出错：{}", file_name, err);
+                output.errors.push(format!("{}：{}", file_name, err));
+                continue;
+            }
+        };
+        let mut sub_panes: Vec<SubPane> = Vec::new();
+        for (pane, series) in collected.series {
+            let Some(pane) = pane else {
+                output.overlays.push(series);
+                continue;
+            };
+            match sub_panes.iter_mut().find(|sub_pane| sub_pane.name == pane) {
+                Some(sub_pane) => sub_pane.series.push(series),
+                None => sub_panes.push(SubPane {
+                    name: pane,
+                    series: vec![series],
+                    range: None,
+                    reference_lines: Vec::new(),
+                    visible: true,
+                }),
+            }
+        }
+        output.sub_panes.extend(sub_panes);
+        //同一种颜色的标记合成一个点序列
+        let stem = file_name.trim_end_matches(".rhai");
+        let mut markers: Vec<Series> = Vec::new();
+        for (ix, price, color) in collected.markers {
+            let series = match markers.iter_mut().find(|series| series.color == color) {
+                Some(series) => series,
+                None => {
+                    markers.push(Series {
+                        name: format!("{}标记", stem),
+                        values: vec![f64::NAN; HISTORY.datetime.len()],
+                        color,
+                        style: SeriesStyle::Dots,
+                    });
+                    markers.last_mut().unwrap()
+                }
+            };
+            series.values[ix] = price;
+        }
+        output.overlays.extend(markers);
+        output.texts.extend(collected.texts);
+    }
+    output
+}
@@ -20,6 +20,8 @@ fn vs_main(vertex_in: VertexInput) -> VertexOutput {
 
 struct CameraUniform {
     view_proj: mat4x4f,
+    pane_size: vec2f,
+    log_scale: u32, //K线图的价格纵轴是否为对数
 };
 struct ScreenUniform {
     width: f32,
@@ -32,24 +34,188 @@ var<uniform> camera_volume: CameraUniform;
 @group(0) @binding(2)
 var<uniform> screen_info: ScreenUniform;
 
+//价格坐标转换成裁剪坐标，对数纵轴时先取价格的对数，view_proj的纵轴范围也是对数
+fn candle_position(position: vec2f) -> vec4f {
+    var y = position.y;
+    if (camera_candle.log_scale != 0u) {
+        y = log(max(y, 1e-30));
+    }
+    return camera_candle.view_proj * vec4<f32>(position.x, y, 0.0, 1.0);
+}
+
 @vertex
 fn vs_main_candle(vertex_in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.pos = camera_candle.view_proj * vec4<f32>(vertex_in.position, 0.0, 1.0);
+    out.pos = candle_position(vertex_in.position);
     return out;
 }
 
+struct ColorVertexInput {
+    @location(0) position: vec2f,
+    @location(1) color: vec4f,
+    @builtin(vertex_index) index: u32,
+};
+
+struct ColorVertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
 @vertex
-fn vs_main_volume(vertex_in: VertexInput) -> VertexOutput {
-    var out: VertexOutput;
-    out.pos = camera_volume.view_proj * vec4<f32>(vertex_in.position, 0.0, 1.0);
+fn vs_main_candle_color(vertex_in: ColorVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = candle_position(vertex_in.position);
+    out.color = vertex_in.color;
+    return out;
+}
+
+//K线样式，K线和成交量柱共用
+struct CandleStyleUniform {
+    wick_color: vec4f, //透明度为0时影线和实体同色
+    half_width: f32,
+    fill: u32,
+};
+@group(0) @binding(3)
+var<uniform> candle_style: CandleStyleUniform;
+
+const BAR_UP: u32 = 1u;
+const BAR_DOWN: u32 = 2u;
+const BAR_ABOVE_PREV_CLOSE: u32 = 4u;
+const FILL_UP: u32 = 1u;
+const FILL_DOWN: u32 = 2u;
+const FILL_BY_PREV_CLOSE: u32 = 4u;
+//z超出0到1的范围，图元被裁掉
+const HIDDEN_POSITION: vec4f = vec4f(0.0, 0.0, 2.0, 1.0);
+
+struct BarVertexInput {
+    @location(0) position: vec2f,
+    @location(1) offset: f32,
+    @location(2) flags: u32,
+};
+
+fn bar_color(flags: u32) -> vec4f {
+    if ((flags & BAR_UP) != 0u) {
+        return vec4f(1.0, 0.068, 0.068, 1.0);
+    }
+    if ((flags & BAR_DOWN) != 0u) {
+        return vec4f(0.0, 1.0, 1.0, 1.0);
+    }
+    return vec4f(1.0, 1.0, 1.0, 1.0);
+}
+
+//平线没有实体，只画边框
+fn bar_filled(flags: u32) -> bool {
+    if ((flags & (BAR_UP | BAR_DOWN)) == 0u) {
+        return false;
+    }
+    if ((candle_style.fill & FILL_BY_PREV_CLOSE) != 0u) {
+        return (flags & BAR_ABOVE_PREV_CLOSE) == 0u;
+    }
+    if ((flags & BAR_UP) != 0u) {
+        return (candle_style.fill & FILL_UP) != 0u;
+    }
+    return (candle_style.fill & FILL_DOWN) != 0u;
+}
+
+fn bar_x(vertex_in: BarVertexInput) -> f32 {
+    return vertex_in.position.x + vertex_in.offset * candle_style.half_width;
+}
+
+@vertex
+fn vs_main_candle_body(vertex_in: BarVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = HIDDEN_POSITION;
+    if (bar_filled(vertex_in.flags)) {
+        out.pos = candle_position(vec2f(bar_x(vertex_in), vertex_in.position.y));
+    }
+    out.color = bar_color(vertex_in.flags);
+    return out;
+}
+
+//实心时边框和实体同色，也画出来，K线很窄时实体可能画不出像素
+@vertex
+fn vs_main_candle_outline(vertex_in: BarVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = candle_position(vec2f(bar_x(vertex_in), vertex_in.position.y));
+    out.color = bar_color(vertex_in.flags);
+    return out;
+}
+
+@vertex
+fn vs_main_candle_wick(vertex_in: BarVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = candle_position(vertex_in.position);
+    out.color = bar_color(vertex_in.flags);
+    if (candle_style.wick_color.a > 0.0) {
+        out.color = candle_style.wick_color;
+    }
+    return out;
+}
+
+@vertex
+fn vs_main_volume_body(vertex_in: BarVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = HIDDEN_POSITION;
+    if (bar_filled(vertex_in.flags)) {
+        out.pos = camera_volume.view_proj * vec4f(bar_x(vertex_in), vertex_in.position.y, 0.0, 1.0);
+    }
+    out.color = bar_color(vertex_in.flags);
+    return out;
+}
+
+@vertex
+fn vs_main_volume_outline(vertex_in: BarVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = camera_volume.view_proj * vec4f(bar_x(vertex_in), vertex_in.position.y, 0.0, 1.0);
+    out.color = bar_color(vertex_in.flags);
+    return out;
+}
+
+//每个副图有自己的纵轴范围，相机放在单独的绑定组里
+@group(1) @binding(0)
+var<uniform> camera_sub: CameraUniform;
+
+@vertex
+fn vs_main_sub_color(vertex_in: ColorVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = camera_sub.view_proj * vec4<f32>(vertex_in.position, 0.0, 1.0);
+    out.color = vertex_in.color;
+    return out;
+}
+
+//点画成边长为2倍DOT_RADIUS像素的正方形，同一个点的6个顶点按序号偏移到两个三角形的角上
+const DOT_RADIUS: f32 = 3.0;
+
+fn dot_offset(index: u32, pane_size: vec2f) -> vec2f {
+    var corners = array<vec2f, 6>(
+        vec2f(-1.0, -1.0), vec2f(1.0, -1.0), vec2f(1.0, 1.0),
+        vec2f(-1.0, -1.0), vec2f(1.0, 1.0), vec2f(-1.0, 1.0),
+    );
+    return corners[index % 6u] * DOT_RADIUS * 2.0 / pane_size;
+}
+
+@vertex
+fn vs_main_candle_dot(vertex_in: ColorVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = candle_position(vertex_in.position);
+    out.pos += vec4<f32>(dot_offset(vertex_in.index, camera_candle.pane_size), 0.0, 0.0);
+    out.color = vertex_in.color;
+    return out;
+}
+
+@vertex
+fn vs_main_sub_dot(vertex_in: ColorVertexInput) -> ColorVertexOutput {
+    var out: ColorVertexOutput;
+    out.pos = camera_sub.view_proj * vec4<f32>(vertex_in.position, 0.0, 1.0);
+    out.pos += vec4<f32>(dot_offset(vertex_in.index, camera_sub.pane_size), 0.0, 0.0);
+    out.color = vertex_in.color;
     return out;
 }
 
 @vertex
 fn vs_main_buy_cover(vertex_in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.pos = camera_candle.view_proj * vec4<f32>(vertex_in.position, 0.0, 1.0);
+    out.pos = candle_position(vertex_in.position);
     if(vertex_in.index % 3 == 1){
         out.pos.x -= 10.0/screen_info.width;
         out.pos.y -= 20.0/screen_info.height;
@@ -64,7 +230,7 @@ fn vs_main_buy_cover(vertex_in: VertexInput) -> VertexOutput {
 @vertex
 fn vs_main_sell_short(vertex_in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.pos = camera_candle.view_proj * vec4<f32>(vertex_in.position, 0.0, 1.0);
+    out.pos = candle_position(vertex_in.position);
     if(vertex_in.index % 3 == 1){
         out.pos.x += 10.0/screen_info.width;
         out.pos.y += 20.0/screen_info.height;
@@ -106,6 +272,17 @@ fn fs_main_stay(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
     return vec4<f32>(1.0, 1.0, 1.0, 1.0);
 }
 
+@fragment
+fn fs_main_close_line(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.3, 0.65, 1.0, 1.0);
+}
+
+//面积图的填充不透明，画在均线等指标下面
+@fragment
+fn fs_main_area(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.08, 0.2, 0.35, 1.0);
+}
+
 @fragment
 fn fs_main_profit(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
     return vec4<f32>(1.0, 0.0, 0.0, 1.0);
@@ -125,3 +302,70 @@ fn fs_main_buy_sell(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
 fn fs_main_short_cover(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
     return vec4<f32>(1.0, 0.0, 1.0, 1.0);
 }
+
+@fragment
+fn fs_main_highlight(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.6, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main_selected(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.2, 0.2, 0.4, 1.0);
+}
+
+@fragment
+fn fs_main_order_working(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 0.6, 1.0, 1.0);
+}
+
+@fragment
+fn fs_main_order_filled(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
+
+@fragment
+fn fs_main_order_cancelled(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.5, 0.5, 0.5, 1.0);
+}
+
+//停止单画虚线
+fn is_dash_gap(pos: vec4<f32>) -> bool {
+    return (u32(pos.x) / 4u) % 2u == 1u;
+}
+
+@fragment
+fn fs_main_order_working_stop(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    if(is_dash_gap(fragment_in.pos)){
+        discard;
+    }
+    return vec4<f32>(0.0, 0.6, 1.0, 1.0);
+}
+
+@fragment
+fn fs_main_order_filled_stop(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    if(is_dash_gap(fragment_in.pos)){
+        discard;
+    }
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
+
+@fragment
+fn fs_main_order_cancelled_stop(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    if(is_dash_gap(fragment_in.pos)){
+        discard;
+    }
+    return vec4<f32>(0.5, 0.5, 0.5, 1.0);
+}
+
+@fragment
+fn fs_main_atr_stop(fragment_in: FragmentInput) -> @location(0) vec4<f32> {
+    if(is_dash_gap(fragment_in.pos)){
+        discard;
+    }
+    return vec4<f32>(1.0, 0.4, 0.4, 1.0);
+}
+
+@fragment
+fn fs_main_color(fragment_in: ColorVertexOutput) -> @location(0) vec4<f32> {
+    return fragment_in.color;
+}
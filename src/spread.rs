@@ -0,0 +1,292 @@
+use std::sync::{LazyLock, Mutex};
+
+use crate::config::{config_error, CONFIG};
+use crate::manager::{
+    data_file_name, data_file_path, read_history_file, read_trades_file, HistoryData, TradeData,
+};
+
+//价差为A - k * B，比价为A / B
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpreadMode {
+    Difference,
+    Ratio,
+}
+
+//两条腿的K线文件合成的价差或比价，配置了[spread]节时代替history.dat作为主图
+pub struct Spread {
+    pub name: String,
+    leg_a: String,
+    leg_b: String,
+    k: f64,
+    mode: SpreadMode,
+    //两条腿各自的成交文件
+    leg_trades: Option<(String, String)>,
+    //合成好的K线，HISTORY初始化时取走
+    history: Mutex<Option<HistoryData>>,
+}
+
+//配置在[spread]节：leg_a、leg_b为两条腿的K线文件，格式同history.dat；k为B的系数，默认为1
+//mode为difference或ratio，默认为difference；name默认由两个文件名组成
+//trades_a、trades_b为两条腿各自的成交文件，格式同trades.dat，要么都配置，要么都不配置
+pub static SPREAD: LazyLock<Option<Spread>> = LazyLock::new(|| {
+    let leg_a = CONFIG.get("spread", "leg_a")?;
+    //价差配置不完整时画history.dat
+    let Some(leg_b) = CONFIG.get("spread", "leg_b") else {
+        config_error(format!(
+            "配置了leg_a = {}但没有配置leg_b，改为画history.dat",
+            leg_a
+        ));
+        return None;
+    };
+    for leg in [leg_a, leg_b] {
+        if !std::path::Path::new(&data_file_path(leg)).is_file() {
+            config_error(format!(
+                "价差文件{}不存在，改为画history.dat",
+                data_file_path(leg)
+            ));
+            return None;
+        }
+    }
+    let k = CONFIG.parse_or("spread", "k", 1.0, |text| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|k| k.is_finite() && *k != 0.0)
+    });
+    let mode = CONFIG.parse_or(
+        "spread",
+        "mode",
+        SpreadMode::Difference,
+        |text| match text {
+            "difference" => Some(SpreadMode::Difference),
+            "ratio" => Some(SpreadMode::Ratio),
+            _ => None,
+        },
+    );
+    let name = CONFIG.get("spread", "name").map_or_else(
+        || {
+            let (a, b) = (data_file_name(leg_a), data_file_name(leg_b));
+            match mode {
+                SpreadMode::Difference if k == 1.0 => format!("{}-{}", a, b),
+                SpreadMode::Difference => format!("{}-{}×{}", a, k, b),
+                SpreadMode::Ratio => format!("{}/{}", a, b),
+            }
+        },
+        str::to_string,
+    );
+    let leg_trades = match (
+        CONFIG.get("spread", "trades_a"),
+        CONFIG.get("spread", "trades_b"),
+    ) {
+        (Some(trades_a), Some(trades_b)) => {
+            let paths = (data_file_path(trades_a), data_file_path(trades_b));
+            let missing = [&paths.0, &paths.1]
+                .into_iter()
+                .find(|path| !std::path::Path::new(path).is_file())
+                .cloned();
+            match missing {
+                Some(path) => {
+                    config_error(format!("成交文件{}不存在，不画两条腿的成交", path));
+                    None
+                }
+                None => Some(paths),
+            }
+        }
+        (None, None) => None,
+        _ => {
+            config_error("trades_a和trades_b只配置了一个，不画两条腿的成交".to_string());
+            None
+        }
+    };
+    let mut spread = Spread {
+        name,
+        leg_a: data_file_path(leg_a),
+        leg_b: data_file_path(leg_b),
+        k,
+        mode,
+        leg_trades,
+        history: Mutex::new(None),
+    };
+    let history = spread.combine_history(
+        &read_history_file(&spread.leg_a),
+        &read_history_file(&spread.leg_b),
+    );
+    if history.datetime.is_empty() {
+        config_error(format!(
+            "{}和{}没有相同时间的K线，改为画history.dat",
+            spread.leg_a, spread.leg_b
+        ));
+        return None;
+    }
+    spread.history = Mutex::new(Some(history));
+    Some(spread)
+});
+
+impl Spread {
+    fn combine(&self, a: f64, b: f64) -> f64 {
+        match self.mode {
+            SpreadMode::Difference => a - self.k * b,
+            SpreadMode::Ratio => a / b,
+        }
+    }
+
+    //只保留两条腿都有的K线；两条腿K线内的最高最低价不一定同时出现，所以最高最低价只取开盘价和收盘价中的较大较小者
+    //成交量取两条腿中较小的一个；比价时B为0的K线算不出来，跳过
+    fn combine_history(&self, a: &HistoryData, b: &HistoryData) -> HistoryData {
+        let mut history = HistoryData::default();
+        let (mut i, mut j) = (0, 0);
+        while i < a.datetime.len() && j < b.datetime.len() {
+            if a.datetime[i] < b.datetime[j] {
+                i += 1;
+                continue;
+            }
+            if a.datetime[i] > b.datetime[j] {
+                j += 1;
+                continue;
+            }
+            let open = self.combine(a.open_price[i], b.open_price[j]);
+            let close = self.combine(a.close_price[i], b.close_price[j]);
+            if open.is_finite() && close.is_finite() {
+                history.datetime.push(a.datetime[i]);
+                history.open_price.push(open);
+                history.high_price.push(open.max(close));
+                history.low_price.push(open.min(close));
+                history.close_price.push(close);
+                history.volume.push(a.volume[i].min(b.volume[j]));
+            }
+            i += 1;
+            j += 1;
+        }
+        history
+    }
+
+    pub fn take_history(&self) -> HistoryData {
+        self.history.lock().unwrap().take().unwrap_or_default()
+    }
+
+    //trades.dat里是价差价格的成交，配置了两条腿的成交文件时，同一时间的A腿和B腿成交合成一笔价差成交加进来
+    //方向和数量取A腿的，找不到另一条腿的成交忽略并提示
+    pub fn trades(&self, mut trades: Vec<TradeData>) -> Vec<TradeData> {
+        let Some((trades_a, trades_b)) = &self.leg_trades else {
+            return trades;
+        };
+        let (leg_trades, unmatched) =
+            self.combine_trades(read_trades_file(trades_a), read_trades_file(trades_b));
+        if unmatched > 0 {
+            config_error(format!(
+                "有{}笔腿的成交找不到同一时间另一条腿的成交，已忽略",
+                unmatched
+            ));
+        }
+        trades.extend(leg_trades);
+        trades.sort_by_key(|trade| trade.datetime);
+        trades
+    }
+
+    //返回合成后的成交和忽略的腿成交笔数
+    fn combine_trades(
+        &self,
+        leg_a: Vec<TradeData>,
+        mut leg_b: Vec<TradeData>,
+    ) -> (Vec<TradeData>, usize) {
+        let mut result = Vec::new();
+        let mut unmatched = 0;
+        for trade_a in leg_a {
+            let matched = leg_b
+                .iter()
+                .position(|trade_b| trade_b.datetime == trade_a.datetime);
+            match matched {
+                Some(i) => {
+                    let trade_b = leg_b.remove(i);
+                    result.push(TradeData {
+                        price: self.combine(trade_a.price, trade_b.price),
+                        ..trade_a
+                    });
+                }
+                None => unmatched += 1,
+            }
+        }
+        unmatched += leg_b.len();
+        result.sort_by_key(|trade| trade.datetime);
+        (result, unmatched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+    fn minute(i: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+            + Duration::minutes(i)
+    }
+
+    fn spread(mode: SpreadMode) -> Spread {
+        Spread {
+            name: String::new(),
+            leg_a: String::new(),
+            leg_b: String::new(),
+            k: 1.0,
+            mode,
+            leg_trades: None,
+            history: Mutex::new(None),
+        }
+    }
+
+    //第i根K线的时间为minute(minutes[i])，开盘价和收盘价都是close_price[i]
+    fn history(minutes: &[i64], close_price: &[f64]) -> HistoryData {
+        HistoryData {
+            datetime: minutes.iter().map(|i| minute(*i)).collect(),
+            open_price: close_price.to_vec(),
+            high_price: close_price.iter().map(|price| price + 1.0).collect(),
+            low_price: close_price.iter().map(|price| price - 1.0).collect(),
+            close_price: close_price.to_vec(),
+            volume: vec![1.0; close_price.len()],
+        }
+    }
+
+    //成交时间和K线时间相同，和实际的成交文件一样
+    fn trade(i: i64, direction: u8, price: f64) -> TradeData {
+        TradeData {
+            datetime: minute(i),
+            direction,
+            price,
+            volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn common_finite_bars_only() {
+        let a = history(&[0, 1, 2, 3], &[10.0, 11.0, 12.0, 13.0]);
+        let b = history(&[1, 2, 3, 4], &[2.0, 0.0, 4.0, 5.0]);
+        //第2分钟B为0，比价算不出来
+        let ratio = spread(SpreadMode::Ratio).combine_history(&a, &b);
+        assert_eq!(ratio.datetime, [minute(1), minute(3)]);
+        assert_eq!(ratio.close_price, [5.5, 3.25]);
+        let difference = spread(SpreadMode::Difference).combine_history(&a, &b);
+        assert_eq!(difference.close_price, [9.0, 12.0, 9.0]);
+        let none = spread(SpreadMode::Difference).combine_history(&a, &history(&[9], &[1.0]));
+        assert!(none.datetime.is_empty());
+    }
+
+    #[test]
+    fn leg_fills_become_spread_trades() {
+        //日历价差两条腿的价格很接近，只能按文件区分是哪条腿
+        let leg_a = vec![
+            trade(0, 1, 3500.0),
+            trade(2, 2, 3519.0),
+            trade(3, 1, 3521.0),
+        ];
+        let leg_b = vec![trade(2, 1, 3517.0), trade(0, 2, 3499.0)];
+        let (trades, unmatched) = spread(SpreadMode::Difference).combine_trades(leg_a, leg_b);
+        let trades: Vec<_> = trades
+            .iter()
+            .map(|trade| (trade.datetime, trade.direction, trade.price))
+            .collect();
+        assert_eq!(trades, [(minute(0), 1, 1.0), (minute(2), 2, 2.0)]);
+        assert_eq!(unmatched, 1);
+    }
+}
@@ -1,7 +1,10 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
 use glam::{Mat4, Vec3, Vec4};
 
+use crate::config::{parse_color, CONFIG};
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct Vertex {
@@ -23,37 +26,205 @@ pub fn create_vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     }
 }
 
+//带颜色的顶点，用于颜色可配置的指标
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ColorVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for ColorVertex {}
+unsafe impl bytemuck::Pod for ColorVertex {}
+
+pub fn create_color_vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: size_of::<ColorVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x2,
+            },
+            wgpu::VertexAttribute {
+                offset: size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                shader_location: 1,
+                format: wgpu::VertexFormat::Float32x4,
+            },
+        ],
+    }
+}
+
+//K线和成交量柱的顶点，position的横坐标为K线中心，offset为-1、0、1表示实体左边、中间、右边，
+//着色器按实体宽度展开，再按flags和样式决定颜色和是否实心
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BarVertex {
+    pub position: [f32; 2],
+    pub offset: f32,
+    pub flags: u32,
+}
+
+pub const BAR_UP: u32 = 1;
+pub const BAR_DOWN: u32 = 2;
+pub const BAR_ABOVE_PREV_CLOSE: u32 = 4; //收盘价高于上一根的收盘价
+
+pub fn create_bar_vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: size_of::<BarVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x2,
+            },
+            wgpu::VertexAttribute {
+                offset: size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                shader_location: 1,
+                format: wgpu::VertexFormat::Float32,
+            },
+            wgpu::VertexAttribute {
+                offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                shader_location: 2,
+                format: wgpu::VertexFormat::Uint32,
+            },
+        ],
+    }
+}
+
+//相邻两个值都有效时连一段线，用LineList画，NaN处自然断开
+pub fn make_line_vertex(values: &[f64], color: [f32; 4]) -> Vec<ColorVertex> {
+    let mut vertex = Vec::new();
+    for (i, pair) in values.windows(2).enumerate() {
+        if pair[0].is_finite() && pair[1].is_finite() {
+            vertex.extend(&[
+                ColorVertex {
+                    position: [i as f32, pair[0] as f32],
+                    color,
+                },
+                ColorVertex {
+                    position: [(i + 1) as f32, pair[1] as f32],
+                    color,
+                },
+            ]);
+        }
+    }
+    vertex
+}
+
+//从0到每个值画一条竖线，用LineList画，正值和负值颜色不同
+pub fn make_histogram_vertex(
+    values: &[f64],
+    up_color: [f32; 4],
+    down_color: [f32; 4],
+) -> Vec<ColorVertex> {
+    let mut vertex = Vec::new();
+    for (i, value) in values.iter().enumerate() {
+        if value.is_finite() {
+            let color = if *value >= 0.0 { up_color } else { down_color };
+            vertex.extend(&[
+                ColorVertex {
+                    position: [i as f32, 0.0],
+                    color,
+                },
+                ColorVertex {
+                    position: [i as f32, *value as f32],
+                    color,
+                },
+            ]);
+        }
+    }
+    vertex
+}
+
+//每个点6个相同的顶点，着色器按顶点序号偏移成一个小正方形，用TriangleList画
+pub fn make_dot_vertex(values: &[f64], color: [f32; 4]) -> Vec<ColorVertex> {
+    let mut vertex = Vec::new();
+    for (i, value) in values.iter().enumerate() {
+        if value.is_finite() {
+            vertex.extend(
+                [ColorVertex {
+                    position: [i as f32, *value as f32],
+                    color,
+                }; 6],
+            );
+        }
+    }
+    vertex
+}
+
+//两条线之间的填充，用TriangleStrip画，从两条线都有效的第一个位置开始，遇到NaN结束
+pub fn make_band_vertex(upper: &[f64], lower: &[f64], color: [f32; 4]) -> Vec<ColorVertex> {
+    let mut vertex = Vec::new();
+    let Some(start) = (0..upper.len()).find(|i| upper[*i].is_finite() && lower[*i].is_finite())
+    else {
+        return vertex;
+    };
+    for i in start..upper.len() {
+        if !upper[i].is_finite() || !lower[i].is_finite() {
+            break;
+        }
+        vertex.extend(&[
+            ColorVertex {
+                position: [i as f32, lower[i] as f32],
+                color,
+            },
+            ColorVertex {
+                position: [i as f32, upper[i] as f32],
+                color,
+            },
+        ]);
+    }
+    vertex
+}
+
 pub static VP_MATRIX: Mutex<Mat4> = Mutex::new(Mat4::IDENTITY);
+static LOG_SCALE: AtomicBool = AtomicBool::new(false);
+
+//蜡烛图坐标转换成裁剪坐标，和着色器里的candle_position一致，需在VP_MATRIX更新之后调用
+pub fn project_candle_position(mut position: Vec4) -> Vec4 {
+    if LOG_SCALE.load(Ordering::Relaxed) {
+        position.y = position.y.max(1e-30).ln();
+    }
+    *VP_MATRIX.lock().unwrap() * position
+}
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
     view_proj: [[f32; 4]; 4],
+    pane_size: [f32; 2], //图的像素大小，着色器用它把像素换算成裁剪坐标
+    log_scale: u32,
+    _padding: u32,
 }
 
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
             view_proj: glam::Mat4::IDENTITY.to_cols_array_2d(),
+            pane_size: [1.0, 1.0],
+            log_scale: 0,
+            _padding: 0,
         }
     }
 
-    pub fn update_view_proj_candle(
-        &mut self,
-        left_ix: i64,
-        right_ix: i64,
-        min_price: f64,
-        max_price: f64,
-    ) {
+    pub fn set_pane_size(&mut self, width: f32, height: f32) {
+        self.pane_size = [width.max(1.0), height.max(1.0)];
+    }
+
+    //横轴为left_ix到right_ix，纵轴为min_value到max_value
+    fn view_proj(left_ix: i64, right_ix: i64, min_value: f64, max_value: f64) -> Mat4 {
         let view = glam::Mat4::look_at_rh(
             Vec3 {
                 x: (left_ix + right_ix) as f32 / 2.0,
-                y: (min_price + max_price) as f32 / 2.0,
+                y: (min_value + max_value) as f32 / 2.0,
                 z: 0.0,
             },
             Vec3 {
                 x: (left_ix + right_ix) as f32 / 2.0,
-                y: (min_price + max_price) as f32 / 2.0,
+                y: (min_value + max_value) as f32 / 2.0,
                 z: -1.0,
             },
             Vec3::Y,
@@ -61,39 +232,47 @@ impl CameraUniform {
         let proj = glam::Mat4::orthographic_rh(
             -((right_ix - left_ix) as f32 / 2.0 + 0.5),
             (right_ix - left_ix) as f32 / 2.0 + 0.5,
-            -((max_price - min_price) as f32 / 2.0),
-            (max_price - min_price) as f32 / 2.0,
+            -((max_value - min_value) as f32 / 2.0),
+            (max_value - min_value) as f32 / 2.0,
             0.0,
             1.0,
         );
-        let vp = proj * view;
+        proj * view
+    }
+
+    //对数纵轴时纵轴范围取对数，着色器里顶点的价格也取对数
+    pub fn update_view_proj_candle(
+        &mut self,
+        left_ix: i64,
+        right_ix: i64,
+        min_price: f64,
+        max_price: f64,
+        log_scale: bool,
+    ) {
+        let vp = if log_scale {
+            Self::view_proj(left_ix, right_ix, min_price.ln(), max_price.ln())
+        } else {
+            Self::view_proj(left_ix, right_ix, min_price, max_price)
+        };
         self.view_proj = vp.to_cols_array_2d();
+        self.log_scale = log_scale as u32;
         *VP_MATRIX.lock().unwrap() = vp;
+        LOG_SCALE.store(log_scale, Ordering::Relaxed);
     }
 
     pub fn update_view_proj_volume(&mut self, left_ix: i64, right_ix: i64, max_volume: f64) {
-        let view = glam::Mat4::look_at_rh(
-            Vec3 {
-                x: (left_ix + right_ix) as f32 / 2.0,
-                y: max_volume as f32 / 2.0,
-                z: 0.0,
-            },
-            Vec3 {
-                x: (left_ix + right_ix) as f32 / 2.0,
-                y: max_volume as f32 / 2.0,
-                z: -1.0,
-            },
-            Vec3::Y,
-        );
-        let proj = glam::Mat4::orthographic_rh(
-            -((right_ix - left_ix) as f32 / 2.0 + 0.5),
-            (right_ix - left_ix) as f32 / 2.0 + 0.5,
-            -(max_volume as f32 / 2.0),
-            max_volume as f32 / 2.0,
-            0.0,
-            1.0,
-        );
-        self.view_proj = (proj * view).to_cols_array_2d();
+        self.view_proj = Self::view_proj(left_ix, right_ix, 0.0, max_volume).to_cols_array_2d();
+    }
+
+    pub fn update_view_proj_sub(
+        &mut self,
+        left_ix: i64,
+        right_ix: i64,
+        min_value: f64,
+        max_value: f64,
+    ) {
+        self.view_proj =
+            Self::view_proj(left_ix, right_ix, min_value, max_value).to_cols_array_2d();
     }
 }
 
@@ -113,6 +292,55 @@ impl ScreenUniform {
     }
 }
 
+//K线样式，在[candle]节中配置，K线和成交量柱都按它画
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CandleStyleUniform {
+    wick_color: [f32; 4], //透明度为0时影线和实体同色
+    half_width: f32,
+    fill: u32,
+    _padding: [u32; 2],
+}
+
+pub const FILL_UP: u32 = 1;
+pub const FILL_DOWN: u32 = 2;
+pub const FILL_BY_PREV_CLOSE: u32 = 4; //收盘价高于上一根收盘价时空心，否则实心，不看涨跌
+
+impl CandleStyleUniform {
+    pub fn from_config() -> Self {
+        let fill_key = |key: &str, default: bool, flag: u32| {
+            let filled = CONFIG.parse_or("candle", key, default, |text| match text {
+                "filled" => Some(true),
+                "hollow" => Some(false),
+                _ => None,
+            });
+            if filled {
+                flag
+            } else {
+                0
+            }
+        };
+        let mut fill = fill_key("up", false, FILL_UP) | fill_key("down", true, FILL_DOWN);
+        if CONFIG.parse_or("candle", "hollow_above_prev_close", false, |text| {
+            text.parse::<bool>().ok()
+        }) {
+            fill |= FILL_BY_PREV_CLOSE;
+        }
+        let body_width = CONFIG.parse_or("candle", "body_width", 0.8, |text| {
+            text.parse::<f32>()
+                .ok()
+                .filter(|width| *width > 0.0 && *width <= 1.0)
+        });
+        let wick_color = CONFIG.parse_or("candle", "wick_color", [0.0; 4], parse_color);
+        Self {
+            wick_color,
+            half_width: body_width / 2.0,
+            fill,
+            _padding: [0; 2],
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct RectangleFrame {
     pub x: f32,
@@ -226,19 +454,40 @@ impl Line {
     }
 }
 
+//实体按TriangleList画，边框和影线按LineList画
+#[derive(Default)]
 pub struct CandleVertex {
+    pub body: Vec<BarVertex>,
+    pub outline: Vec<BarVertex>,
+    pub wick: Vec<BarVertex>,
+}
+
+#[derive(Default)]
+pub struct OhlcVertex {
     pub up: Vec<Vertex>,
     pub down: Vec<Vertex>,
-    pub down_hl: Vec<Vertex>,
     pub stay: Vec<Vertex>,
 }
 
 pub struct VolumeVertex {
-    pub up: Vec<Vertex>,
-    pub down: Vec<Vertex>,
-    pub stay: Vec<Vertex>,
+    pub body: Vec<BarVertex>,
+    pub outline: Vec<BarVertex>,
+}
+
+//市场轮廓的方块按TriangleList画，POC、价值区和初始平衡区的线按LineList画
+pub struct TpoVertex {
+    pub blocks: Vec<ColorVertex>,
+    pub lines: Vec<ColorVertex>,
+}
+
+//足迹图的格子和每根K线左边的细实体按TriangleList画，影线和成交量最多一行的框按LineList画
+#[derive(Default)]
+pub struct FootprintVertex {
+    pub cells: Vec<ColorVertex>,
+    pub lines: Vec<ColorVertex>,
 }
 
+#[derive(Default)]
 pub struct TradePairVertex {
     pub profit: Vec<Vertex>,
     pub loss: Vec<Vertex>,
@@ -251,3 +500,12 @@ pub struct TradePairVertex {
     pub short_text: Vec<(Vec4, String)>,
     pub cover_text: Vec<(Vec4, String)>,
 }
+
+pub struct OrderVertex {
+    pub working: Vec<Vertex>,
+    pub filled: Vec<Vertex>,
+    pub cancelled: Vec<Vertex>,
+    pub stop_working: Vec<Vertex>,
+    pub stop_filled: Vec<Vertex>,
+    pub stop_cancelled: Vec<Vertex>,
+}
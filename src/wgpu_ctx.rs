@@ -1,9 +1,26 @@
-use crate::manager::{Manager, CANDLE_VERTEX, HISTORY, TRADE_PAIRS_VERTEX, VOLUME_VERTEX};
+use crate::compare::{CompareMode, CompareView, COMPARE};
+use crate::export::{default_export_dir, export_trade_pairs};
+use crate::footprint::FOOTPRINT;
+use crate::heatmap::DEPTH_HEATMAP;
+use crate::indicator::{format_value, BandFill, Series, SeriesStyle, SubPane};
+use crate::manager::{
+    ChartStyle, Manager, PriceScale, TradeHover, TradeSort, AREA_VERTEX, ATR_STOP_VERTEX,
+    CANDLE_VERTEX, CLOSE_LINE_VERTEX, FOOTPRINT_VERTEX, HEATMAP_VERTEX, HEIKIN_ASHI_VERTEX,
+    HISTORY, OHLC_VERTEX, ORDERS_VERTEX, TPO_VERTEX, TRADE_PAIRS, TRADE_PAIRS_VERTEX,
+    VOLUME_VERTEX,
+};
+use crate::price_chart::PriceChart;
+use crate::profile::{tpo_letter, Profile};
 use crate::vertex::{
-    create_vertex_buffer_layout, CameraUniform, Line, RectangleFilled, RectangleFrame,
-    ScreenUniform, VP_MATRIX,
+    create_bar_vertex_buffer_layout, create_color_vertex_buffer_layout,
+    create_vertex_buffer_layout, make_band_vertex, make_dot_vertex, make_histogram_vertex,
+    make_line_vertex, project_candle_position, CameraUniform, CandleStyleUniform, CandleVertex,
+    ColorVertex, Line, OhlcVertex, RectangleFilled, RectangleFrame, ScreenUniform, TradePairVertex,
+    Vertex,
 };
+use glam::Vec4;
 use std::borrow::Cow;
+use std::ops::Range;
 use std::sync::Arc;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::MemoryHints::Performance;
@@ -20,8 +37,33 @@ const AXIS_X_HEIGHT: f32 = 32.0;
 const AXIS_X_LABEL_BIAS: f32 = 30.0;
 const AXIS_Y_WIDTH: f32 = 80.0;
 const AXIS_Y_LABEL_BIAS: f32 = 8.0;
-const HINT_HEIGHT: f32 = 80.0;
+const HINT_ROW_HEIGHT: f32 = 20.0;
+//按键提示每列的行数，从交易图例右边开始排
+const KEY_HINT_ROWS: usize = 3;
+const KEY_HINT_X: f32 = 460.0;
 const INFO_SIZE: (f32, f32) = (80.0, 320.0);
+const INFO_ITEM_HEIGHT: f32 = 48.0;
+//信息栏固定的项数：日期、时间、开高低收和成交量
+const INFO_BASE_ITEM_COUNT: usize = 7;
+//K线图、成交量和每个副图的高度比例
+const CANDLE_PANE_WEIGHT: f32 = 0.7;
+const VOLUME_PANE_WEIGHT: f32 = 0.3;
+const SUB_PANE_WEIGHT: f32 = 0.3;
+const REFERENCE_LINE_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+const TOOLTIP_WIDTH: f32 = 200.0;
+const TOOLTIP_LINE_HEIGHT: f32 = 20.0;
+const TRADE_LIST_WIDTH: f32 = 530.0;
+const TRADE_LIST_ROW_HEIGHT: f32 = 20.0;
+const TRADE_LIST_COLUMNS: [(&str, f32); 8] = [
+    ("开仓时间", 4.0),
+    ("平仓时间", 104.0),
+    ("方向", 204.0),
+    ("开仓价", 236.0),
+    ("平仓价", 302.0),
+    ("数量", 368.0),
+    ("盈亏", 412.0),
+    ("K线数", 486.0),
+];
 
 pub struct RectangleFramePack {
     render_pipeline: wgpu::RenderPipeline,
@@ -129,58 +171,114 @@ impl LinePack {
 }
 
 pub struct CandlePack {
+    //K线的实体、边框和影线，样式在着色器里按[candle]配置决定
+    body_render_pipeline: wgpu::RenderPipeline,
+    body_vertex_buffer: Option<wgpu::Buffer>,
+    outline_render_pipeline: wgpu::RenderPipeline,
+    outline_vertex_buffer: Option<wgpu::Buffer>,
+    wick_render_pipeline: wgpu::RenderPipeline,
+    wick_vertex_buffer: Option<wgpu::Buffer>,
+    //美国线和点数图按涨跌画成不同颜色的线
     up_render_pipeline: wgpu::RenderPipeline,
-    up_vertex_buffer: wgpu::Buffer,
     down_render_pipeline: wgpu::RenderPipeline,
-    down_vertex_buffer: wgpu::Buffer,
-    down_hl_render_pipeline: wgpu::RenderPipeline,
-    down_hl_vertex_buffer: wgpu::Buffer,
     stay_render_pipeline: wgpu::RenderPipeline,
-    stay_vertex_buffer: wgpu::Buffer,
+    //折线和面积图
+    close_line_render_pipeline: wgpu::RenderPipeline,
+    close_line_vertex_buffer: Option<wgpu::Buffer>,
+    area_render_pipeline: wgpu::RenderPipeline,
+    area_vertex_buffer: Option<wgpu::Buffer>,
+    ohlc_up_vertex_buffer: Option<wgpu::Buffer>,
+    ohlc_down_vertex_buffer: Option<wgpu::Buffer>,
+    ohlc_stay_vertex_buffer: Option<wgpu::Buffer>,
+    //平均K线，复用K线的管线
+    heikin_ashi_body_vertex_buffer: Option<wgpu::Buffer>,
+    heikin_ashi_outline_vertex_buffer: Option<wgpu::Buffer>,
+    heikin_ashi_wick_vertex_buffer: Option<wgpu::Buffer>,
 }
 
 impl CandlePack {
+    //用K线的管线画一组实体、边框和影线
+    fn draw(
+        &self,
+        rpass: &mut wgpu::RenderPass,
+        buffers: [&Option<wgpu::Buffer>; 3],
+        vertex: &CandleVertex,
+    ) {
+        let parts = [
+            (&self.body_render_pipeline, buffers[0], &vertex.body),
+            (&self.outline_render_pipeline, buffers[1], &vertex.outline),
+            (&self.wick_render_pipeline, buffers[2], &vertex.wick),
+        ];
+        for (render_pipeline, buffer, vertex) in parts {
+            if let Some(buffer) = buffer {
+                rpass.set_pipeline(render_pipeline);
+                rpass.set_vertex_buffer(0, buffer.slice(..));
+                rpass.draw(0..vertex.len() as u32, 0..1);
+            }
+        }
+    }
+
+    //按涨跌颜色画一组线
+    fn draw_lines(
+        &self,
+        rpass: &mut wgpu::RenderPass,
+        buffers: [&Option<wgpu::Buffer>; 3],
+        vertex: &OhlcVertex,
+    ) {
+        let parts = [
+            (&self.up_render_pipeline, buffers[0], &vertex.up),
+            (&self.down_render_pipeline, buffers[1], &vertex.down),
+            (&self.stay_render_pipeline, buffers[2], &vertex.stay),
+        ];
+        for (render_pipeline, buffer, vertex) in parts {
+            if let Some(buffer) = buffer {
+                rpass.set_pipeline(render_pipeline);
+                rpass.set_vertex_buffer(0, buffer.slice(..));
+                rpass.draw(0..vertex.len() as u32, 0..1);
+            }
+        }
+    }
+
     pub fn new(
         device: &wgpu::Device,
         swap_chain_format: wgpu::TextureFormat,
         camera_bind_group_layout: &BindGroupLayout,
     ) -> Self {
-        let up_render_pipeline = create_candle_pipeline(
+        let body_render_pipeline = create_bar_pipeline(
             device,
             swap_chain_format,
-            "fs_main_up",
+            "vs_main_candle_body",
+            wgpu::PrimitiveTopology::TriangleList,
+            camera_bind_group_layout,
+        );
+        let outline_render_pipeline = create_bar_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_outline",
             wgpu::PrimitiveTopology::LineList,
             camera_bind_group_layout,
         );
-        let up_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&CANDLE_VERTEX.up),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let down_render_pipeline = create_candle_pipeline(
+        let wick_render_pipeline = create_bar_pipeline(
             device,
             swap_chain_format,
-            "fs_main_down",
-            wgpu::PrimitiveTopology::TriangleList,
+            "vs_main_candle_wick",
+            wgpu::PrimitiveTopology::LineList,
             camera_bind_group_layout,
         );
-        let down_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&CANDLE_VERTEX.down),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let down_hl_render_pipeline = create_candle_pipeline(
+        let up_render_pipeline = create_candle_pipeline(
+            device,
+            swap_chain_format,
+            "fs_main_up",
+            wgpu::PrimitiveTopology::LineList,
+            camera_bind_group_layout,
+        );
+        let down_render_pipeline = create_candle_pipeline(
             device,
             swap_chain_format,
             "fs_main_down",
             wgpu::PrimitiveTopology::LineList,
             camera_bind_group_layout,
         );
-        let down_hl_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&CANDLE_VERTEX.down_hl),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
         let stay_render_pipeline = create_candle_pipeline(
             device,
             swap_chain_format,
@@ -188,31 +286,92 @@ impl CandlePack {
             wgpu::PrimitiveTopology::LineList,
             camera_bind_group_layout,
         );
-        let stay_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&CANDLE_VERTEX.stay),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
+        let close_line_render_pipeline = create_candle_pipeline(
+            device,
+            swap_chain_format,
+            "fs_main_close_line",
+            wgpu::PrimitiveTopology::LineStrip,
+            camera_bind_group_layout,
+        );
+        let area_render_pipeline = create_candle_pipeline(
+            device,
+            swap_chain_format,
+            "fs_main_area",
+            wgpu::PrimitiveTopology::TriangleStrip,
+            camera_bind_group_layout,
+        );
         CandlePack {
+            body_render_pipeline,
+            body_vertex_buffer: create_vertex_buffer(device, &CANDLE_VERTEX.body),
+            outline_render_pipeline,
+            outline_vertex_buffer: create_vertex_buffer(device, &CANDLE_VERTEX.outline),
+            wick_render_pipeline,
+            wick_vertex_buffer: create_vertex_buffer(device, &CANDLE_VERTEX.wick),
             up_render_pipeline,
-            up_vertex_buffer,
             down_render_pipeline,
-            down_vertex_buffer,
-            down_hl_render_pipeline,
-            down_hl_vertex_buffer,
             stay_render_pipeline,
-            stay_vertex_buffer,
+            close_line_render_pipeline,
+            close_line_vertex_buffer: create_vertex_buffer(device, &CLOSE_LINE_VERTEX),
+            area_render_pipeline,
+            area_vertex_buffer: create_vertex_buffer(device, &AREA_VERTEX),
+            ohlc_up_vertex_buffer: create_vertex_buffer(device, &OHLC_VERTEX.up),
+            ohlc_down_vertex_buffer: create_vertex_buffer(device, &OHLC_VERTEX.down),
+            ohlc_stay_vertex_buffer: create_vertex_buffer(device, &OHLC_VERTEX.stay),
+            heikin_ashi_body_vertex_buffer: create_vertex_buffer(device, &HEIKIN_ASHI_VERTEX.body),
+            heikin_ashi_outline_vertex_buffer: create_vertex_buffer(
+                device,
+                &HEIKIN_ASHI_VERTEX.outline,
+            ),
+            heikin_ashi_wick_vertex_buffer: create_vertex_buffer(device, &HEIKIN_ASHI_VERTEX.wick),
+        }
+    }
+}
+
+//不按时间画的图，切换到时才创建，复用K线的管线
+pub struct PriceChartPack {
+    style: ChartStyle,
+    chart: &'static PriceChart,
+    body_vertex_buffer: Option<wgpu::Buffer>,
+    outline_vertex_buffer: Option<wgpu::Buffer>,
+    wick_vertex_buffer: Option<wgpu::Buffer>,
+    line_up_vertex_buffer: Option<wgpu::Buffer>,
+    line_down_vertex_buffer: Option<wgpu::Buffer>,
+    line_stay_vertex_buffer: Option<wgpu::Buffer>,
+    trade: TradePack,
+}
+
+impl PriceChartPack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+        style: ChartStyle,
+        chart: &'static PriceChart,
+    ) -> Self {
+        PriceChartPack {
+            style,
+            chart,
+            body_vertex_buffer: create_vertex_buffer(device, &chart.candle_vertex.body),
+            outline_vertex_buffer: create_vertex_buffer(device, &chart.candle_vertex.outline),
+            wick_vertex_buffer: create_vertex_buffer(device, &chart.candle_vertex.wick),
+            line_up_vertex_buffer: create_vertex_buffer(device, &chart.line_vertex.up),
+            line_down_vertex_buffer: create_vertex_buffer(device, &chart.line_vertex.down),
+            line_stay_vertex_buffer: create_vertex_buffer(device, &chart.line_vertex.stay),
+            trade: TradePack::new(
+                device,
+                swap_chain_format,
+                camera_bind_group_layout,
+                &chart.trade_vertex,
+            ),
         }
     }
 }
 
 pub struct VolumePack {
-    up_render_pipeline: wgpu::RenderPipeline,
-    up_vertex_buffer: wgpu::Buffer,
-    down_render_pipeline: wgpu::RenderPipeline,
-    down_vertex_buffer: wgpu::Buffer,
-    stay_render_pipeline: wgpu::RenderPipeline,
-    stay_vertex_buffer: wgpu::Buffer,
+    body_render_pipeline: wgpu::RenderPipeline,
+    body_vertex_buffer: Option<wgpu::Buffer>,
+    outline_render_pipeline: wgpu::RenderPipeline,
+    outline_vertex_buffer: Option<wgpu::Buffer>,
 }
 
 impl VolumePack {
@@ -221,49 +380,25 @@ impl VolumePack {
         swap_chain_format: wgpu::TextureFormat,
         camera_bind_group_layout: &BindGroupLayout,
     ) -> Self {
-        let up_render_pipeline = create_volume_pipeline(
+        let body_render_pipeline = create_bar_pipeline(
             device,
             swap_chain_format,
-            "fs_main_up",
-            wgpu::PrimitiveTopology::LineList,
-            camera_bind_group_layout,
-        );
-        let up_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&VOLUME_VERTEX.up),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let down_render_pipeline = create_volume_pipeline(
-            device,
-            swap_chain_format,
-            "fs_main_down",
+            "vs_main_volume_body",
             wgpu::PrimitiveTopology::TriangleList,
             camera_bind_group_layout,
         );
-        let down_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&VOLUME_VERTEX.down),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let stay_render_pipeline = create_volume_pipeline(
+        let outline_render_pipeline = create_bar_pipeline(
             device,
             swap_chain_format,
-            "fs_main_stay",
+            "vs_main_volume_outline",
             wgpu::PrimitiveTopology::LineList,
             camera_bind_group_layout,
         );
-        let stay_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&VOLUME_VERTEX.stay),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
         VolumePack {
-            up_render_pipeline,
-            up_vertex_buffer,
-            down_render_pipeline,
-            down_vertex_buffer,
-            stay_render_pipeline,
-            stay_vertex_buffer,
+            body_render_pipeline,
+            body_vertex_buffer: create_vertex_buffer(device, &VOLUME_VERTEX.body),
+            outline_render_pipeline,
+            outline_vertex_buffer: create_vertex_buffer(device, &VOLUME_VERTEX.outline),
         }
     }
 }
@@ -281,100 +416,761 @@ pub struct TradePack {
     short_vertex_buffer: wgpu::Buffer,
     cover_render_pipeline: wgpu::RenderPipeline,
     cover_vertex_buffer: wgpu::Buffer,
+    vertex: &'static TradePairVertex,
+}
+impl TradePack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+        vertex: &'static TradePairVertex,
+    ) -> Self {
+        let profit_render_pipeline = create_candle_pipeline(
+            device,
+            swap_chain_format,
+            "fs_main_profit",
+            wgpu::PrimitiveTopology::LineList,
+            camera_bind_group_layout,
+        );
+        let profit_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertex.profit),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let loss_render_pipeline = create_candle_pipeline(
+            device,
+            swap_chain_format,
+            "fs_main_loss",
+            wgpu::PrimitiveTopology::LineList,
+            camera_bind_group_layout,
+        );
+        let loss_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertex.loss),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let buy_render_pipeline = create_triangle_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_buy_cover",
+            "fs_main_buy_sell",
+            camera_bind_group_layout,
+        );
+        let buy_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertex.buy),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let sell_render_pipeline = create_triangle_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_sell_short",
+            "fs_main_buy_sell",
+            camera_bind_group_layout,
+        );
+        let sell_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertex.sell),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let short_render_pipeline = create_triangle_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_sell_short",
+            "fs_main_short_cover",
+            camera_bind_group_layout,
+        );
+        let short_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertex.short),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let cover_render_pipeline = create_triangle_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_buy_cover",
+            "fs_main_short_cover",
+            camera_bind_group_layout,
+        );
+        let cover_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertex.cover),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        TradePack {
+            profit_render_pipeline,
+            profit_vertex_buffer,
+            loss_render_pipeline,
+            loss_vertex_buffer,
+            buy_render_pipeline,
+            buy_vertex_buffer,
+            sell_render_pipeline,
+            sell_vertex_buffer,
+            short_render_pipeline,
+            short_vertex_buffer,
+            cover_render_pipeline,
+            cover_vertex_buffer,
+            vertex,
+        }
+    }
+}
+
+//蜡烛图上的指标线和通道填充
+pub struct OverlayPack {
+    line_render_pipeline: wgpu::RenderPipeline,
+    line_vertex_buffer: Option<wgpu::Buffer>,
+    line_vertex_count: u32,
+    dot_render_pipeline: wgpu::RenderPipeline,
+    dot_vertex_buffer: Option<wgpu::Buffer>,
+    dot_vertex_count: u32,
+    fill_render_pipeline: wgpu::RenderPipeline,
+    fill_vertex_buffer: Option<wgpu::Buffer>,
+    fill_ranges: Vec<Range<u32>>,
+}
+
+impl OverlayPack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+        series: &[Series],
+        fills: &[BandFill],
+    ) -> Self {
+        let line_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::LineList,
+            &[camera_bind_group_layout],
+            None,
+        );
+        let dot_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_dot",
+            wgpu::PrimitiveTopology::TriangleList,
+            &[camera_bind_group_layout],
+            None,
+        );
+        let fill_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::TriangleStrip,
+            &[camera_bind_group_layout],
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+        let mut overlay = OverlayPack {
+            line_render_pipeline,
+            line_vertex_buffer: None,
+            line_vertex_count: 0,
+            dot_render_pipeline,
+            dot_vertex_buffer: None,
+            dot_vertex_count: 0,
+            fill_render_pipeline,
+            fill_vertex_buffer: None,
+            fill_ranges: Vec::new(),
+        };
+        overlay.update(device, series, fills);
+        overlay
+    }
+
+    //指标变化后重建顶点
+    pub fn update(&mut self, device: &wgpu::Device, series: &[Series], fills: &[BandFill]) {
+        //每条通道单独画一个TriangleStrip，避免不同通道连在一起
+        let mut vertex = Vec::new();
+        self.fill_ranges.clear();
+        for fill in fills {
+            let start = vertex.len() as u32;
+            vertex.extend(make_band_vertex(
+                &series[fill.upper].values,
+                &series[fill.lower].values,
+                fill.color,
+            ));
+            self.fill_ranges.push(start..vertex.len() as u32);
+        }
+        self.fill_vertex_buffer = create_vertex_buffer(device, &vertex);
+
+        let mut vertex = Vec::new();
+        for line in series {
+            vertex.extend(make_series_vertex(line));
+        }
+        self.line_vertex_count = vertex.len() as u32;
+        self.line_vertex_buffer = create_vertex_buffer(device, &vertex);
+
+        let vertex = make_series_dot_vertex(series);
+        self.dot_vertex_count = vertex.len() as u32;
+        self.dot_vertex_buffer = create_vertex_buffer(device, &vertex);
+    }
+}
+
+//副图，每个副图的纵轴范围不同，所以各有一个相机缓冲和绑定组
+pub struct SubPanePack {
+    frame: RectangleFramePack,
+    camera_buffer: Buffer,
+    camera_bind_group: BindGroup,
+    line_render_pipeline: wgpu::RenderPipeline,
+    line_vertex_buffer: Option<wgpu::Buffer>,
+    line_vertex_count: u32,
+    dot_render_pipeline: wgpu::RenderPipeline,
+    dot_vertex_buffer: Option<wgpu::Buffer>,
+    dot_vertex_count: u32,
+}
+
+impl SubPanePack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+        sub_bind_group_layout: &BindGroupLayout,
+        pane: &SubPane,
+    ) -> Self {
+        let frame = RectangleFramePack::new(device, swap_chain_format, "fs_main_gray2", 40);
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: sub_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+            label: Some("sub_bind_group"),
+        });
+        let line_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_sub_color",
+            wgpu::PrimitiveTopology::LineList,
+            &[camera_bind_group_layout, sub_bind_group_layout],
+            None,
+        );
+        let dot_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_sub_dot",
+            wgpu::PrimitiveTopology::TriangleList,
+            &[camera_bind_group_layout, sub_bind_group_layout],
+            None,
+        );
+        let mut sub_pane = SubPanePack {
+            frame,
+            camera_buffer,
+            camera_bind_group,
+            line_render_pipeline,
+            line_vertex_buffer: None,
+            line_vertex_count: 0,
+            dot_render_pipeline,
+            dot_vertex_buffer: None,
+            dot_vertex_count: 0,
+        };
+        sub_pane.update(device, pane);
+        sub_pane
+    }
+
+    //指标变化后重建顶点，参考线先画，在指标线下面
+    pub fn update(&mut self, device: &wgpu::Device, pane: &SubPane) {
+        let last_ix = HISTORY.datetime.len().saturating_sub(1) as f32;
+        let mut vertex = Vec::new();
+        for value in pane.reference_lines.iter() {
+            vertex.extend(&[
+                ColorVertex {
+                    position: [0.0, *value as f32],
+                    color: REFERENCE_LINE_COLOR,
+                },
+                ColorVertex {
+                    position: [last_ix, *value as f32],
+                    color: REFERENCE_LINE_COLOR,
+                },
+            ]);
+        }
+        for series in pane.series.iter() {
+            vertex.extend(make_series_vertex(series));
+        }
+        self.line_vertex_count = vertex.len() as u32;
+        self.line_vertex_buffer = create_vertex_buffer(device, &vertex);
+
+        let vertex = make_series_dot_vertex(&pane.series);
+        self.dot_vertex_count = vertex.len() as u32;
+        self.dot_vertex_buffer = create_vertex_buffer(device, &vertex);
+    }
+}
+
+//委托线，按撤销、成交、活动的顺序画，活动委托在最上面
+fn order_vertex_lists() -> [(&'static str, &'static Vec<Vertex>); 6] {
+    [
+        ("fs_main_order_cancelled", &ORDERS_VERTEX.cancelled),
+        (
+            "fs_main_order_cancelled_stop",
+            &ORDERS_VERTEX.stop_cancelled,
+        ),
+        ("fs_main_order_filled", &ORDERS_VERTEX.filled),
+        ("fs_main_order_filled_stop", &ORDERS_VERTEX.stop_filled),
+        ("fs_main_order_working", &ORDERS_VERTEX.working),
+        ("fs_main_order_working_stop", &ORDERS_VERTEX.stop_working),
+    ]
+}
+
+pub struct OrderPack {
+    render_pipelines: Vec<wgpu::RenderPipeline>,
+    vertex_buffers: Vec<wgpu::Buffer>,
+}
+
+impl OrderPack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let mut render_pipelines = Vec::new();
+        let mut vertex_buffers = Vec::new();
+        for (fs_main, vertex) in order_vertex_lists() {
+            render_pipelines.push(create_candle_pipeline(
+                device,
+                swap_chain_format,
+                fs_main,
+                wgpu::PrimitiveTopology::LineList,
+                camera_bind_group_layout,
+            ));
+            vertex_buffers.push(device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(vertex),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            }));
+        }
+        OrderPack {
+            render_pipelines,
+            vertex_buffers,
+        }
+    }
+}
+
+//交易的ATR止损线
+pub struct AtrStopPack {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: Option<wgpu::Buffer>,
+}
+
+impl AtrStopPack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let render_pipeline = create_candle_pipeline(
+            device,
+            swap_chain_format,
+            "fs_main_atr_stop",
+            wgpu::PrimitiveTopology::LineList,
+            camera_bind_group_layout,
+        );
+        AtrStopPack {
+            render_pipeline,
+            vertex_buffer: create_vertex_buffer(device, &ATR_STOP_VERTEX),
+        }
+    }
+}
+
+//成交量分布，画在K线图右侧，可见范围或横轴变化时重建顶点
+pub struct VolumeProfilePack {
+    bar_render_pipeline: wgpu::RenderPipeline,
+    bar_vertex_buffer: Option<wgpu::Buffer>,
+    bar_vertex_count: u32,
+    line_render_pipeline: wgpu::RenderPipeline,
+    line_vertex_buffer: Option<wgpu::Buffer>,
+    line_vertex_count: u32,
+    key: Option<((i64, i64), (i64, i64))>, //建顶点时的可见范围和横轴范围
+}
+
+impl VolumeProfilePack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let bar_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::TriangleList,
+            &[camera_bind_group_layout],
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+        let line_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::LineList,
+            &[camera_bind_group_layout],
+            None,
+        );
+        VolumeProfilePack {
+            bar_render_pipeline,
+            bar_vertex_buffer: None,
+            bar_vertex_count: 0,
+            line_render_pipeline,
+            line_vertex_buffer: None,
+            line_vertex_count: 0,
+            key: None,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        profile: Option<&Profile>,
+        key: Option<((i64, i64), (i64, i64))>,
+    ) {
+        if self.key == key {
+            return;
+        }
+        self.key = key;
+        let (bar_vertex, line_vertex) = match (profile, key) {
+            (Some(profile), Some((_, x_range))) => make_volume_profile_vertex(profile, x_range),
+            _ => (Vec::new(), Vec::new()),
+        };
+        self.bar_vertex_count = bar_vertex.len() as u32;
+        self.bar_vertex_buffer = create_vertex_buffer(device, &bar_vertex);
+        self.line_vertex_count = line_vertex.len() as u32;
+        self.line_vertex_buffer = create_vertex_buffer(device, &line_vertex);
+    }
+}
+
+//对比品种的收盘价折线，只生成可见范围的顶点，可见范围或纵轴变化时重建
+pub struct ComparePack {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: Option<wgpu::Buffer>,
+    vertex_count: u32,
+    key: Option<CompareKey>,
+}
+
+//可见范围、对比品种的对齐方式、主图纵轴范围和纵轴类型
+type CompareKey = ((i64, i64), CompareView, (f64, f64), PriceScale);
+
+impl ComparePack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::LineList,
+            &[camera_bind_group_layout],
+            None,
+        );
+        ComparePack {
+            render_pipeline,
+            vertex_buffer: None,
+            vertex_count: 0,
+            key: None,
+        }
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, manager: &Manager) {
+        let key = manager.compare_view.map(|view| {
+            (
+                (manager.left_ix, manager.right_ix),
+                view,
+                (manager.min_price_view, manager.max_price_view),
+                manager.price_scale,
+            )
+        });
+        if self.key == key {
+            return;
+        }
+        self.key = key;
+        let mut vertex = Vec::new();
+        if let (Some(compare), Some(_)) = (COMPARE.as_ref(), key) {
+            let (left, right) = (manager.left_ix as usize, manager.right_ix as usize);
+            let values: Vec<f64> = compare.close_price[left..=right]
+                .iter()
+                .map(|price| manager.compare_y(*price).unwrap_or(f64::NAN))
+                .collect();
+            vertex = make_line_vertex(&values, compare.color);
+            for point in vertex.iter_mut() {
+                point.position[0] += left as f32;
+            }
+        }
+        self.vertex_count = vertex.len() as u32;
+        self.vertex_buffer = create_vertex_buffer(device, &vertex);
+    }
+}
+
+//市场轮廓的方块和线，按时间画，创建时生成全部交易日的顶点
+pub struct TpoPack {
+    block_render_pipeline: wgpu::RenderPipeline,
+    block_vertex_buffer: Option<wgpu::Buffer>,
+    line_render_pipeline: wgpu::RenderPipeline,
+    line_vertex_buffer: Option<wgpu::Buffer>,
+}
+
+impl TpoPack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let block_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::TriangleList,
+            &[camera_bind_group_layout],
+            None,
+        );
+        let line_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::LineList,
+            &[camera_bind_group_layout],
+            None,
+        );
+        TpoPack {
+            block_render_pipeline,
+            block_vertex_buffer: create_vertex_buffer(device, &TPO_VERTEX.blocks),
+            line_render_pipeline,
+            line_vertex_buffer: create_vertex_buffer(device, &TPO_VERTEX.lines),
+        }
+    }
+}
+
+//足迹图的格子和细K线，有逐笔成交时创建，生成全部K线的顶点
+pub struct FootprintPack {
+    cell_render_pipeline: wgpu::RenderPipeline,
+    cell_vertex_buffer: Option<wgpu::Buffer>,
+    line_render_pipeline: wgpu::RenderPipeline,
+    line_vertex_buffer: Option<wgpu::Buffer>,
+}
+
+impl FootprintPack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let cell_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::TriangleList,
+            &[camera_bind_group_layout],
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+        let line_render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::LineList,
+            &[camera_bind_group_layout],
+            None,
+        );
+        FootprintPack {
+            cell_render_pipeline,
+            cell_vertex_buffer: create_vertex_buffer(device, &FOOTPRINT_VERTEX.cells),
+            line_render_pipeline,
+            line_vertex_buffer: create_vertex_buffer(device, &FOOTPRINT_VERTEX.lines),
+        }
+    }
+}
+
+//盘口热力图，有盘口快照时创建，生成全部K线的顶点
+pub struct HeatmapPack {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: Option<wgpu::Buffer>,
+}
+
+impl HeatmapPack {
+    pub fn new(
+        device: &wgpu::Device,
+        swap_chain_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let render_pipeline = create_color_pipeline(
+            device,
+            swap_chain_format,
+            "vs_main_candle_color",
+            wgpu::PrimitiveTopology::TriangleList,
+            &[camera_bind_group_layout],
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+        HeatmapPack {
+            render_pipeline,
+            vertex_buffer: create_vertex_buffer(device, &HEATMAP_VERTEX),
+        }
+    }
+}
+
+//选中交易对的高亮连线和三角
+pub struct TradeHighlightPack {
+    line_render_pipeline: wgpu::RenderPipeline,
+    line_vertex_buffer: wgpu::Buffer,
+    up_render_pipeline: wgpu::RenderPipeline,
+    up_vertex_buffer: wgpu::Buffer,
+    down_render_pipeline: wgpu::RenderPipeline,
+    down_vertex_buffer: wgpu::Buffer,
 }
-impl TradePack {
+
+impl TradeHighlightPack {
     pub fn new(
         device: &wgpu::Device,
         swap_chain_format: wgpu::TextureFormat,
         camera_bind_group_layout: &BindGroupLayout,
     ) -> Self {
-        let profit_render_pipeline = create_candle_pipeline(
-            device,
-            swap_chain_format,
-            "fs_main_profit",
-            wgpu::PrimitiveTopology::LineList,
-            camera_bind_group_layout,
-        );
-        let profit_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&TRADE_PAIRS_VERTEX.profit),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let loss_render_pipeline = create_candle_pipeline(
+        let line_render_pipeline = create_candle_pipeline(
             device,
             swap_chain_format,
-            "fs_main_loss",
+            "fs_main_highlight",
             wgpu::PrimitiveTopology::LineList,
             camera_bind_group_layout,
         );
-        let loss_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        let line_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&TRADE_PAIRS_VERTEX.loss),
+            contents: &[0; 16],
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
-        let buy_render_pipeline = create_triangle_pipeline(
+        let up_render_pipeline = create_triangle_pipeline(
             device,
             swap_chain_format,
             "vs_main_buy_cover",
-            "fs_main_buy_sell",
+            "fs_main_highlight",
             camera_bind_group_layout,
         );
-        let buy_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        let up_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&TRADE_PAIRS_VERTEX.buy),
+            contents: &[0; 24],
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
-        let sell_render_pipeline = create_triangle_pipeline(
+        let down_render_pipeline = create_triangle_pipeline(
             device,
             swap_chain_format,
             "vs_main_sell_short",
-            "fs_main_buy_sell",
+            "fs_main_highlight",
             camera_bind_group_layout,
         );
-        let sell_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        let down_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&TRADE_PAIRS_VERTEX.sell),
+            contents: &[0; 24],
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
-        let short_render_pipeline = create_triangle_pipeline(
-            device,
-            swap_chain_format,
-            "vs_main_sell_short",
-            "fs_main_short_cover",
-            camera_bind_group_layout,
+        TradeHighlightPack {
+            line_render_pipeline,
+            line_vertex_buffer,
+            up_render_pipeline,
+            up_vertex_buffer,
+            down_render_pipeline,
+            down_vertex_buffer,
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, pair_ix: usize) {
+        let d = &TRADE_PAIRS[pair_ix];
+        let line = [
+            Vertex {
+                position: [d.open_ix as f32, d.open_price as f32],
+            },
+            Vertex {
+                position: [d.close_ix as f32, d.close_price as f32],
+            },
+        ];
+        queue.write_buffer(&self.line_vertex_buffer, 0, bytemuck::cast_slice(&line));
+        //多头开仓三角在最低价下方，平仓三角在最高价上方，空头相反
+        let (up, down) = if d.is_long() {
+            (
+                [
+                    d.open_ix as f32,
+                    HISTORY.low_price[d.open_ix as usize] as f32,
+                ],
+                [
+                    d.close_ix as f32,
+                    HISTORY.high_price[d.close_ix as usize] as f32,
+                ],
+            )
+        } else {
+            (
+                [
+                    d.close_ix as f32,
+                    HISTORY.low_price[d.close_ix as usize] as f32,
+                ],
+                [
+                    d.open_ix as f32,
+                    HISTORY.high_price[d.open_ix as usize] as f32,
+                ],
+            )
+        };
+        queue.write_buffer(
+            &self.up_vertex_buffer,
+            0,
+            bytemuck::cast_slice(&[Vertex { position: up }].repeat(3)),
         );
-        let short_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&TRADE_PAIRS_VERTEX.short),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let cover_render_pipeline = create_triangle_pipeline(
-            device,
-            swap_chain_format,
-            "vs_main_buy_cover",
-            "fs_main_short_cover",
-            camera_bind_group_layout,
+        queue.write_buffer(
+            &self.down_vertex_buffer,
+            0,
+            bytemuck::cast_slice(&[Vertex { position: down }].repeat(3)),
         );
-        let cover_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&TRADE_PAIRS_VERTEX.cover),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        TradePack {
-            profit_render_pipeline,
-            profit_vertex_buffer,
-            loss_render_pipeline,
-            loss_vertex_buffer,
-            buy_render_pipeline,
-            buy_vertex_buffer,
-            sell_render_pipeline,
-            sell_vertex_buffer,
-            short_render_pipeline,
-            short_vertex_buffer,
-            cover_render_pipeline,
-            cover_vertex_buffer,
+    }
+}
+
+//按键提示的文字和颜色，配置了对比品种、有盘口快照才提示对应的键
+fn key_hints() -> Vec<(&'static str, [f32; 4])> {
+    let mut hints = vec![
+        ("T：交易列表", [0.8, 0.8, 0.8, 1.0]),
+        ("O：委托（虚线为停止单）", [0.8, 0.8, 0.8, 1.0]),
+        ("E：导出交易对", [0.8, 0.8, 0.8, 1.0]),
+        ("1-9：显示/隐藏副图", [0.8, 0.8, 0.8, 1.0]),
+        ("A：ATR止损线", [1.0, 0.4, 0.4, 1.0]),
+        ("F：输入公式", [0.8, 0.8, 0.8, 1.0]),
+        ("R：重新加载脚本", [0.8, 0.8, 0.8, 1.0]),
+        ("M：切换主图画法", [0.8, 0.8, 0.8, 1.0]),
+        ("L：线性/对数/涨跌幅纵轴", [0.8, 0.8, 0.8, 1.0]),
+        ("P：成交量分布", VOLUME_PROFILE_POC_COLOR),
+    ];
+    if let Some(compare) = COMPARE.as_ref() {
+        hints.push(("C：对比品种涨跌幅/副坐标轴", compare.color));
+    }
+    if DEPTH_HEATMAP.is_some() {
+        hints.push(("H：盘口热力图", [0.8, 0.8, 0.8, 1.0]));
+    }
+    hints
+}
+
+//提示文字的宽度，按默认16像素的字号估算，ASCII字符算半个字
+fn hint_text_width(text: &str) -> f32 {
+    text.chars()
+        .map(|c| if c.is_ascii() { 8.0 } else { 16.0 })
+        .sum()
+}
+
+//按键提示按列排，每列KEY_HINT_ROWS行，窗口宽度放不下时换到下面一排，从左边开始
+//返回每个按键提示的(x, 行)和提示区的总行数，第0行留给状态提示，第一排左边是交易图例
+fn key_hint_layout(width: f32) -> (Vec<(f32, usize)>, usize) {
+    let mut positions = Vec::new();
+    let (mut x, mut band) = (KEY_HINT_X, 0);
+    for column in key_hints().chunks(KEY_HINT_ROWS) {
+        let column_width = column
+            .iter()
+            .map(|(text, _)| hint_text_width(text))
+            .fold(0.0, f32::max);
+        if x > 10.0 && x + column_width > width - MARGIN {
+            band += 1;
+            x = 10.0;
         }
+        for i in 0..column.len() {
+            positions.push((x, 1 + band * KEY_HINT_ROWS + i));
+        }
+        x += column_width + 20.0;
     }
+    (positions, 1 + (band + 1) * KEY_HINT_ROWS)
+}
+
+//提示区的高度，上下各留10像素
+fn hint_height(rows: usize) -> f32 {
+    HINT_ROW_HEIGHT * rows as f32 + 20.0
 }
 
 pub struct WgpuCtx<'window, 'font> {
@@ -393,18 +1189,34 @@ pub struct WgpuCtx<'window, 'font> {
     screen_uniform: ScreenUniform,
     screen_buffer: Buffer,
     camera_bind_group: BindGroup,
+    camera_bind_group_layout: BindGroupLayout,
+    sub_bind_group_layout: BindGroupLayout,
 
     chart_frame: RectangleFramePack,
     chart_k: RectangleFramePack,
     chart_volume: RectangleFramePack,
     candle_bar: CandlePack,
+    price_chart: Option<PriceChartPack>,
     trade: TradePack,
+    order: OrderPack,
+    atr_stop: AtrStopPack,
+    volume_profile: VolumeProfilePack,
+    tpo: Option<TpoPack>,
+    footprint: Option<FootprintPack>,
+    heatmap: Option<HeatmapPack>,
+    compare: ComparePack,
+    overlay: OverlayPack,
     volume_bar: VolumePack,
+    sub_pane: Vec<SubPanePack>,
     cursor_horizontal: LinePack,
     cursor_horizontal_label: RectangleFilledPack,
     cursor_vertical: LinePack,
     cursor_vertical_label: RectangleFilledPack,
     info_box: RectangleFilledPack,
+    trade_list_frame: RectangleFramePack,
+    trade_list_selected: RectangleFilledPack,
+    trade_highlight: TradeHighlightPack,
+    tooltip: RectangleFilledPack,
 
     cursor_show: bool,
     cursor_dock_left: bool,
@@ -487,6 +1299,11 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             contents: bytemuck::cast_slice(&[screen_uniform]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
+        let candle_style_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Candle Style Buffer"),
+            contents: bytemuck::cast_slice(&[CandleStyleUniform::from_config()]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
 
         let camera_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -521,6 +1338,16 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                         },
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
                 label: Some("camera_bind_group_layout"),
             });
@@ -540,6 +1367,10 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                     binding: 2,
                     resource: screen_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: candle_style_buffer.as_entire_binding(),
+                },
             ],
             label: Some("camera_bind_group"),
         });
@@ -553,9 +1384,73 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             RectangleFramePack::new(&device, surface_config.format, "fs_main_gray2", 40);
 
         let candle_bar = CandlePack::new(&device, surface_config.format, &camera_bind_group_layout);
-        let trade = TradePack::new(&device, surface_config.format, &camera_bind_group_layout);
+        let manager = Manager::new();
+        let price_chart = manager.chart_style.price_chart().map(|chart| {
+            PriceChartPack::new(
+                &device,
+                surface_config.format,
+                &camera_bind_group_layout,
+                manager.chart_style,
+                chart,
+            )
+        });
+        let trade = TradePack::new(
+            &device,
+            surface_config.format,
+            &camera_bind_group_layout,
+            &TRADE_PAIRS_VERTEX,
+        );
+        let overlay = OverlayPack::new(
+            &device,
+            surface_config.format,
+            &camera_bind_group_layout,
+            &manager.overlays,
+            &manager.band_fills,
+        );
+        let order = OrderPack::new(&device, surface_config.format, &camera_bind_group_layout);
+        let atr_stop = AtrStopPack::new(&device, surface_config.format, &camera_bind_group_layout);
+        let volume_profile =
+            VolumeProfilePack::new(&device, surface_config.format, &camera_bind_group_layout);
+        let compare = ComparePack::new(&device, surface_config.format, &camera_bind_group_layout);
+        //切换到市场轮廓时才创建
+        let tpo = (manager.chart_style == ChartStyle::Tpo)
+            .then(|| TpoPack::new(&device, surface_config.format, &camera_bind_group_layout));
+        let footprint = FOOTPRINT
+            .is_some()
+            .then(|| FootprintPack::new(&device, surface_config.format, &camera_bind_group_layout));
+        let heatmap = DEPTH_HEATMAP
+            .is_some()
+            .then(|| HeatmapPack::new(&device, surface_config.format, &camera_bind_group_layout));
         let volume_bar = VolumePack::new(&device, surface_config.format, &camera_bind_group_layout);
 
+        let sub_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("sub_bind_group_layout"),
+            });
+        let sub_pane = manager
+            .sub_panes
+            .iter()
+            .map(|pane| {
+                SubPanePack::new(
+                    &device,
+                    surface_config.format,
+                    &camera_bind_group_layout,
+                    &sub_bind_group_layout,
+                    pane,
+                )
+            })
+            .collect();
+
         let cursor_horizontal = LinePack::new(&device, surface_config.format, "fs_main_gray2", 16);
         let cursor_horizontal_label =
             RectangleFilledPack::new(&device, surface_config.format, "fs_main_label_bg", 32);
@@ -566,6 +1461,15 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
         let info_box =
             RectangleFilledPack::new(&device, surface_config.format, "fs_main_label_bg", 32);
 
+        let trade_list_frame =
+            RectangleFramePack::new(&device, surface_config.format, "fs_main_gray1", 40);
+        let trade_list_selected =
+            RectangleFilledPack::new(&device, surface_config.format, "fs_main_selected", 32);
+        let trade_highlight =
+            TradeHighlightPack::new(&device, surface_config.format, &camera_bind_group_layout);
+        let tooltip =
+            RectangleFilledPack::new(&device, surface_config.format, "fs_main_label_bg", 32);
+
         WgpuCtx {
             surface,
             surface_config,
@@ -581,20 +1485,36 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             screen_uniform,
             screen_buffer,
             camera_bind_group,
+            camera_bind_group_layout,
+            sub_bind_group_layout,
             chart_frame,
             chart_k,
             chart_volume,
             candle_bar,
+            price_chart,
             trade,
+            order,
+            atr_stop,
+            volume_profile,
+            tpo,
+            footprint,
+            heatmap,
+            compare,
+            overlay,
             volume_bar,
+            sub_pane,
             cursor_horizontal,
             cursor_horizontal_label,
             cursor_vertical,
             cursor_vertical_label,
             info_box,
+            trade_list_frame,
+            trade_list_selected,
+            trade_highlight,
+            tooltip,
             cursor_show: false,
             cursor_dock_left: true,
-            manager: Manager::new(),
+            manager,
         }
     }
 
@@ -602,6 +1522,38 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
         pollster::block_on(WgpuCtx::new_async(window))
     }
 
+    //切换画法后，不按时间画的图换成当前的，其他画法时释放
+    //切换到市场轮廓时创建它的顶点缓冲，切走后保留
+    fn update_tpo(&mut self) {
+        if self.manager.chart_style == ChartStyle::Tpo && self.tpo.is_none() {
+            self.tpo = Some(TpoPack::new(
+                &self.device,
+                self.surface_config.format,
+                &self.camera_bind_group_layout,
+            ));
+        }
+    }
+
+    fn update_price_chart(&mut self) {
+        let style = self.manager.chart_style;
+        if self
+            .price_chart
+            .as_ref()
+            .is_some_and(|pack| pack.style == style)
+        {
+            return;
+        }
+        self.price_chart = style.price_chart().map(|chart| {
+            PriceChartPack::new(
+                &self.device,
+                self.surface_config.format,
+                &self.camera_bind_group_layout,
+                style,
+                chart,
+            )
+        });
+    }
+
     pub fn resize(&mut self, new_size: (u32, u32)) {
         let (s_width, s_height) = new_size;
         let s_width = s_width.max(1);
@@ -621,11 +1573,16 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             .resize_view(s_width as f32, s_height as f32, &self.queue);
 
         //更新边框
+        let trade_list_width = if self.manager.show_trade_list {
+            TRADE_LIST_WIDTH + MARGIN
+        } else {
+            0.0
+        };
         self.chart_frame.shape = RectangleFrame {
             x: MARGIN,
             y: MARGIN,
-            width: s_width as f32 - MARGIN * 2.0,
-            height: s_height as f32 - MARGIN * 2.0 - HINT_HEIGHT,
+            width: s_width as f32 - MARGIN * 2.0 - trade_list_width,
+            height: s_height as f32 - MARGIN * 2.0 - hint_height(key_hint_layout(s_width as f32).1),
             vertex: None,
         };
         self.chart_frame.shape.make_vertex(s_width, s_height);
@@ -635,11 +1592,22 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             bytemuck::cast_slice(&self.chart_frame.shape.vertex.unwrap()),
         );
 
+        //K线图、成交量和显示的副图按比例分配高度，隐藏的副图高度为0
+        let visible_sub_pane_count = self
+            .manager
+            .sub_panes
+            .iter()
+            .filter(|pane| pane.visible)
+            .count();
+        let unit_height = (self.chart_frame.shape.height - AXIS_X_HEIGHT)
+            / (CANDLE_PANE_WEIGHT
+                + VOLUME_PANE_WEIGHT
+                + SUB_PANE_WEIGHT * visible_sub_pane_count as f32);
         self.chart_k.shape = RectangleFrame {
             x: MARGIN,
             y: MARGIN,
-            width: s_width as f32 - MARGIN * 2.0 - AXIS_Y_WIDTH,
-            height: (self.chart_frame.shape.height - AXIS_X_HEIGHT) * 0.7,
+            width: self.chart_frame.shape.width - AXIS_Y_WIDTH,
+            height: unit_height * CANDLE_PANE_WEIGHT,
             vertex: None,
         };
         self.chart_k.shape.make_vertex(s_width, s_height);
@@ -657,8 +1625,8 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
         self.chart_volume.shape = RectangleFrame {
             x: MARGIN,
             y: MARGIN + self.chart_k.shape.height,
-            width: s_width as f32 - MARGIN * 2.0 - AXIS_Y_WIDTH,
-            height: (self.chart_frame.shape.height - AXIS_X_HEIGHT) * 0.3,
+            width: self.chart_frame.shape.width - AXIS_Y_WIDTH,
+            height: unit_height * VOLUME_PANE_WEIGHT,
             vertex: None,
         };
         self.chart_volume.shape.make_vertex(s_width, s_height);
@@ -667,6 +1635,43 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             0,
             bytemuck::cast_slice(&self.chart_volume.shape.vertex.unwrap()),
         );
+
+        let mut sub_pane_y = self.chart_volume.shape.y + self.chart_volume.shape.height;
+        for (pack, pane) in self.sub_pane.iter_mut().zip(&self.manager.sub_panes) {
+            pack.frame.shape = RectangleFrame {
+                x: MARGIN,
+                y: sub_pane_y,
+                width: self.chart_frame.shape.width - AXIS_Y_WIDTH,
+                height: if pane.visible {
+                    unit_height * SUB_PANE_WEIGHT
+                } else {
+                    0.0
+                },
+                vertex: None,
+            };
+            pack.frame.shape.make_vertex(s_width, s_height);
+            self.queue.write_buffer(
+                &pack.frame.vertex_buffer,
+                0,
+                bytemuck::cast_slice(&pack.frame.shape.vertex.unwrap()),
+            );
+            sub_pane_y += pack.frame.shape.height;
+        }
+
+        //交易列表
+        self.trade_list_frame.shape = RectangleFrame {
+            x: s_width as f32 - MARGIN - TRADE_LIST_WIDTH,
+            y: MARGIN,
+            width: TRADE_LIST_WIDTH,
+            height: self.chart_frame.shape.height,
+            vertex: None,
+        };
+        self.trade_list_frame.shape.make_vertex(s_width, s_height);
+        self.queue.write_buffer(
+            &self.trade_list_frame.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&self.trade_list_frame.shape.vertex.unwrap()),
+        );
         //更新屏幕uniform
         self.screen_uniform = ScreenUniform {
             width: s_width as f32,
@@ -679,6 +1684,15 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
         );
     }
 
+    //信息栏的列数和每列的项数，项多时分成几列，不超出K线图
+    fn info_layout(&self) -> (usize, usize) {
+        let item_count = INFO_BASE_ITEM_COUNT + self.manager.info_item_count();
+        let rows = ((self.chart_k.shape.height - INFO_SIZE.1) / INFO_ITEM_HEIGHT).floor() as i64
+            + INFO_BASE_ITEM_COUNT as i64;
+        let rows = (rows.max(1) as usize).min(item_count);
+        (item_count.div_ceil(rows), rows)
+    }
+
     pub fn cursor_moved(&mut self, position: PhysicalPosition<f64>) {
         self.manager.current_cursor_position = (position.x, position.y);
 
@@ -703,28 +1717,25 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
         }
 
         //光标与信息栏
+        let panes_bottom = self.panes_bottom();
         if position.x as f32 >= self.chart_k.shape.x
             && position.x as f32 <= self.chart_k.shape.x + self.chart_k.shape.width
             && position.y as f32 >= self.chart_k.shape.y
-            && position.y as f32 <= self.chart_volume.shape.y + self.chart_volume.shape.height
+            && position.y as f32 <= panes_bottom
         {
             self.cursor_show = true;
 
             //垂直光标
-            let bar_width = self.chart_k.shape.width
-                / (self.manager.right_ix - self.manager.left_ix + 1) as f32;
-            self.manager.cursor_ix = ((position.x as f32 - self.chart_k.shape.x) / bar_width)
-                as i64
-                + self.manager.left_ix;
-            self.manager.cursor_ix = self
-                .manager
-                .cursor_ix
-                .min(HISTORY.datetime.len() as i64 - 1);
+            let (left_x, right_x) = self.manager.chart_x_range();
+            let bar_width = self.chart_k.shape.width / (right_x - left_x + 1) as f32;
+            self.manager.set_cursor_x(
+                ((position.x as f32 - self.chart_k.shape.x) / bar_width) as i64 + left_x,
+            );
             self.cursor_vertical.shape = Line {
                 x1: position.x as f32,
                 y1: self.chart_k.shape.y,
                 x2: position.x as f32,
-                y2: self.chart_volume.shape.y + self.chart_volume.shape.height,
+                y2: panes_bottom,
                 vertex: None,
             };
             self.cursor_vertical
@@ -738,7 +1749,7 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
 
             self.cursor_vertical_label.shape = RectangleFilled {
                 x: position.x as f32,
-                y: self.chart_volume.shape.y + self.chart_volume.shape.height,
+                y: panes_bottom,
                 width: 80.0,
                 height: 32.0,
                 vertex: None,
@@ -753,53 +1764,28 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             );
 
             //水平光标
+            self.manager.cursor_price = None;
+            self.manager.cursor_volume = None;
+            self.manager.cursor_sub = None;
+            let y = position.y as f32;
+            let sub_pane_view = self
+                .sub_pane
+                .iter()
+                .position(|pack| {
+                    y > pack.frame.shape.y && y <= pack.frame.shape.y + pack.frame.shape.height
+                })
+                .and_then(|i| Some((i, self.manager.sub_views.get(i).copied()??)));
             //价格框内
-            if position.y as f32 >= self.chart_k.shape.y
-                && (position.y as f32) < self.chart_k.shape.y + self.chart_k.shape.height
-            {
-                self.manager.cursor_price = Some(
+            if y >= self.chart_k.shape.y && y < self.chart_k.shape.y + self.chart_k.shape.height {
+                self.manager.cursor_price = Some(self.manager.price_at(
                     (self.chart_k.shape.y as f64 + self.chart_k.shape.height as f64 - position.y)
-                        / self.chart_k.shape.height as f64
-                        * (self.manager.max_price_view - self.manager.min_price_view)
-                        + self.manager.min_price_view,
-                );
-                self.cursor_horizontal.shape = Line {
-                    x1: self.chart_k.shape.x,
-                    y1: position.y as f32,
-                    x2: self.chart_k.shape.x + self.chart_k.shape.width,
-                    y2: position.y as f32,
-                    vertex: None,
-                };
-                self.cursor_horizontal
-                    .shape
-                    .make_vertex(self.surface_config.width, self.surface_config.height);
-                self.queue.write_buffer(
-                    &self.cursor_horizontal.vertex_buffer,
-                    0,
-                    bytemuck::cast_slice(&self.cursor_horizontal.shape.vertex.unwrap()),
-                );
-
-                self.cursor_horizontal_label.shape = RectangleFilled {
-                    x: self.chart_k.shape.x + self.chart_k.shape.width,
-                    y: position.y as f32 - AXIS_Y_LABEL_BIAS,
-                    width: AXIS_Y_WIDTH,
-                    height: 16.0,
-                    vertex: None,
-                };
-                self.cursor_horizontal_label
-                    .shape
-                    .make_vertex(self.surface_config.width, self.surface_config.height);
-                self.queue.write_buffer(
-                    &self.cursor_horizontal_label.vertex_buffer,
-                    0,
-                    bytemuck::cast_slice(&self.cursor_horizontal_label.shape.vertex.unwrap()),
-                );
-            } else {
-                self.manager.cursor_price = None;
+                        / self.chart_k.shape.height as f64,
+                ));
+                self.update_cursor_horizontal(self.chart_k.shape.x, self.chart_k.shape.width, y);
             }
             //成交量框内
-            if position.y as f32 > self.chart_volume.shape.y
-                && (position.y as f32) <= self.chart_volume.shape.y + self.chart_volume.shape.height
+            else if y > self.chart_volume.shape.y
+                && y <= self.chart_volume.shape.y + self.chart_volume.shape.height
             {
                 self.manager.cursor_volume = Some(
                     (self.chart_volume.shape.y as f64 + self.chart_volume.shape.height as f64
@@ -807,43 +1793,27 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                         / self.chart_volume.shape.height as f64
                         * self.manager.max_volume_view,
                 );
-                self.cursor_horizontal.shape = Line {
-                    x1: self.chart_volume.shape.x,
-                    y1: position.y as f32,
-                    x2: self.chart_volume.shape.x + self.chart_volume.shape.width,
-                    y2: position.y as f32,
-                    vertex: None,
-                };
-                self.cursor_horizontal
-                    .shape
-                    .make_vertex(self.surface_config.width, self.surface_config.height);
-                self.queue.write_buffer(
-                    &self.cursor_horizontal.vertex_buffer,
-                    0,
-                    bytemuck::cast_slice(&self.cursor_horizontal.shape.vertex.unwrap()),
-                );
-
-                self.cursor_horizontal_label.shape = RectangleFilled {
-                    x: self.chart_volume.shape.x + self.chart_volume.shape.width,
-                    y: position.y as f32 - AXIS_Y_LABEL_BIAS,
-                    width: AXIS_Y_WIDTH,
-                    height: 16.0,
-                    vertex: None,
-                };
-                self.cursor_horizontal_label
-                    .shape
-                    .make_vertex(self.surface_config.width, self.surface_config.height);
-                self.queue.write_buffer(
-                    &self.cursor_horizontal_label.vertex_buffer,
-                    0,
-                    bytemuck::cast_slice(&self.cursor_horizontal_label.shape.vertex.unwrap()),
+                self.update_cursor_horizontal(
+                    self.chart_volume.shape.x,
+                    self.chart_volume.shape.width,
+                    y,
                 );
-            } else {
-                self.manager.cursor_volume = None;
+            }
+            //副图内
+            else if let Some((i, (min_value, max_value))) = sub_pane_view {
+                let shape = &self.sub_pane[i].frame.shape;
+                let value = (shape.y as f64 + shape.height as f64 - position.y)
+                    / shape.height as f64
+                    * (max_value - min_value)
+                    + min_value;
+                self.manager.cursor_sub = Some((i, value));
+                self.update_cursor_horizontal(shape.x, shape.width, y);
             }
 
             //信息栏停靠在左边还是右边
-            if (position.x as f32) < (self.chart_k.shape.x + INFO_SIZE.0 * 2.0) {
+            let (columns, rows) = self.info_layout();
+            let info_width = INFO_SIZE.0 * columns as f32;
+            if (position.x as f32) < (self.chart_k.shape.x + info_width + INFO_SIZE.0) {
                 self.cursor_dock_left = false;
             } else {
                 self.cursor_dock_left = true;
@@ -853,11 +1823,12 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 x: if self.cursor_dock_left {
                     self.chart_k.shape.x
                 } else {
-                    self.chart_k.shape.x + self.chart_k.shape.width - INFO_SIZE.0
+                    self.chart_k.shape.x + self.chart_k.shape.width - info_width
                 },
                 y: self.chart_k.shape.y,
-                width: INFO_SIZE.0,
-                height: INFO_SIZE.1,
+                width: info_width,
+                height: INFO_SIZE.1
+                    + INFO_ITEM_HEIGHT * (rows as f32 - INFO_BASE_ITEM_COUNT as f32),
                 vertex: None,
             };
             self.info_box
@@ -873,8 +1844,177 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
         }
     }
 
+    //最下面一个图的底边，时间轴和光标垂线画到这里
+    fn panes_bottom(&self) -> f32 {
+        let shape = self
+            .sub_pane
+            .last()
+            .map_or(&self.chart_volume.shape, |pack| &pack.frame.shape);
+        shape.y + shape.height
+    }
+
+    //在横坐标x到x + width之间画水平光标，标签在右边的纵轴上
+    fn update_cursor_horizontal(&mut self, x: f32, width: f32, y: f32) {
+        self.cursor_horizontal.shape = Line {
+            x1: x,
+            y1: y,
+            x2: x + width,
+            y2: y,
+            vertex: None,
+        };
+        self.cursor_horizontal
+            .shape
+            .make_vertex(self.surface_config.width, self.surface_config.height);
+        self.queue.write_buffer(
+            &self.cursor_horizontal.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&self.cursor_horizontal.shape.vertex.unwrap()),
+        );
+
+        self.cursor_horizontal_label.shape = RectangleFilled {
+            x: x + width,
+            y: y - AXIS_Y_LABEL_BIAS,
+            width: AXIS_Y_WIDTH,
+            height: 16.0,
+            vertex: None,
+        };
+        self.cursor_horizontal_label
+            .shape
+            .make_vertex(self.surface_config.width, self.surface_config.height);
+        self.queue.write_buffer(
+            &self.cursor_horizontal_label.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&self.cursor_horizontal_label.shape.vertex.unwrap()),
+        );
+    }
+
+    //蜡烛图坐标转换成屏幕坐标，需在VP_MATRIX更新之后调用
+    fn project_candle(&self, x: f32, y: f32) -> (f32, f32) {
+        let pos = project_candle_position(Vec4::new(x, y, 0.0, 1.0));
+        (
+            self.chart_k.shape.x + (pos.x + 1.0) / 2.0 * self.chart_k.shape.width,
+            self.chart_k.shape.y + (1.0 - pos.y) / 2.0 * self.chart_k.shape.height,
+        )
+    }
+
+    //找出鼠标悬停的交易对，三角优先于连线
+    fn hit_test_trade(&self) -> Option<(usize, TradeHover)> {
+        let (cursor_x, cursor_y) = self.manager.current_cursor_position;
+        let (cursor_x, cursor_y) = (cursor_x as f32, cursor_y as f32);
+        //和着色器里三角的大小保持一致
+        let half_width =
+            (10.0 / self.surface_config.width as f32 * self.chart_k.shape.width / 2.0).max(4.0);
+        let height = 20.0 / self.surface_config.height as f32 * self.chart_k.shape.height / 2.0;
+        let visible = || {
+            TRADE_PAIRS.iter().enumerate().filter(|(_, d)| {
+                d.close_ix >= self.manager.left_ix && d.open_ix <= self.manager.right_ix
+            })
+        };
+        for (i, d) in visible() {
+            let (open_y, close_y) = if d.is_long() {
+                (
+                    HISTORY.low_price[d.open_ix as usize],
+                    HISTORY.high_price[d.close_ix as usize],
+                )
+            } else {
+                (
+                    HISTORY.high_price[d.open_ix as usize],
+                    HISTORY.low_price[d.close_ix as usize],
+                )
+            };
+            //向上的三角在点的下方，向下的三角在点的上方
+            let markers = [
+                (d.open_ix, open_y, d.is_long(), TradeHover::Open),
+                (d.close_ix, close_y, !d.is_long(), TradeHover::Close),
+            ];
+            for (ix, y, arrow_up, hover) in markers {
+                let (x, y) = self.project_candle(ix as f32, y as f32);
+                let (top, bottom) = if arrow_up {
+                    (y, y + height)
+                } else {
+                    (y - height, y)
+                };
+                if (cursor_x - x).abs() <= half_width
+                    && cursor_y >= top - 2.0
+                    && cursor_y <= bottom + 2.0
+                {
+                    return Some((i, hover));
+                }
+            }
+        }
+        let mut nearest = None;
+        let mut nearest_distance = 4.0;
+        for (i, d) in visible() {
+            let (x1, y1) = self.project_candle(d.open_ix as f32, d.open_price as f32);
+            let (x2, y2) = self.project_candle(d.close_ix as f32, d.close_price as f32);
+            let distance = distance_to_segment((cursor_x, cursor_y), (x1, y1), (x2, y2));
+            if distance <= nearest_distance {
+                nearest_distance = distance;
+                nearest = Some((i, TradeHover::Line));
+            }
+        }
+        nearest
+    }
+
+    fn cursor_in_trade_list(&self) -> bool {
+        let (x, y) = self.manager.current_cursor_position;
+        let shape = &self.trade_list_frame.shape;
+        self.manager.show_trade_list
+            && x as f32 >= shape.x
+            && x as f32 <= shape.x + shape.width
+            && y as f32 >= shape.y
+            && y as f32 <= shape.y + shape.height
+    }
+
+    fn trade_list_visible_rows(&self) -> usize {
+        ((self.trade_list_frame.shape.height - TRADE_LIST_ROW_HEIGHT) / TRADE_LIST_ROW_HEIGHT)
+            .max(0.0) as usize
+    }
+
+    //点击表头排序，点击行选中交易对
+    fn trade_list_clicked(&mut self) {
+        let (x, y) = self.manager.current_cursor_position;
+        let row = ((y as f32 - self.trade_list_frame.shape.y) / TRADE_LIST_ROW_HEIGHT) as usize;
+        if row == 0 {
+            let x = x as f32 - self.trade_list_frame.shape.x;
+            let column = TRADE_LIST_COLUMNS
+                .iter()
+                .rposition(|(_, offset)| x >= *offset)
+                .unwrap_or(0);
+            match column {
+                0 | 1 => {
+                    if self.manager.trade_sort == TradeSort::TimeAsc {
+                        self.manager.sort_trade_list(TradeSort::TimeDesc);
+                    } else {
+                        self.manager.sort_trade_list(TradeSort::TimeAsc);
+                    }
+                }
+                6 => {
+                    if self.manager.trade_sort == TradeSort::PnlDesc {
+                        self.manager.sort_trade_list(TradeSort::PnlAsc);
+                    } else {
+                        self.manager.sort_trade_list(TradeSort::PnlDesc);
+                    }
+                }
+                _ => (),
+            }
+            return;
+        }
+        let list_ix = row - 1 + self.manager.trade_list_scroll;
+        if row - 1 < self.trade_list_visible_rows() && list_ix < self.manager.trade_list_order.len()
+        {
+            let pair_ix = self.manager.trade_list_order[list_ix];
+            self.manager.select_trade(pair_ix);
+            self.trade_highlight.update(&self.queue, pair_ix);
+        }
+    }
+
     pub fn mouse_input(&mut self, state: ElementState, button: MouseButton) {
         if button == MouseButton::Left {
+            if state == ElementState::Pressed && self.cursor_in_trade_list() {
+                self.trade_list_clicked();
+                return;
+            }
             match state {
                 ElementState::Pressed => {
                     self.manager.pressed_position = Some(self.manager.current_cursor_position);
@@ -887,12 +2027,32 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 }
             }
         }
+        //在K线图上右键设置锚定VWAP的起点
+        if button == MouseButton::Right
+            && state == ElementState::Pressed
+            && self.cursor_show
+            && self.manager.cursor_price.is_some()
+        {
+            self.manager.set_vwap_anchor(self.manager.cursor_ix);
+            self.overlay.update(
+                &self.device,
+                &self.manager.overlays,
+                &self.manager.band_fills,
+            );
+            //指标数量变了，按当前光标位置重新计算信息栏的高度
+            let (x, y) = self.manager.current_cursor_position;
+            self.cursor_moved(PhysicalPosition::new(x, y));
+        }
     }
 
     pub fn mouse_wheel(&mut self, delta: MouseScrollDelta, _phase: TouchPhase) {
         match delta {
             MouseScrollDelta::LineDelta(_x, y) => {
-                if y > 0.0 {
+                if self.cursor_in_trade_list() {
+                    let visible_rows = self.trade_list_visible_rows();
+                    self.manager
+                        .scroll_trade_list(-(y * 3.0) as i64, visible_rows);
+                } else if y > 0.0 {
                     self.manager.zoom_in_by();
                 } else if y < 0.0 {
                     self.manager.zoom_out_by();
@@ -903,6 +2063,13 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
     }
 
     pub fn keyboard_input(&mut self, key_event: KeyEvent) {
+        //输入公式时按键都当作输入
+        if self.manager.formula_input.is_some() {
+            self.formula_keyboard_input(key_event);
+            return;
+        }
+        //开关类按键只响应按下，不响应松开和长按重复
+        let toggle = key_event.state == ElementState::Pressed && !key_event.repeat;
         match key_event.physical_key {
             PhysicalKey::Code(KeyCode::ArrowUp) => {
                 self.manager.zoom_in();
@@ -910,48 +2077,230 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             PhysicalKey::Code(KeyCode::ArrowDown) => {
                 self.manager.zoom_out();
             }
-            _ => (),
+            PhysicalKey::Code(KeyCode::KeyE) if toggle => {
+                let dir = default_export_dir();
+                self.manager.status_message = Some(match export_trade_pairs(&dir) {
+                    Ok(_) => format!("交易对已导出到{}", dir.display()),
+                    Err(err) => {
+                        log::error!("导出交易对失败：{err}");
+                        format!("导出失败：{err}")
+                    }
+                });
+            }
+            PhysicalKey::Code(KeyCode::KeyO) if toggle => {
+                self.manager.show_orders = !self.manager.show_orders;
+            }
+            PhysicalKey::Code(KeyCode::KeyM) if toggle => {
+                self.manager.chart_style = self.manager.chart_style.next();
+                self.update_price_chart();
+                self.update_tpo();
+                self.manager.status_message =
+                    Some(format!("主图：{}", self.manager.chart_style.name()));
+                //平均K线在信息栏中多几项，重新计算信息栏的高度
+                let (x, y) = self.manager.current_cursor_position;
+                self.cursor_moved(PhysicalPosition::new(x, y));
+            }
+            PhysicalKey::Code(KeyCode::KeyL) if toggle => {
+                self.manager.price_scale = self.manager.price_scale.next();
+                self.manager.status_message =
+                    Some(format!("价格纵轴：{}", self.manager.price_scale.name()));
+            }
+            PhysicalKey::Code(KeyCode::KeyA) if toggle => {
+                self.manager.show_atr_stop = !self.manager.show_atr_stop;
+            }
+            PhysicalKey::Code(KeyCode::KeyC) if toggle && COMPARE.is_some() => {
+                self.manager.compare_mode = CompareMode::next(self.manager.compare_mode);
+                self.manager.status_message = Some(format!(
+                    "对比品种：{}",
+                    CompareMode::name(self.manager.compare_mode)
+                ));
+            }
+            PhysicalKey::Code(KeyCode::KeyH) if toggle && DEPTH_HEATMAP.is_some() => {
+                self.manager.show_depth_heatmap = !self.manager.show_depth_heatmap;
+            }
+            PhysicalKey::Code(KeyCode::KeyP) if toggle => {
+                self.manager.show_volume_profile = !self.manager.show_volume_profile;
+            }
+            PhysicalKey::Code(code) if toggle && digit_key_index(code).is_some() => {
+                let i = digit_key_index(code).unwrap();
+                if let Some(pane) = self.manager.sub_panes.get_mut(i) {
+                    pane.visible = !pane.visible;
+                    self.resize((self.surface_config.width, self.surface_config.height));
+                }
+            }
+            PhysicalKey::Code(KeyCode::KeyT) if toggle => {
+                self.manager.show_trade_list = !self.manager.show_trade_list;
+                self.resize((self.surface_config.width, self.surface_config.height));
+            }
+            PhysicalKey::Code(KeyCode::KeyR) if toggle => {
+                self.manager.reload_scripts();
+                self.overlay.update(
+                    &self.device,
+                    &self.manager.overlays,
+                    &self.manager.band_fills,
+                );
+                //脚本的副图数量可能变了，全部重建
+                self.sub_pane = self
+                    .manager
+                    .sub_panes
+                    .iter()
+                    .map(|pane| {
+                        SubPanePack::new(
+                            &self.device,
+                            self.surface_config.format,
+                            &self.camera_bind_group_layout,
+                            &self.sub_bind_group_layout,
+                            pane,
+                        )
+                    })
+                    .collect();
+                self.resize((self.surface_config.width, self.surface_config.height));
+                let (x, y) = self.manager.current_cursor_position;
+                self.cursor_moved(PhysicalPosition::new(x, y));
+            }
+            PhysicalKey::Code(KeyCode::KeyF) if toggle => {
+                self.manager.formula_input = Some(String::new());
+                self.manager.status_message = None;
+            }
+            _ => (),
+        }
+    }
+
+    //回车添加公式，Esc取消，出错时保留输入以便修改
+    fn formula_keyboard_input(&mut self, key_event: KeyEvent) {
+        if key_event.state != ElementState::Pressed {
+            return;
+        }
+        let Some(input) = self.manager.formula_input.as_mut() else {
+            return;
+        };
+        match key_event.physical_key {
+            PhysicalKey::Code(KeyCode::Escape) => {
+                self.manager.formula_input = None;
+                self.manager.status_message = None;
+            }
+            PhysicalKey::Code(KeyCode::Backspace) => {
+                input.pop();
+            }
+            PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => {
+                let input = input.clone();
+                self.add_formula(&input);
+            }
+            _ => {
+                if let Some(text) = &key_event.text {
+                    input.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+
+    fn add_formula(&mut self, input: &str) {
+        match self.manager.add_formula(input) {
+            Ok(sub_pane_ix) => {
+                match sub_pane_ix {
+                    None => self.overlay.update(
+                        &self.device,
+                        &self.manager.overlays,
+                        &self.manager.band_fills,
+                    ),
+                    Some(i) if i < self.sub_pane.len() => {
+                        self.sub_pane[i].update(&self.device, &self.manager.sub_panes[i])
+                    }
+                    Some(i) => self.sub_pane.push(SubPanePack::new(
+                        &self.device,
+                        self.surface_config.format,
+                        &self.camera_bind_group_layout,
+                        &self.sub_bind_group_layout,
+                        &self.manager.sub_panes[i],
+                    )),
+                }
+                self.manager.formula_input = None;
+                self.manager.status_message = Some(format!("已添加公式{}", input));
+                self.resize((self.surface_config.width, self.surface_config.height));
+                //指标数量变了，按当前光标位置重新计算信息栏的高度
+                let (x, y) = self.manager.current_cursor_position;
+                self.cursor_moved(PhysicalPosition::new(x, y));
+            }
+            Err(err) => self.manager.status_message = Some(err),
         }
     }
 
     pub fn draw(&mut self) {
         self.manager.update_maxmin_by_left_right_ix();
-
-        //画提示
+        //热力图颜色等配置在第一次画的时候才读
+        self.manager.report_config_errors();
+        let (info_columns, info_rows) = self.info_layout();
+        let info_width = INFO_SIZE.0 * info_columns as f32;
+
+        //画提示，第0行是状态提示，下面是交易图例和按键提示
+        let (key_hint_positions, hint_rows) = key_hint_layout(self.surface_config.width as f32);
+        let hint_top = self.surface_config.height as f32 - hint_height(hint_rows) + 10.0;
+        let hint_y = |row: usize| hint_top + HINT_ROW_HEIGHT * row as f32;
         let hint1 = Section::default()
             .add_text(Text::new("红色虚线：盈利交易").with_color([1.0, 0.0, 0.0, 1.0]))
-            .with_screen_position((10.0, self.surface_config.height as f32 - 70.0));
+            .with_screen_position((10.0, hint_y(1)));
         let hint2 = Section::default()
             .add_text(Text::new("绿色虚线：亏损交易").with_color([0.0, 1.0, 0.0, 1.0]))
-            .with_screen_position((220.0, self.surface_config.height as f32 - 70.0));
+            .with_screen_position((220.0, hint_y(1)));
         let hint3 = Section::default()
             .add_text(Text::new("黄色向上箭头：买入开仓Buy").with_color([1.0, 1.0, 0.0, 1.0]))
-            .with_screen_position((10.0, self.surface_config.height as f32 - 50.0));
+            .with_screen_position((10.0, hint_y(2)));
         let hint4 = Section::default()
             .add_text(Text::new("黄色向下箭头：卖出平仓Sell").with_color([1.0, 1.0, 0.0, 1.0]))
-            .with_screen_position((220.0, self.surface_config.height as f32 - 50.0));
+            .with_screen_position((220.0, hint_y(2)));
         let hint5 = Section::default()
             .add_text(Text::new("紫色向下箭头：卖出开仓Short").with_color([1.0, 0.0, 1.0, 1.0]))
-            .with_screen_position((10.0, self.surface_config.height as f32 - 30.0));
+            .with_screen_position((10.0, hint_y(3)));
         let hint6 = Section::default()
             .add_text(Text::new("紫色向上箭头：买入平仓Cover").with_color([1.0, 0.0, 1.0, 1.0]))
-            .with_screen_position((220.0, self.surface_config.height as f32 - 30.0));
+            .with_screen_position((220.0, hint_y(3)));
         let mut text_list = vec![hint1, hint2, hint3, hint4, hint5, hint6];
+        for ((text, color), (x, row)) in key_hints().into_iter().zip(key_hint_positions) {
+            let hint = Section::default()
+                .add_text(Text::new(text).with_color(color))
+                .with_screen_position((x, hint_y(row)));
+            text_list.push(hint);
+        }
+        //正在输入的公式，后面跟着出错信息
+        let formula_text = self
+            .manager
+            .formula_input
+            .as_ref()
+            .map(|input| format!("{}_", input));
+        let formula_error = self
+            .manager
+            .status_message
+            .as_ref()
+            .map(|message| format!("  {}", message));
+        if let Some(formula_text) = &formula_text {
+            let mut input = Section::default()
+                .add_text(Text::new("公式：").with_color([1.0, 1.0, 0.759, 1.0]))
+                .add_text(Text::new(formula_text).with_color([1.0, 1.0, 1.0, 1.0]))
+                .with_screen_position((10.0, hint_y(0)));
+            if let Some(formula_error) = &formula_error {
+                input = input.add_text(Text::new(formula_error).with_color([1.0, 0.4, 0.4, 1.0]));
+            }
+            text_list.push(input);
+        }
+        //状态提示，例如导出结果
+        else if let Some(status_message) = &self.manager.status_message {
+            let status = Section::default()
+                .add_text(Text::new(status_message).with_color([1.0, 1.0, 0.759, 1.0]))
+                .with_screen_position((10.0, hint_y(0)));
+            text_list.push(status);
+        }
 
         //画价格刻度值
         let num_axis_price = (self.chart_k.shape.height / 30.0) as usize + 1;
         let item_distance = self.chart_k.shape.height / num_axis_price as f32;
-        let item_distance_price =
-            (self.manager.max_price_view - self.manager.min_price_view) / num_axis_price as f64;
         let mut axis_price_string = Vec::new();
         for i in 0..=num_axis_price {
-            let price;
-            if i == num_axis_price {
-                price = self.manager.max_price_view;
+            let price = if i == num_axis_price {
+                self.manager.max_price_view
             } else {
-                price = self.manager.min_price_view + i as f64 * item_distance_price;
-            }
-            axis_price_string.push(price.to_string());
+                self.manager.price_at(i as f64 / num_axis_price as f64)
+            };
+            axis_price_string.push(self.manager.price_label(price));
         }
         for i in 0..=num_axis_price {
             let axis_price = Section::default()
@@ -965,6 +2314,69 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             text_list.push(axis_price);
         }
 
+        //成交量分布的POC和价值区上下沿，标在分布的左边
+        let mut profile_labels = Vec::new();
+        if let Some(profile) = &self.manager.volume_profile {
+            for (name, price, color) in [
+                ("POC", profile.poc_price(), VOLUME_PROFILE_POC_COLOR),
+                (
+                    "VAH",
+                    profile.value_area_high(),
+                    VOLUME_PROFILE_VALUE_AREA_COLOR,
+                ),
+                (
+                    "VAL",
+                    profile.value_area_low(),
+                    VOLUME_PROFILE_VALUE_AREA_COLOR,
+                ),
+            ] {
+                let fraction = self.manager.price_fraction(price) as f32;
+                if !(0.0..=1.0).contains(&fraction) {
+                    continue;
+                }
+                let text = format!("{} {}", name, self.manager.price_label(price));
+                profile_labels.push((text, fraction, color));
+            }
+        }
+        for (text, fraction, color) in profile_labels.iter() {
+            let label = Section::default()
+                .add_text(Text::new(text).with_color(*color))
+                .with_screen_position((
+                    self.chart_k.shape.x + self.chart_k.shape.width * (1.0 - VOLUME_PROFILE_WIDTH)
+                        - 120.0,
+                    self.chart_k.shape.y + (1.0 - fraction) * self.chart_k.shape.height
+                        - AXIS_Y_LABEL_BIAS,
+                ));
+            text_list.push(label);
+        }
+
+        //对比品种的副坐标轴，刻度画在K线图内的右边
+        let mut axis_compare_string = Vec::new();
+        if let (Some(compare), Some(CompareView::Axis { min, max })) =
+            (COMPARE.as_ref(), self.manager.compare_view)
+        {
+            let num_axis_compare = (self.chart_k.shape.height / 60.0) as usize + 1;
+            for i in 0..=num_axis_compare {
+                let price = min + (max - min) * i as f64 / num_axis_compare as f64;
+                if let Some(y) = self.manager.compare_y(price) {
+                    let fraction = self.manager.price_fraction(y) as f32;
+                    axis_compare_string.push((format_value(price), fraction, compare.color));
+                }
+            }
+        }
+        for (text, fraction, color) in axis_compare_string.iter() {
+            let axis_compare = Section::default()
+                .add_text(Text::new(text).with_color(*color))
+                .with_screen_position((
+                    self.chart_k.shape.x + self.chart_k.shape.width
+                        - text.chars().count() as f32 * 8.0
+                        - 4.0,
+                    self.chart_k.shape.y + (1.0 - fraction) * self.chart_k.shape.height
+                        - AXIS_Y_LABEL_BIAS,
+                ));
+            text_list.push(axis_compare);
+        }
+
         //画成交量刻度
         let num_axis_volume = (self.chart_volume.shape.height / 30.0) as usize + 1;
         let item_distance = self.chart_volume.shape.height / num_axis_volume as f32;
@@ -986,7 +2398,35 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
             text_list.push(axis_volume);
         }
 
+        //画副图刻度
+        let mut axis_sub_string = Vec::new();
+        for (pack, view) in self.sub_pane.iter().zip(&self.manager.sub_views) {
+            let Some((min_value, max_value)) = view else {
+                continue;
+            };
+            let shape = &pack.frame.shape;
+            let num_axis_sub = (shape.height / 30.0) as usize + 1;
+            let item_distance = shape.height / num_axis_sub as f32;
+            let item_distance_value = (max_value - min_value) / num_axis_sub as f64;
+            for i in 0..num_axis_sub {
+                axis_sub_string.push((
+                    format_value(min_value + i as f64 * item_distance_value),
+                    (
+                        shape.x + shape.width,
+                        shape.y + shape.height - i as f32 * item_distance - AXIS_Y_LABEL_BIAS,
+                    ),
+                ));
+            }
+        }
+        for (text, pos) in axis_sub_string.iter() {
+            let axis_sub = Section::default()
+                .add_text(Text::new(text).with_color([0.8, 0.8, 0.8, 1.0]))
+                .with_screen_position(*pos);
+            text_list.push(axis_sub);
+        }
+
         //画日期时间刻度值
+        let panes_bottom = self.panes_bottom();
         let num_axis_datetime = (self.chart_k.shape.width / 300.0) as usize + 1;
         let item_distance = self.chart_k.shape.width / num_axis_datetime as f32;
         let item_distance_ix =
@@ -1010,11 +2450,160 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 .add_text(Text::new(&axis_datetime_string[i]).with_color([0.8, 0.8, 0.8, 1.0]))
                 .with_screen_position((
                     self.chart_volume.shape.x + i as f32 * item_distance - AXIS_X_LABEL_BIAS,
-                    self.chart_volume.shape.y + self.chart_volume.shape.height,
+                    panes_bottom,
                 ));
             text_list.push(axis_datetime);
         }
 
+        //画指标图例
+        let legend_ix = self.manager.legend_ix(self.cursor_show);
+        let mut legend_string = Vec::new();
+        for line in self.manager.overlays.iter() {
+            legend_string.push((
+                format!("{} {}", line.name, format_value(line.value_at(legend_ix))),
+                line.color,
+            ));
+        }
+        //对比品种的图例，按涨跌幅画时加上相对第一根可见K线的涨跌幅
+        if let (Some(compare), Some(view)) = (COMPARE.as_ref(), self.manager.compare_view) {
+            let price = compare
+                .close_price
+                .get(legend_ix as usize)
+                .copied()
+                .unwrap_or(f64::NAN);
+            let text = match view {
+                CompareView::Percent { base, .. } if price.is_finite() => format!(
+                    "{} {} ({:+.2}%)",
+                    compare.name,
+                    format_value(price),
+                    (price / base - 1.0) * 100.0
+                ),
+                _ => format!("{} {}", compare.name, format_value(price)),
+            };
+            legend_string.push((text, compare.color));
+        }
+        let mut legend_x = if self.cursor_show && self.cursor_dock_left {
+            self.chart_k.shape.x + info_width + 4.0
+        } else {
+            self.chart_k.shape.x + 4.0
+        };
+        for (text, color) in legend_string.iter() {
+            let legend = Section::default()
+                .add_text(Text::new(text).with_color(*color).with_scale(14.0))
+                .with_screen_position((legend_x, self.chart_k.shape.y + 2.0));
+            text_list.push(legend);
+            legend_x += text.chars().count() as f32 * 8.0 + 12.0;
+        }
+
+        //画副图图例，先写副图名称
+        let mut sub_legend_string = Vec::new();
+        for (pack, pane) in self.sub_pane.iter().zip(&self.manager.sub_panes) {
+            if !pane.visible {
+                continue;
+            }
+            let mut x = pack.frame.shape.x + 4.0;
+            let y = pack.frame.shape.y + 2.0;
+            let mut items = vec![(pane.name.clone(), [0.8, 0.8, 0.8, 1.0])];
+            for series in pane.series.iter() {
+                items.push((
+                    format!(
+                        "{} {}",
+                        series.name,
+                        format_value(series.value_at(legend_ix))
+                    ),
+                    series.color,
+                ));
+            }
+            for (text, color) in items {
+                let width = text.chars().count() as f32 * 8.0 + 12.0;
+                sub_legend_string.push(((x, y), text, color));
+                x += width;
+            }
+        }
+        for (pos, text, color) in sub_legend_string.iter() {
+            let legend = Section::default()
+                .add_text(Text::new(text).with_color(*color).with_scale(14.0))
+                .with_screen_position(*pos);
+            text_list.push(legend);
+        }
+
+        //画交易列表
+        let mut trade_list_string = Vec::new();
+        let mut selected_row = None;
+        if self.manager.show_trade_list {
+            let x = self.trade_list_frame.shape.x;
+            let y = self.trade_list_frame.shape.y;
+            for (i, (title, offset)) in TRADE_LIST_COLUMNS.iter().enumerate() {
+                let mark = match (i, self.manager.trade_sort) {
+                    (0, TradeSort::TimeAsc) => "↑",
+                    (0, TradeSort::TimeDesc) => "↓",
+                    (6, TradeSort::PnlAsc) => "↑",
+                    (6, TradeSort::PnlDesc) => "↓",
+                    _ => "",
+                };
+                trade_list_string.push((
+                    (x + offset, y),
+                    format!("{}{}", title, mark),
+                    [0.8, 0.8, 0.8, 1.0],
+                ));
+            }
+            let visible_rows = self.trade_list_visible_rows();
+            for row in 0..visible_rows {
+                let list_ix = row + self.manager.trade_list_scroll;
+                if list_ix >= self.manager.trade_list_order.len() {
+                    break;
+                }
+                let pair_ix = self.manager.trade_list_order[list_ix];
+                if self.manager.selected_trade == Some(pair_ix) {
+                    selected_row = Some(row);
+                }
+                let d = &TRADE_PAIRS[pair_ix];
+                let row_y = y + (row + 1) as f32 * TRADE_LIST_ROW_HEIGHT;
+                let pnl = d.pnl();
+                let color = if pnl >= 0.0 {
+                    [1.0, 0.0, 0.0, 1.0]
+                } else {
+                    [0.0, 1.0, 0.0, 1.0]
+                };
+                let cells = [
+                    d.open_dt.format("%y-%m-%d %H:%M").to_string(),
+                    d.close_dt.format("%y-%m-%d %H:%M").to_string(),
+                    if d.is_long() { "多" } else { "空" }.to_string(),
+                    d.open_price.to_string(),
+                    d.close_price.to_string(),
+                    d.volume.to_string(),
+                    format!("{:.2}", pnl),
+                    d.holding_bars().to_string(),
+                ];
+                for (cell, (_, offset)) in cells.into_iter().zip(TRADE_LIST_COLUMNS.iter()) {
+                    trade_list_string.push(((x + offset, row_y), cell, color));
+                }
+            }
+        }
+        if let Some(row) = selected_row {
+            self.trade_list_selected.shape = RectangleFilled {
+                x: self.trade_list_frame.shape.x + 1.0,
+                y: self.trade_list_frame.shape.y + (row + 1) as f32 * TRADE_LIST_ROW_HEIGHT,
+                width: TRADE_LIST_WIDTH - 2.0,
+                height: TRADE_LIST_ROW_HEIGHT,
+                vertex: None,
+            };
+            self.trade_list_selected
+                .shape
+                .make_vertex(self.surface_config.width, self.surface_config.height);
+            self.queue.write_buffer(
+                &self.trade_list_selected.vertex_buffer,
+                0,
+                bytemuck::cast_slice(&self.trade_list_selected.shape.vertex.unwrap()),
+            );
+        }
+        for (pos, text, color) in trade_list_string.iter() {
+            let cell = Section::default()
+                .add_text(Text::new(text).with_color(*color).with_scale(14.0))
+                .with_screen_position(*pos);
+            text_list.push(cell);
+        }
+
         let surface_texture = self
             .surface
             .get_current_texture()
@@ -1057,13 +2646,48 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 0..1,
             );
 
+            for (pack, pane) in self.sub_pane.iter().zip(&self.manager.sub_panes) {
+                if !pane.visible {
+                    continue;
+                }
+                rpass.set_pipeline(&pack.frame.render_pipeline);
+                rpass.set_vertex_buffer(0, pack.frame.vertex_buffer.slice(..));
+                rpass.draw(0..pack.frame.shape.vertex.unwrap().len() as u32, 0..1);
+            }
+
             //画蜡烛图
+            self.camera_uniform
+                .set_pane_size(self.chart_k.shape.width, self.chart_k.shape.height);
+            //不按时间画的图横轴是砖块或列，和按时间的指标、委托对不上，只画砖块和交易
+            let time_axis = self.price_chart.is_none();
+            let footprint = self
+                .manager
+                .footprint_fits(self.chart_k.shape.width, self.chart_k.shape.height);
+            let (left_x, right_x) = self.manager.chart_x_range();
+            self.compare.update(&self.device, &self.manager);
+            self.volume_profile.update(
+                &self.device,
+                self.manager.volume_profile.as_ref(),
+                self.manager
+                    .volume_profile_key
+                    .map(|key| (key, (left_x, right_x))),
+            );
             self.camera_uniform.update_view_proj_candle(
-                self.manager.left_ix,
-                self.manager.right_ix,
+                left_x,
+                right_x,
                 self.manager.min_price_view,
                 self.manager.max_price_view,
+                self.manager.price_scale == PriceScale::Log,
             );
+            self.manager.hover_trade = if time_axis
+                && self.cursor_show
+                && self.manager.cursor_price.is_some()
+                && self.manager.pressed_position.is_none()
+            {
+                self.hit_test_trade()
+            } else {
+                None
+            };
             self.queue.write_buffer(
                 &self.camera_buffer_candle,
                 0,
@@ -1077,50 +2701,231 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 0.0,
                 1.0,
             );
-            if CANDLE_VERTEX.up.len() > 0 {
-                rpass.set_pipeline(&self.candle_bar.up_render_pipeline);
-                rpass.set_vertex_buffer(0, self.candle_bar.up_vertex_buffer.slice(..));
-                rpass.draw(0..CANDLE_VERTEX.up.len() as u32, 0..1);
+            //盘口热力图画在最下面
+            if let Some(pack) = self
+                .heatmap
+                .as_ref()
+                .filter(|_| time_axis && self.manager.show_depth_heatmap)
+            {
+                if let Some(buffer) = &pack.vertex_buffer {
+                    rpass.set_pipeline(&pack.render_pipeline);
+                    rpass.set_vertex_buffer(0, buffer.slice(..));
+                    rpass.draw(0..HEATMAP_VERTEX.len() as u32, 0..1);
+                }
+            }
+            //通道填充画在K线下面
+            if let Some(buffer) = self
+                .overlay
+                .fill_vertex_buffer
+                .as_ref()
+                .filter(|_| time_axis)
+            {
+                rpass.set_pipeline(&self.overlay.fill_render_pipeline);
+                rpass.set_vertex_buffer(0, buffer.slice(..));
+                for range in self.overlay.fill_ranges.iter() {
+                    rpass.draw(range.clone(), 0..1);
+                }
+            }
+            //成交量分布画在K线下面
+            if let Some(buffer) = &self.volume_profile.bar_vertex_buffer {
+                rpass.set_pipeline(&self.volume_profile.bar_render_pipeline);
+                rpass.set_vertex_buffer(0, buffer.slice(..));
+                rpass.draw(0..self.volume_profile.bar_vertex_count, 0..1);
+            }
+            if let Some(buffer) = &self.volume_profile.line_vertex_buffer {
+                rpass.set_pipeline(&self.volume_profile.line_render_pipeline);
+                rpass.set_vertex_buffer(0, buffer.slice(..));
+                rpass.draw(0..self.volume_profile.line_vertex_count, 0..1);
+            }
+            match self.manager.chart_style {
+                ChartStyle::Candle if footprint => {
+                    if let Some(pack) = &self.footprint {
+                        if let Some(buffer) = &pack.cell_vertex_buffer {
+                            rpass.set_pipeline(&pack.cell_render_pipeline);
+                            rpass.set_vertex_buffer(0, buffer.slice(..));
+                            rpass.draw(0..FOOTPRINT_VERTEX.cells.len() as u32, 0..1);
+                        }
+                        if let Some(buffer) = &pack.line_vertex_buffer {
+                            rpass.set_pipeline(&pack.line_render_pipeline);
+                            rpass.set_vertex_buffer(0, buffer.slice(..));
+                            rpass.draw(0..FOOTPRINT_VERTEX.lines.len() as u32, 0..1);
+                        }
+                    }
+                }
+                ChartStyle::Candle => self.candle_bar.draw(
+                    &mut rpass,
+                    [
+                        &self.candle_bar.body_vertex_buffer,
+                        &self.candle_bar.outline_vertex_buffer,
+                        &self.candle_bar.wick_vertex_buffer,
+                    ],
+                    &CANDLE_VERTEX,
+                ),
+                ChartStyle::HeikinAshi => self.candle_bar.draw(
+                    &mut rpass,
+                    [
+                        &self.candle_bar.heikin_ashi_body_vertex_buffer,
+                        &self.candle_bar.heikin_ashi_outline_vertex_buffer,
+                        &self.candle_bar.heikin_ashi_wick_vertex_buffer,
+                    ],
+                    &HEIKIN_ASHI_VERTEX,
+                ),
+                ChartStyle::Renko | ChartStyle::RangeBar | ChartStyle::PointFigure => {
+                    if let Some(pack) = &self.price_chart {
+                        self.candle_bar.draw(
+                            &mut rpass,
+                            [
+                                &pack.body_vertex_buffer,
+                                &pack.outline_vertex_buffer,
+                                &pack.wick_vertex_buffer,
+                            ],
+                            &pack.chart.candle_vertex,
+                        );
+                        self.candle_bar.draw_lines(
+                            &mut rpass,
+                            [
+                                &pack.line_up_vertex_buffer,
+                                &pack.line_down_vertex_buffer,
+                                &pack.line_stay_vertex_buffer,
+                            ],
+                            &pack.chart.line_vertex,
+                        );
+                    }
+                }
+                ChartStyle::Line | ChartStyle::Area => {
+                    //面积图先画填充，再在上面画收盘价折线
+                    if self.manager.chart_style == ChartStyle::Area {
+                        if let Some(buffer) = &self.candle_bar.area_vertex_buffer {
+                            rpass.set_pipeline(&self.candle_bar.area_render_pipeline);
+                            rpass.set_vertex_buffer(0, buffer.slice(..));
+                            rpass.draw(0..AREA_VERTEX.len() as u32, 0..1);
+                        }
+                    }
+                    if let Some(buffer) = &self.candle_bar.close_line_vertex_buffer {
+                        rpass.set_pipeline(&self.candle_bar.close_line_render_pipeline);
+                        rpass.set_vertex_buffer(0, buffer.slice(..));
+                        rpass.draw(0..CLOSE_LINE_VERTEX.len() as u32, 0..1);
+                    }
+                }
+                ChartStyle::Tpo => {
+                    if let Some(pack) = &self.tpo {
+                        if let Some(buffer) = pack.block_vertex_buffer.as_ref().filter(|_| {
+                            !self.manager.tpo_letters_fit(
+                                self.chart_k.shape.width,
+                                self.chart_k.shape.height,
+                            )
+                        }) {
+                            rpass.set_pipeline(&pack.block_render_pipeline);
+                            rpass.set_vertex_buffer(0, buffer.slice(..));
+                            rpass.draw(0..TPO_VERTEX.blocks.len() as u32, 0..1);
+                        }
+                        if let Some(buffer) = &pack.line_vertex_buffer {
+                            rpass.set_pipeline(&pack.line_render_pipeline);
+                            rpass.set_vertex_buffer(0, buffer.slice(..));
+                            rpass.draw(0..TPO_VERTEX.lines.len() as u32, 0..1);
+                        }
+                    }
+                }
+                ChartStyle::Ohlc => self.candle_bar.draw_lines(
+                    &mut rpass,
+                    [
+                        &self.candle_bar.ohlc_up_vertex_buffer,
+                        &self.candle_bar.ohlc_down_vertex_buffer,
+                        &self.candle_bar.ohlc_stay_vertex_buffer,
+                    ],
+                    &OHLC_VERTEX,
+                ),
+            }
+
+            //画对比品种，砖块图等不按时间的图上compare_view为None，不画
+            if let Some(buffer) = self
+                .compare
+                .vertex_buffer
+                .as_ref()
+                .filter(|_| self.manager.compare_view.is_some())
+            {
+                rpass.set_pipeline(&self.compare.render_pipeline);
+                rpass.set_vertex_buffer(0, buffer.slice(..));
+                rpass.draw(0..self.compare.vertex_count, 0..1);
             }
-            if CANDLE_VERTEX.down.len() > 0 {
-                rpass.set_pipeline(&self.candle_bar.down_render_pipeline);
-                rpass.set_vertex_buffer(0, self.candle_bar.down_vertex_buffer.slice(..));
-                rpass.draw(0..CANDLE_VERTEX.down.len() as u32, 0..1);
+
+            //画均线等指标
+            if time_axis {
+                if let Some(buffer) = &self.overlay.line_vertex_buffer {
+                    rpass.set_pipeline(&self.overlay.line_render_pipeline);
+                    rpass.set_vertex_buffer(0, buffer.slice(..));
+                    rpass.draw(0..self.overlay.line_vertex_count, 0..1);
+                }
+                if let Some(buffer) = &self.overlay.dot_vertex_buffer {
+                    rpass.set_pipeline(&self.overlay.dot_render_pipeline);
+                    rpass.set_vertex_buffer(0, buffer.slice(..));
+                    rpass.draw(0..self.overlay.dot_vertex_count, 0..1);
+                }
             }
-            if CANDLE_VERTEX.down_hl.len() > 0 {
-                rpass.set_pipeline(&self.candle_bar.down_hl_render_pipeline);
-                rpass.set_vertex_buffer(0, self.candle_bar.down_hl_vertex_buffer.slice(..));
-                rpass.draw(0..CANDLE_VERTEX.down_hl.len() as u32, 0..1);
+
+            //画委托
+            if time_axis && self.manager.show_orders {
+                for (i, (_, vertex)) in order_vertex_lists().iter().enumerate() {
+                    if !vertex.is_empty() {
+                        rpass.set_pipeline(&self.order.render_pipelines[i]);
+                        rpass.set_vertex_buffer(0, self.order.vertex_buffers[i].slice(..));
+                        rpass.draw(0..vertex.len() as u32, 0..1);
+                    }
+                }
             }
-            if CANDLE_VERTEX.stay.len() > 0 {
-                rpass.set_pipeline(&self.candle_bar.stay_render_pipeline);
-                rpass.set_vertex_buffer(0, self.candle_bar.stay_vertex_buffer.slice(..));
-                rpass.draw(0..CANDLE_VERTEX.stay.len() as u32, 0..1);
+
+            //画ATR止损线
+            if time_axis && self.manager.show_atr_stop {
+                if let Some(buffer) = &self.atr_stop.vertex_buffer {
+                    rpass.set_pipeline(&self.atr_stop.render_pipeline);
+                    rpass.set_vertex_buffer(0, buffer.slice(..));
+                    rpass.draw(0..ATR_STOP_VERTEX.len() as u32, 0..1);
+                }
             }
 
             //画交易对连线
-            rpass.set_pipeline(&self.trade.profit_render_pipeline);
-            rpass.set_vertex_buffer(0, self.trade.profit_vertex_buffer.slice(..));
-            rpass.draw(0..TRADE_PAIRS_VERTEX.profit.len() as u32, 0..1);
-            rpass.set_pipeline(&self.trade.loss_render_pipeline);
-            rpass.set_vertex_buffer(0, self.trade.loss_vertex_buffer.slice(..));
-            rpass.draw(0..TRADE_PAIRS_VERTEX.loss.len() as u32, 0..1);
+            let trade = self
+                .price_chart
+                .as_ref()
+                .map_or(&self.trade, |pack| &pack.trade);
+            rpass.set_pipeline(&trade.profit_render_pipeline);
+            rpass.set_vertex_buffer(0, trade.profit_vertex_buffer.slice(..));
+            rpass.draw(0..trade.vertex.profit.len() as u32, 0..1);
+            rpass.set_pipeline(&trade.loss_render_pipeline);
+            rpass.set_vertex_buffer(0, trade.loss_vertex_buffer.slice(..));
+            rpass.draw(0..trade.vertex.loss.len() as u32, 0..1);
             //画交易对三角，平仓先画
-            rpass.set_pipeline(&self.trade.sell_render_pipeline);
-            rpass.set_vertex_buffer(0, self.trade.sell_vertex_buffer.slice(..));
-            rpass.draw(0..TRADE_PAIRS_VERTEX.sell.len() as u32, 0..1);
-            rpass.set_pipeline(&self.trade.cover_render_pipeline);
-            rpass.set_vertex_buffer(0, self.trade.cover_vertex_buffer.slice(..));
-            rpass.draw(0..TRADE_PAIRS_VERTEX.cover.len() as u32, 0..1);
+            rpass.set_pipeline(&trade.sell_render_pipeline);
+            rpass.set_vertex_buffer(0, trade.sell_vertex_buffer.slice(..));
+            rpass.draw(0..trade.vertex.sell.len() as u32, 0..1);
+            rpass.set_pipeline(&trade.cover_render_pipeline);
+            rpass.set_vertex_buffer(0, trade.cover_vertex_buffer.slice(..));
+            rpass.draw(0..trade.vertex.cover.len() as u32, 0..1);
             //开仓故意后画
-            rpass.set_pipeline(&self.trade.buy_render_pipeline);
-            rpass.set_vertex_buffer(0, self.trade.buy_vertex_buffer.slice(..));
-            rpass.draw(0..TRADE_PAIRS_VERTEX.buy.len() as u32, 0..1);
-            rpass.set_pipeline(&self.trade.short_render_pipeline);
-            rpass.set_vertex_buffer(0, self.trade.short_vertex_buffer.slice(..));
-            rpass.draw(0..TRADE_PAIRS_VERTEX.short.len() as u32, 0..1);
+            rpass.set_pipeline(&trade.buy_render_pipeline);
+            rpass.set_vertex_buffer(0, trade.buy_vertex_buffer.slice(..));
+            rpass.draw(0..trade.vertex.buy.len() as u32, 0..1);
+            rpass.set_pipeline(&trade.short_render_pipeline);
+            rpass.set_vertex_buffer(0, trade.short_vertex_buffer.slice(..));
+            rpass.draw(0..trade.vertex.short.len() as u32, 0..1);
+            //画选中的交易对
+            if time_axis && self.manager.selected_trade.is_some() {
+                rpass.set_pipeline(&self.trade_highlight.line_render_pipeline);
+                rpass.set_vertex_buffer(0, self.trade_highlight.line_vertex_buffer.slice(..));
+                rpass.draw(0..2, 0..1);
+                rpass.set_pipeline(&self.trade_highlight.up_render_pipeline);
+                rpass.set_vertex_buffer(0, self.trade_highlight.up_vertex_buffer.slice(..));
+                rpass.draw(0..3, 0..1);
+                rpass.set_pipeline(&self.trade_highlight.down_render_pipeline);
+                rpass.set_vertex_buffer(0, self.trade_highlight.down_vertex_buffer.slice(..));
+                rpass.draw(0..3, 0..1);
+            }
 
             //画成交量
+            self.camera_uniform.set_pane_size(
+                self.chart_volume.shape.width,
+                self.chart_volume.shape.height,
+            );
             self.camera_uniform.update_view_proj_volume(
                 self.manager.left_ix,
                 self.manager.right_ix,
@@ -1139,22 +2944,114 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 0.0,
                 1.0,
             );
-            if VOLUME_VERTEX.up.len() > 0 {
-                rpass.set_pipeline(&self.volume_bar.up_render_pipeline);
-                rpass.set_vertex_buffer(0, self.volume_bar.up_vertex_buffer.slice(..));
-                rpass.draw(0..VOLUME_VERTEX.up.len() as u32, 0..1);
-            }
-            if VOLUME_VERTEX.down.len() > 0 {
-                rpass.set_pipeline(&self.volume_bar.down_render_pipeline);
-                rpass.set_vertex_buffer(0, self.volume_bar.down_vertex_buffer.slice(..));
-                rpass.draw(0..VOLUME_VERTEX.down.len() as u32, 0..1);
+            for (render_pipeline, buffer, vertex) in [
+                (
+                    &self.volume_bar.body_render_pipeline,
+                    &self.volume_bar.body_vertex_buffer,
+                    &VOLUME_VERTEX.body,
+                ),
+                (
+                    &self.volume_bar.outline_render_pipeline,
+                    &self.volume_bar.outline_vertex_buffer,
+                    &VOLUME_VERTEX.outline,
+                ),
+            ] {
+                if let Some(buffer) = buffer {
+                    rpass.set_pipeline(render_pipeline);
+                    rpass.set_vertex_buffer(0, buffer.slice(..));
+                    rpass.draw(0..vertex.len() as u32, 0..1);
+                }
             }
-            if VOLUME_VERTEX.stay.len() > 0 {
-                rpass.set_pipeline(&self.volume_bar.stay_render_pipeline);
-                rpass.set_vertex_buffer(0, self.volume_bar.stay_vertex_buffer.slice(..));
-                rpass.draw(0..VOLUME_VERTEX.stay.len() as u32, 0..1);
+
+            //画副图，每个副图写自己的相机缓冲
+            for (pack, view) in self.sub_pane.iter().zip(&self.manager.sub_views) {
+                let Some((min_value, max_value)) = view else {
+                    continue;
+                };
+                self.camera_uniform
+                    .set_pane_size(pack.frame.shape.width, pack.frame.shape.height);
+                self.camera_uniform.update_view_proj_sub(
+                    self.manager.left_ix,
+                    self.manager.right_ix,
+                    *min_value,
+                    *max_value,
+                );
+                self.queue.write_buffer(
+                    &pack.camera_buffer,
+                    0,
+                    bytemuck::cast_slice(&[self.camera_uniform]),
+                );
+                rpass.set_viewport(
+                    pack.frame.shape.x,
+                    pack.frame.shape.y,
+                    pack.frame.shape.width,
+                    pack.frame.shape.height,
+                    0.0,
+                    1.0,
+                );
+                rpass.set_bind_group(1, &pack.camera_bind_group, &[]);
+                if let Some(buffer) = &pack.line_vertex_buffer {
+                    rpass.set_pipeline(&pack.line_render_pipeline);
+                    rpass.set_vertex_buffer(0, buffer.slice(..));
+                    rpass.draw(0..pack.line_vertex_count, 0..1);
+                }
+                if let Some(buffer) = &pack.dot_vertex_buffer {
+                    rpass.set_pipeline(&pack.dot_render_pipeline);
+                    rpass.set_vertex_buffer(0, buffer.slice(..));
+                    rpass.draw(0..pack.dot_vertex_count, 0..1);
+                }
             }
 
+            //市场轮廓放大后画字母，每个字母居中画在自己的方块上
+            let mut tpo_letters = Vec::new();
+            if self.manager.chart_style == ChartStyle::Tpo
+                && self
+                    .manager
+                    .tpo_letters_fit(self.chart_k.shape.width, self.chart_k.shape.height)
+            {
+                for session in self.manager.visible_tpo_sessions() {
+                    let width = session.column_width();
+                    let row_size = session.profile.row_size;
+                    for (period, row, k) in session.blocks() {
+                        let (x, y) = session.block_position(row, k);
+                        let position = Vec4::new(
+                            (x + width * 0.45) as f32,
+                            (y + row_size / 2.0) as f32,
+                            0.0,
+                            1.0,
+                        );
+                        tpo_letters.push((position, tpo_letter(period).to_string()));
+                    }
+                }
+            }
+            //足迹图每个格子居中写“主动卖出×主动买入”，K线下方写这根K线的主动买入减主动卖出
+            let mut footprint_texts = Vec::new();
+            if let Some(data) = FOOTPRINT.as_ref().filter(|_| footprint) {
+                for i in self.manager.left_ix..=self.manager.right_ix {
+                    let x = i as f32 + 0.05;
+                    for cell in data.bars[i as usize].iter() {
+                        let position = Vec4::new(x, cell.price as f32, 0.0, 1.0);
+                        footprint_texts.push((position, cell.text(), [0.9, 0.9, 0.9, 1.0], -9.0));
+                    }
+                    if data.bars[i as usize].is_empty() {
+                        continue;
+                    }
+                    let delta = data.delta(i as usize);
+                    let (text, color) = if delta > 0.0 {
+                        (
+                            format!("+{}", format_value(delta)),
+                            [1.0, 0.068, 0.068, 1.0],
+                        )
+                    } else if delta < 0.0 {
+                        (format_value(delta), [0.0, 1.0, 1.0, 1.0])
+                    } else {
+                        ("0".to_string(), [1.0, 1.0, 1.0, 1.0])
+                    };
+                    let low = HISTORY.low_price[i as usize] - data.row_size / 2.0;
+                    let position = Vec4::new(x, low as f32, 0.0, 1.0);
+                    footprint_texts.push((position, text, color, 4.0));
+                }
+            }
             if self.manager.right_ix - self.manager.left_ix + 1
                 <= self.chart_k.shape.width as i64 * 5
             {
@@ -1169,9 +3066,13 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 );
                 let brush_candle = self.brush_candle.as_mut().unwrap();
                 let mut candle_sections = Vec::new();
+                let trade_vertex = self
+                    .price_chart
+                    .as_ref()
+                    .map_or(&*TRADE_PAIRS_VERTEX, |pack| &pack.chart.trade_vertex);
                 //先画平仓文字
-                for pos_and_text in TRADE_PAIRS_VERTEX.sell_text.iter() {
-                    let vec4 = ((*VP_MATRIX.lock().unwrap()) * pos_and_text.0).to_array();
+                for pos_and_text in trade_vertex.sell_text.iter() {
+                    let vec4 = project_candle_position(pos_and_text.0).to_array();
                     if vec4[0] < -1.2 || vec4[0] > 1.2 {
                         continue;
                     }
@@ -1184,8 +3085,8 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                         .with_screen_position(pos);
                     candle_sections.push(trade_volume_text);
                 }
-                for pos_and_text in TRADE_PAIRS_VERTEX.cover_text.iter() {
-                    let vec4 = ((*VP_MATRIX.lock().unwrap()) * pos_and_text.0).to_array();
+                for pos_and_text in trade_vertex.cover_text.iter() {
+                    let vec4 = project_candle_position(pos_and_text.0).to_array();
                     if vec4[0] < -1.2 || vec4[0] > 1.2 {
                         continue;
                     }
@@ -1199,8 +3100,8 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                     candle_sections.push(trade_volume_text);
                 }
                 //先画开仓文字
-                for pos_and_text in TRADE_PAIRS_VERTEX.buy_text.iter() {
-                    let vec4 = ((*VP_MATRIX.lock().unwrap()) * pos_and_text.0).to_array();
+                for pos_and_text in trade_vertex.buy_text.iter() {
+                    let vec4 = project_candle_position(pos_and_text.0).to_array();
                     if vec4[0] < -1.2 || vec4[0] > 1.2 {
                         continue;
                     }
@@ -1213,8 +3114,8 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                         .with_screen_position(pos);
                     candle_sections.push(trade_volume_text);
                 }
-                for pos_and_text in TRADE_PAIRS_VERTEX.short_text.iter() {
-                    let vec4 = ((*VP_MATRIX.lock().unwrap()) * pos_and_text.0).to_array();
+                for pos_and_text in trade_vertex.short_text.iter() {
+                    let vec4 = project_candle_position(pos_and_text.0).to_array();
                     if vec4[0] < -1.2 || vec4[0] > 1.2 {
                         continue;
                     }
@@ -1227,6 +3128,48 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                         .with_screen_position(pos);
                     candle_sections.push(trade_volume_text);
                 }
+                for (position, letter) in tpo_letters.iter() {
+                    let vec4 = project_candle_position(*position).to_array();
+                    if vec4[0] < -1.2 || vec4[0] > 1.2 {
+                        continue;
+                    }
+                    let pos = (
+                        (vec4[0] + 1.0) / 2.0 * self.chart_k.shape.width - 4.0,
+                        (-vec4[1] + 1.0) / 2.0 * self.chart_k.shape.height - 9.0,
+                    );
+                    let section = Section::default()
+                        .add_text(Text::new(letter).with_color([0.9, 0.9, 0.9, 1.0]))
+                        .with_screen_position(pos);
+                    candle_sections.push(section);
+                }
+                for (position, text, color, offset_y) in footprint_texts.iter() {
+                    let vec4 = project_candle_position(*position).to_array();
+                    let pos = (
+                        (vec4[0] + 1.0) / 2.0 * self.chart_k.shape.width
+                            - (text.chars().count() + 1) as f32 * 4.0,
+                        (-vec4[1] + 1.0) / 2.0 * self.chart_k.shape.height + offset_y,
+                    );
+                    let section = Section::default()
+                        .add_text(Text::new(text).with_color(*color))
+                        .with_screen_position(pos);
+                    candle_sections.push(section);
+                }
+                //脚本的文字，居中画在指定的价格上
+                for script_text in self.manager.script_texts.iter().filter(|_| time_axis) {
+                    let vec4 = project_candle_position(script_text.position).to_array();
+                    if vec4[0] < -1.2 || vec4[0] > 1.2 {
+                        continue;
+                    }
+                    let pos = (
+                        (vec4[0] + 1.0) / 2.0 * self.chart_k.shape.width
+                            - script_text.text.chars().count() as f32 * 4.0,
+                        (-vec4[1] + 1.0) / 2.0 * self.chart_k.shape.height - 9.0,
+                    );
+                    let section = Section::default()
+                        .add_text(Text::new(&script_text.text).with_color(script_text.color))
+                        .with_screen_position(pos);
+                    candle_sections.push(section);
+                }
                 match brush_candle.queue(&self.device, &self.queue, candle_sections) {
                     Ok(_) => (),
                     Err(err) => {
@@ -1245,6 +3188,22 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 0.0,
                 1.0,
             );
+            if self.manager.show_trade_list {
+                rpass.set_pipeline(&self.trade_list_frame.render_pipeline);
+                rpass.set_vertex_buffer(0, self.trade_list_frame.vertex_buffer.slice(..));
+                rpass.draw(
+                    0..self.trade_list_frame.shape.vertex.unwrap().len() as u32,
+                    0..1,
+                );
+                if selected_row.is_some() {
+                    rpass.set_pipeline(&self.trade_list_selected.render_pipeline);
+                    rpass.set_vertex_buffer(0, self.trade_list_selected.vertex_buffer.slice(..));
+                    rpass.draw(
+                        0..self.trade_list_selected.shape.vertex.unwrap().len() as u32,
+                        0..1,
+                    );
+                }
+            }
             match brush.queue(&self.device, &self.queue, text_list) {
                 Ok(_) => (),
                 Err(err) => {
@@ -1255,33 +3214,17 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
 
             if self.cursor_show {
                 let mut sections = Vec::new();
-                //画光标水平线
-                let price_label_string;
-                if self.manager.cursor_price.is_some() {
-                    rpass.set_pipeline(&self.cursor_horizontal.render_pipeline);
-                    rpass.set_vertex_buffer(0, self.cursor_horizontal.vertex_buffer.slice(..));
-                    rpass.draw(
-                        0..self.cursor_horizontal.shape.vertex.unwrap().len() as u32,
-                        0..1,
-                    );
-                    rpass.set_pipeline(&self.cursor_horizontal_label.render_pipeline);
-                    rpass
-                        .set_vertex_buffer(0, self.cursor_horizontal_label.vertex_buffer.slice(..));
-                    rpass.draw(
-                        0..self.cursor_horizontal_label.shape.vertex.unwrap().len() as u32,
-                        0..1,
-                    );
-                    price_label_string = self.manager.cursor_price.unwrap().to_string();
-                    let price_label = Section::default()
-                        .add_text(Text::new(&price_label_string).with_color([0.0, 0.0, 0.0, 1.0]))
-                        .with_screen_position((
-                            self.cursor_horizontal_label.shape.x + 1.0,
-                            self.cursor_horizontal_label.shape.y,
-                        ));
-                    sections.push(price_label);
-                }
-                let volume_label_string;
-                if self.manager.cursor_volume.is_some() {
+                //画光标水平线，光标在哪个图里就显示哪个图的值
+                let horizontal_label_string = if let Some(price) = self.manager.cursor_price {
+                    Some(self.manager.price_label(price))
+                } else if let Some(volume) = self.manager.cursor_volume {
+                    Some(volume.to_string())
+                } else {
+                    self.manager
+                        .cursor_sub
+                        .map(|(_, value)| format_value(value))
+                };
+                if let Some(horizontal_label_string) = &horizontal_label_string {
                     rpass.set_pipeline(&self.cursor_horizontal.render_pipeline);
                     rpass.set_vertex_buffer(0, self.cursor_horizontal.vertex_buffer.slice(..));
                     rpass.draw(
@@ -1295,14 +3238,15 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                         0..self.cursor_horizontal_label.shape.vertex.unwrap().len() as u32,
                         0..1,
                     );
-                    volume_label_string = self.manager.cursor_volume.unwrap().to_string();
-                    let volume_label = Section::default()
-                        .add_text(Text::new(&volume_label_string).with_color([0.0, 0.0, 0.0, 1.0]))
+                    let horizontal_label = Section::default()
+                        .add_text(
+                            Text::new(horizontal_label_string).with_color([0.0, 0.0, 0.0, 1.0]),
+                        )
                         .with_screen_position((
                             self.cursor_horizontal_label.shape.x + 1.0,
                             self.cursor_horizontal_label.shape.y,
                         ));
-                    sections.push(volume_label);
+                    sections.push(horizontal_label);
                 }
                 //画光标垂线
                 rpass.set_pipeline(&self.cursor_vertical.render_pipeline);
@@ -1331,31 +3275,88 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
                 rpass.set_vertex_buffer(0, self.info_box.vertex_buffer.slice(..));
                 rpass.draw(0..self.info_box.shape.vertex.unwrap().len() as u32, 0..1);
 
-                let info_text_string = format!(
-                    "Date\n{}\n\nTime\n{}\n\nOpen\n{}\n\nHigh\n{}\n\nLow\n{}\n\nClose\n{}\n\nVolume\n{}",
-                    HISTORY.datetime[self.manager.cursor_ix as usize]
-                        .format("%Y-%m-%d")
-                        .to_string(),
-                    HISTORY.datetime[self.manager.cursor_ix as usize].format("%H:%M").to_string(),
-                    HISTORY.open_price[self.manager.cursor_ix as usize],
-                    HISTORY.high_price[self.manager.cursor_ix as usize],
-                    HISTORY.low_price[self.manager.cursor_ix as usize],
-                    HISTORY.close_price[self.manager.cursor_ix as usize],
-                    HISTORY.volume[self.manager.cursor_ix as usize],
-                );
-                let info_label = Section::default()
-                    .add_text(Text::new(&info_text_string).with_color([0.0, 0.0, 0.0, 1.0]))
-                    .with_screen_position((
-                        if self.cursor_dock_left {
-                            self.chart_k.shape.x
-                        } else {
-                            self.chart_k.shape.x + self.chart_k.shape.width - INFO_SIZE.0
-                        },
-                        self.chart_k.shape.y,
+                let cursor_ix = self.manager.cursor_ix as usize;
+                let mut info_items = vec![
+                    format!("Date\n{}", HISTORY.datetime[cursor_ix].format("%Y-%m-%d")),
+                    format!("Time\n{}", HISTORY.datetime[cursor_ix].format("%H:%M")),
+                    format!("Open\n{}", HISTORY.open_price[cursor_ix]),
+                    format!("High\n{}", HISTORY.high_price[cursor_ix]),
+                    format!("Low\n{}", HISTORY.low_price[cursor_ix]),
+                    format!("Close\n{}", HISTORY.close_price[cursor_ix]),
+                    format!("Volume\n{}", HISTORY.volume[cursor_ix]),
+                ];
+                //平均K线、砖块图等在真实价格后面显示主图上对应的那根的价格
+                if let Some((label, history, ix)) = self.manager.cursor_chart_bar() {
+                    info_items.extend([
+                        format!("{label} Open\n{}", format_value(history.open_price[ix])),
+                        format!("{label} High\n{}", format_value(history.high_price[ix])),
+                        format!("{label} Low\n{}", format_value(history.low_price[ix])),
+                        format!("{label} Close\n{}", format_value(history.close_price[ix])),
+                    ]);
+                }
+                for line in self.manager.overlays.iter() {
+                    info_items.push(format!(
+                        "{}\n{}",
+                        line.name,
+                        format_value(line.value_at(self.manager.cursor_ix))
                     ));
+                }
+                let info_x = if self.cursor_dock_left {
+                    self.chart_k.shape.x
+                } else {
+                    self.chart_k.shape.x + self.chart_k.shape.width - info_width
+                };
+                let info_column_strings: Vec<String> = info_items
+                    .chunks(info_rows)
+                    .map(|column| column.join("\n\n"))
+                    .collect();
+                let info_y = self.chart_k.shape.y;
+                let info_labels = info_column_strings.iter().enumerate().map(|(i, text)| {
+                    Section::default()
+                        .add_text(Text::new(text).with_color([0.0, 0.0, 0.0, 1.0]))
+                        .with_screen_position((info_x + INFO_SIZE.0 * i as f32, info_y))
+                });
+                //画交易对悬停提示
+                let tooltip_string;
+                if let Some((pair_ix, hover)) = self.manager.hover_trade {
+                    tooltip_string = TRADE_PAIRS[pair_ix].tooltip(hover);
+                    let height = tooltip_string.lines().count() as f32 * TOOLTIP_LINE_HEIGHT + 4.0;
+                    let (cursor_x, cursor_y) = self.manager.current_cursor_position;
+                    let mut x = cursor_x as f32 + 16.0;
+                    if x + TOOLTIP_WIDTH > self.chart_k.shape.x + self.chart_k.shape.width {
+                        x = cursor_x as f32 - 16.0 - TOOLTIP_WIDTH;
+                    }
+                    let mut y = cursor_y as f32 + 16.0;
+                    if y + height > self.surface_config.height as f32 {
+                        y = (self.surface_config.height as f32 - height).max(0.0);
+                    }
+                    self.tooltip.shape = RectangleFilled {
+                        x,
+                        y,
+                        width: TOOLTIP_WIDTH,
+                        height,
+                        vertex: None,
+                    };
+                    self.tooltip
+                        .shape
+                        .make_vertex(self.surface_config.width, self.surface_config.height);
+                    self.queue.write_buffer(
+                        &self.tooltip.vertex_buffer,
+                        0,
+                        bytemuck::cast_slice(&self.tooltip.shape.vertex.unwrap()),
+                    );
+                    rpass.set_pipeline(&self.tooltip.render_pipeline);
+                    rpass.set_vertex_buffer(0, self.tooltip.vertex_buffer.slice(..));
+                    rpass.draw(0..self.tooltip.shape.vertex.unwrap().len() as u32, 0..1);
+                    let tooltip_label = Section::default()
+                        .add_text(Text::new(&tooltip_string).with_color([0.0, 0.0, 0.0, 1.0]))
+                        .with_screen_position((x + 4.0, y + 2.0));
+                    sections.push(tooltip_label);
+                }
                 //画文字（第二阶段）
                 let brush_top = self.brush_top.as_mut().unwrap();
-                sections.extend([datetime_label, info_label]);
+                sections.push(datetime_label);
+                sections.extend(info_labels);
                 match brush_top.queue(&self.device, &self.queue, sections) {
                     Ok(_) => (),
                     Err(err) => {
@@ -1370,6 +3371,132 @@ impl<'window, 'font> WgpuCtx<'window, 'font> {
     }
 }
 
+//顶点为空时返回None，空缓冲区不能设为顶点缓冲
+fn create_vertex_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    vertex: &[T],
+) -> Option<wgpu::Buffer> {
+    if vertex.is_empty() {
+        return None;
+    }
+    Some(device.create_buffer_init(&BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(vertex),
+        usage: wgpu::BufferUsages::VERTEX,
+    }))
+}
+
+//成交量分布占K线图宽度的比例
+const VOLUME_PROFILE_WIDTH: f32 = 0.25;
+const VOLUME_PROFILE_COLOR: [f32; 4] = [0.45, 0.45, 0.55, 0.35];
+const VOLUME_PROFILE_VALUE_AREA_COLOR: [f32; 4] = [0.55, 0.65, 0.9, 1.0];
+const VOLUME_PROFILE_POC_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+
+//成交量分布的横条和POC、价值区上下沿的横线，横条从K线图右边向左伸出，最长的一行占VOLUME_PROFILE_WIDTH
+fn make_volume_profile_vertex(
+    profile: &Profile,
+    (left_x, right_x): (i64, i64),
+) -> (Vec<ColorVertex>, Vec<ColorVertex>) {
+    let right = right_x as f32 + 0.5;
+    let width = (right_x - left_x + 1) as f32 * VOLUME_PROFILE_WIDTH;
+    let max_value = profile.max_value();
+    let mut bar_vertex = Vec::new();
+    for (row, value) in profile.values.iter().enumerate() {
+        if *value <= 0.0 {
+            continue;
+        }
+        let color = if row == profile.poc {
+            [1.0, 0.8, 0.2, 0.5]
+        } else if (profile.value_area.0..=profile.value_area.1).contains(&row) {
+            [0.55, 0.65, 0.9, 0.4]
+        } else {
+            VOLUME_PROFILE_COLOR
+        };
+        let left = right - width * (*value / max_value) as f32;
+        let bottom = profile.row_low(row) as f32;
+        let top = profile.row_low(row + 1) as f32;
+        let point = |x: f32, y: f32| ColorVertex {
+            position: [x, y],
+            color,
+        };
+        bar_vertex.extend([
+            point(left, top),
+            point(left, bottom),
+            point(right, top),
+            point(right, top),
+            point(left, bottom),
+            point(right, bottom),
+        ]);
+    }
+    let mut line_vertex = Vec::new();
+    for (price, color) in [
+        (profile.poc_price(), VOLUME_PROFILE_POC_COLOR),
+        (profile.value_area_high(), VOLUME_PROFILE_VALUE_AREA_COLOR),
+        (profile.value_area_low(), VOLUME_PROFILE_VALUE_AREA_COLOR),
+    ] {
+        line_vertex.extend([
+            ColorVertex {
+                position: [right - width, price as f32],
+                color,
+            },
+            ColorVertex {
+                position: [right, price as f32],
+                color,
+            },
+        ]);
+    }
+    (bar_vertex, line_vertex)
+}
+
+//数字键1到9对应第1到第9个副图
+fn digit_key_index(code: KeyCode) -> Option<usize> {
+    [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ]
+    .iter()
+    .position(|digit| *digit == code)
+}
+
+//按指标序列的画法生成LineList顶点，点另外画
+fn make_series_vertex(series: &Series) -> Vec<ColorVertex> {
+    match series.style {
+        SeriesStyle::Line => make_line_vertex(&series.values, series.color),
+        SeriesStyle::Histogram { down_color } => {
+            make_histogram_vertex(&series.values, series.color, down_color)
+        }
+        SeriesStyle::Dots => Vec::new(),
+    }
+}
+
+//画成点的指标序列，生成TriangleList顶点
+fn make_series_dot_vertex(series: &[Series]) -> Vec<ColorVertex> {
+    series
+        .iter()
+        .filter(|series| series.style == SeriesStyle::Dots)
+        .flat_map(|series| make_dot_vertex(&series.values, series.color))
+        .collect()
+}
+
+fn distance_to_segment(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - x).powi(2) + (p.1 - y).powi(2)).sqrt()
+}
+
 fn create_pipeline(
     device: &wgpu::Device,
     swap_chain_format: wgpu::TextureFormat,
@@ -1450,10 +3577,59 @@ fn create_candle_pipeline(
     })
 }
 
-fn create_volume_pipeline(
+fn create_color_pipeline(
     device: &wgpu::Device,
     swap_chain_format: wgpu::TextureFormat,
-    fs_main: &str,
+    vs_main: &str,
+    topology: wgpu::PrimitiveTopology,
+    bind_group_layouts: &[&BindGroupLayout],
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    // Load the shaders from disk
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+    });
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Render Pipeline Layout"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&render_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some(vs_main),
+            buffers: &[create_color_vertex_buffer_layout()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main_color"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: swap_chain_format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+//K线和成交量柱的管线，颜色由顶点着色器按涨跌和样式给出
+fn create_bar_pipeline(
+    device: &wgpu::Device,
+    swap_chain_format: wgpu::TextureFormat,
+    vs_main: &str,
     topology: wgpu::PrimitiveTopology,
     camera_bind_group_layout: &BindGroupLayout,
 ) -> wgpu::RenderPipeline {
@@ -1472,13 +3648,13 @@ fn create_volume_pipeline(
         layout: Some(&render_pipeline_layout),
         vertex: wgpu::VertexState {
             module: &shader,
-            entry_point: Some("vs_main_volume"),
-            buffers: &[create_vertex_buffer_layout()],
+            entry_point: Some(vs_main),
+            buffers: &[create_bar_vertex_buffer_layout()],
             compilation_options: Default::default(),
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
-            entry_point: Some(fs_main),
+            entry_point: Some("fs_main_color"),
             compilation_options: Default::default(),
             targets: &[Some(swap_chain_format.into())],
         }),